@@ -0,0 +1,28 @@
+#![no_main]
+
+use adwlauncher::app_discovery::parse_desktop_entry;
+use adwlauncher::exec::{self, Context};
+use libfuzzer_sys::fuzz_target;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+/// Parsing reads from a file, so each input is written to the same one
+fn input_path() -> &'static PathBuf {
+    static PATH: OnceLock<PathBuf> = OnceLock::new();
+    PATH.get_or_init(|| {
+        std::env::temp_dir().join(format!("adwlauncher-fuzz-{}.desktop", std::process::id()))
+    })
+}
+
+fuzz_target!(|data: &[u8]| {
+    let path = input_path();
+    if std::fs::write(path, data).is_err() {
+        return;
+    }
+
+    // Malformed files must be rejected with an error, never panic
+    if let Ok(entry) = parse_desktop_entry(path) {
+        assert!(!entry.name.is_empty());
+        exec::arguments(&entry.exec, &Context::for_entry(&entry, Some(path)));
+    }
+});
@@ -0,0 +1,17 @@
+#![no_main]
+
+use adwlauncher::exec::{self, Context};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|exec: &str| {
+    // Without a name, icon or desktop file, field codes expand to nothing
+    let arguments = exec::arguments(exec, &Context::default());
+
+    // Splitting never invents text, every argument char comes from the Exec line
+    let total: usize = arguments.iter().map(|argument| argument.len()).sum();
+    assert!(total <= exec.len());
+    // Empty arguments only come from empty quotes
+    for argument in &arguments {
+        assert!(!argument.is_empty() || exec.contains('"'));
+    }
+});
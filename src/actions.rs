@@ -0,0 +1,86 @@
+use crate::app_discovery::{self, close_window, kill_window_process, uninstall_entry};
+use crate::config::{Config, Confirm};
+use crate::error::Result;
+use crate::types::Entry;
+use relm4::adw::prelude::*;
+use relm4::prelude::*;
+
+/// Actions that can't be undone and may ask for confirmation first
+#[derive(Debug, Clone)]
+pub enum DestructiveAction {
+    Power(Entry),
+    CloseWindow(Entry),
+    KillProcess(Entry),
+    Uninstall(Entry),
+}
+
+impl DestructiveAction {
+    pub fn needs_confirmation(&self, confirm: &Confirm) -> bool {
+        match self {
+            DestructiveAction::Power(_) => confirm.power,
+            DestructiveAction::CloseWindow(_) => confirm.close_window,
+            DestructiveAction::KillProcess(_) => confirm.kill_process,
+            DestructiveAction::Uninstall(_) => confirm.uninstall,
+        }
+    }
+
+    fn heading(&self) -> String {
+        match self {
+            DestructiveAction::Power(entry) => format!("{}?", entry.name),
+            DestructiveAction::CloseWindow(entry) => format!("Close “{}”?", entry.name),
+            DestructiveAction::KillProcess(entry) => format!("Kill “{}”?", entry.name),
+            DestructiveAction::Uninstall(entry) => format!("Uninstall {}?", entry.name),
+        }
+    }
+
+    fn body(&self) -> &'static str {
+        match self {
+            DestructiveAction::Power(_) => "Unsaved work in open applications may be lost.",
+            DestructiveAction::CloseWindow(_) => "Unsaved changes in this window may be lost.",
+            DestructiveAction::KillProcess(_) => {
+                "The application will be terminated immediately and unsaved work will be lost."
+            }
+            DestructiveAction::Uninstall(_) => "The application will be removed from the system.",
+        }
+    }
+
+    fn confirm_label(&self) -> &'static str {
+        match self {
+            DestructiveAction::Power(_) => "Continue",
+            DestructiveAction::CloseWindow(_) => "Close",
+            DestructiveAction::KillProcess(_) => "Kill",
+            DestructiveAction::Uninstall(_) => "Uninstall",
+        }
+    }
+
+    pub fn perform(&self, config: &Config) -> Result<()> {
+        match self {
+            DestructiveAction::Power(entry) => app_discovery::launch_entry(entry, config),
+            DestructiveAction::CloseWindow(entry) => close_window(&entry.exec),
+            DestructiveAction::KillProcess(entry) => kill_window_process(&entry.exec),
+            DestructiveAction::Uninstall(entry) => uninstall_entry(entry),
+        }
+    }
+}
+
+/// Ask the user to confirm `action`, calling `on_confirm` only if they do
+pub fn confirm(
+    parent: &impl IsA<gtk::Widget>,
+    action: &DestructiveAction,
+    on_confirm: impl Fn() + 'static,
+) {
+    let dialog = adw::AlertDialog::new(Some(&action.heading()), Some(action.body()));
+    dialog.add_response("cancel", "Cancel");
+    dialog.add_response("confirm", action.confirm_label());
+    dialog.set_response_appearance("confirm", adw::ResponseAppearance::Destructive);
+    dialog.set_default_response(Some("cancel"));
+    dialog.set_close_response("cancel");
+
+    dialog.connect_response(None, move |_, response| {
+        if response == "confirm" {
+            on_confirm();
+        }
+    });
+
+    dialog.present(Some(parent));
+}
@@ -1,81 +1,381 @@
+use crate::autostart;
 use crate::cache::{self, Cache, CacheData};
+use crate::categories;
+use crate::cli::Mode;
+use crate::compositor::{self, Backend, Events, Window, WindowEvent};
+use crate::config::{Config, WindowRules, WindowScope};
+use crate::environment;
 use crate::error::{LauncherError, Result};
+use crate::exec;
+use crate::games;
+use crate::history;
+use crate::hooks;
 use crate::icon;
+use crate::index;
+use crate::man;
+use crate::mime;
+use crate::network;
+use crate::notify;
+use crate::search::{self, SearchOptions};
 use crate::types::{Entry, OpenType};
+use crate::wine;
 use freedesktop_desktop_entry::DesktopEntry;
-use niri_ipc::{Action, Request, Response};
 use rayon::prelude::*;
-use std::path::PathBuf;
+use std::collections::{HashMap, HashSet};
+use std::os::unix::fs::PermissionsExt;
+use std::os::unix::process::CommandExt;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, mpsc};
+use std::time::{Duration, Instant, SystemTime};
 
-pub fn get_entries() -> Result<Vec<Entry>> {
+/// Sources of the entries shown without a mode. Their results are listed in this
+/// order, so equally scored matches keep their order however late a source answers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Provider {
+    Apps,
+    Windows,
+    Power,
+}
+
+impl Provider {
+    pub const ALL: [Provider; 3] = [Provider::Apps, Provider::Windows, Provider::Power];
+
+    pub fn entries(self, config: &Config) -> Result<Vec<Entry>> {
+        let mut entries = match self {
+            // Served from the cache when it is still valid
+            Provider::Apps => get_desktop_entries_cached(config)?,
+            // Always fresh
+            Provider::Windows => get_window_entries(config.window_scope)?,
+            Provider::Power => get_power_entries(),
+        };
+
+        entries.retain(|entry| config.kiosk.allows(entry));
+        Ok(entries)
+    }
+
+    /// The provider an entry shown without a mode came from
+    pub fn of(entry: &Entry) -> Self {
+        match entry.open_type {
+            OpenType::Window => Provider::Windows,
+            OpenType::Power => Provider::Power,
+            _ => Provider::Apps,
+        }
+    }
+
+    /// The weight of the provider's matches once merged. Apps lead, as launching
+    /// is what the launcher is for, then the windows they opened.
+    pub fn priority(self) -> f64 {
+        match self {
+            Provider::Apps => 1.0,
+            Provider::Windows => 0.9,
+            Provider::Power => 0.8,
+        }
+    }
+}
+
+/// Put the providers' matches for a typed query on one scale: scores are divided by
+/// the best one the query can reach and weighted by their provider's priority, so a
+/// strong match keeps its lead over a weak one from another provider, and an app
+/// leads its equally matching window. Stable, so equal scores keep the providers'
+/// order. Only for a typed query, the empty one's usage scores already compare.
+pub fn merge_ranked(ranked: &mut [(f64, Entry)], query: &str, options: SearchOptions) {
+    let best = search::best_score(query, options);
+    if best <= 0.0 {
+        return;
+    }
+
+    for (score, entry) in ranked.iter_mut() {
+        *score = *score / best * Provider::of(entry).priority();
+    }
+    ranked.sort_by(|a, b| b.0.total_cmp(&a.0));
+}
+
+/// Query every provider at once, calling `on_loaded` from each provider's thread
+/// as soon as it answers, so a slow one doesn't hold up the others
+pub fn query_providers(config: &Config, on_loaded: impl Fn(Provider, Result<Vec<Entry>>) + Sync) {
+    std::thread::scope(|scope| {
+        for provider in Provider::ALL {
+            let on_loaded = &on_loaded;
+            scope.spawn(move || on_loaded(provider, provider.entries(config)));
+        }
+    });
+}
+
+/// Every provider's entries, without those of providers that failed
+pub fn get_entries(config: &Config) -> Result<Vec<Entry>> {
+    let results = Mutex::new(Vec::with_capacity(Provider::ALL.len()));
+    query_providers(config, |provider, result| {
+        if let Ok(mut results) = results.lock() {
+            results.push((provider, result));
+        }
+    });
+
+    let mut results = results.into_inner().unwrap_or_default();
+    results.sort_by_key(|(provider, _)| *provider);
     let mut entries = vec![];
+    for (provider, result) in results {
+        // A stuck compositor costs the windows, not the apps
+        match result {
+            Ok(provider_entries) => entries.extend(provider_entries),
+            Err(e) => eprintln!("Failed to load {:?} entries: {:#}", provider, e),
+        }
+    }
+    Ok(entries)
+}
 
-    // Get desktop application entries (with caching)
-    entries.extend(get_desktop_entries_cached()?);
+/// Installs are noticed through the fingerprint, the TTL catches renamed games
+const GAMES_POLICY: index::Policy = index::Policy {
+    ttl: Duration::from_secs(10 * 60),
+    key: games::fingerprint,
+};
+/// mandb may update the database in place, which the TTL catches
+const MAN_POLICY: index::Policy = index::Policy {
+    ttl: Duration::from_secs(60 * 60),
+    key: man::fingerprint,
+};
 
-    // Get open windows (always fresh)
-    entries.extend(get_window_entries()?);
+/// All entries, or only those of the given kind
+pub fn get_entries_for_mode(mode: Option<Mode>, config: &Config) -> Result<Vec<Entry>> {
+    let mut entries = match mode {
+        Some(mode) if !config.kiosk.allows_mode(mode) => Ok(vec![]),
+        Some(Mode::Apps) => get_desktop_entries_cached(config),
+        Some(Mode::Windows) => get_window_entries(config.window_scope),
+        Some(Mode::Power) => Ok(get_power_entries()),
+        Some(Mode::Autostart) => autostart::get_autostart_entries(),
+        Some(Mode::MimeTypes) => Ok(mime::mime_entries(&get_desktop_entries_cached(config)?)),
+        Some(Mode::Games) => index::get_or_load(
+            "games",
+            index::Limits::from_config(config),
+            GAMES_POLICY,
+            games::get_game_entries,
+        ),
+        Some(Mode::Env) => Ok(environment::get_variable_entries()),
+        Some(Mode::Run) => Ok(history::get_history_entries()),
+        Some(Mode::Man) => index::get_or_load(
+            "man",
+            index::Limits::from_config(config),
+            MAN_POLICY,
+            || man::get_man_entries(config),
+        ),
+        Some(Mode::All) | None => get_entries(config),
+    }?;
 
+    entries.retain(|entry| config.kiosk.allows(entry));
+    if !config.network {
+        network::drop_remote_icons(&mut entries);
+    }
+    Ok(entries)
+}
+
+/// Rescan all application directories and resolve icons again regardless of the cache,
+/// e.g. after installing an app into a directory whose modification time doesn't change
+pub fn refresh_desktop_entries(config: &Config) -> Result<Vec<Entry>> {
+    icon::reload_icon_themes();
+    Cache::new(config.compress_cache)?.clear()?;
+    get_desktop_entries_cached(config)
+}
+
+/// Desktop application entries only, served from the cache when it is still valid
+pub fn get_desktop_entries_cached(config: &Config) -> Result<Vec<Entry>> {
+    let mut entries = load_desktop_entries(config)?;
+    // The cache keeps them, so they show again once the network is allowed
+    if !config.network {
+        network::drop_remote_icons(&mut entries);
+    }
     Ok(entries)
 }
 
-fn get_desktop_entries_cached() -> Result<Vec<Entry>> {
-    let cache = Cache::new()?;
-    let app_dirs = cache::get_app_directories();
+fn load_desktop_entries(config: &Config) -> Result<Vec<Entry>> {
+    let cache = Cache::new(config.compress_cache)?;
+    let app_dirs = cache::get_app_directories(&config.app_directories);
+    let mut watched_dirs = app_dirs.clone();
+    if config.wine_apps {
+        watched_dirs.extend(wine::watched_directories()?);
+    }
+
+    // Try to load from cache, an unreadable cache is simply rebuilt
+    let cache_data = cache.load().unwrap_or_else(|e| {
+        eprintln!("Failed to load cache: {:#}", e);
+        CacheData::new()
+    });
 
-    // Try to load from cache
-    let cache_data = cache.load()?;
+    let budget = Duration::from_millis(config.scan_timeout_ms);
+
+    // Even a stat can hang on a network mount, so directories are checked with a deadline.
+    // Desktop files may sit in subdirectories of the application directories too.
+    let nested: HashSet<PathBuf> = app_dirs.iter().cloned().collect();
+    let timestamps = scan_directories(&watched_dirs, budget, move |dir| {
+        if nested.contains(dir) {
+            cache::get_tree_mtime(dir).ok()
+        } else {
+            cache::get_dir_mtime(dir).ok()
+        }
+    });
+    if timestamps.iter().any(Option::is_none) && !cache_data.entries.is_empty() {
+        eprintln!("Some application directories didn't respond, using cached entries");
+        return Ok(cache_data.entries);
+    }
+    let timestamps: HashMap<PathBuf, SystemTime> = watched_dirs
+        .iter()
+        .zip(timestamps)
+        .filter_map(|(dir, mtime)| Some((dir.clone(), mtime.flatten()?)))
+        .collect();
 
     // Check if cache is valid
-    if cache.is_valid(&cache_data, &app_dirs) && !cache_data.entries.is_empty() {
+    if cache.is_valid(&cache_data, &timestamps) && !cache_data.entries.is_empty() {
         return Ok(cache_data.entries);
     }
 
     // Cache is invalid or empty, rebuild it
-    let entries = get_desktop_entries(&app_dirs)?;
+    let (mut entries, complete) = get_desktop_entries(&app_dirs, budget);
+    if config.wine_apps {
+        // Wine's own menu entries are desktop files in the applications directory
+        let ids: HashSet<String> = entries.iter().map(|entry| entry.id.clone()).collect();
+        entries.extend(
+            wine::get_wine_entries()?
+                .into_iter()
+                .filter(|entry| !ids.contains(&entry.id)),
+        );
+    }
+
+    // A partial scan must not end up in the cache, or the missing apps would stay missing
+    if !complete {
+        eprintln!("Some application directories timed out, their apps are missing");
+        notify::notify(
+            "Some applications are missing",
+            "Scanning an application directory took too long",
+        );
+        return Ok(entries);
+    }
 
     // Save to cache
     let new_cache_data = CacheData {
         entries: entries.clone(),
-        directory_timestamps: cache::collect_directory_timestamps(&app_dirs),
+        directory_timestamps: timestamps,
+        icon_themes: icon::icon_themes(),
+        ..CacheData::new()
     };
 
     if let Err(e) = cache.save(&new_cache_data) {
-        eprintln!("Failed to save cache: {}", e);
+        eprintln!("Failed to save cache: {:#}", e);
+        notify::notify("Failed to save application cache", &e.to_string());
     }
 
     Ok(entries)
 }
 
-fn get_desktop_entries(app_dirs: &[PathBuf]) -> Result<Vec<Entry>> {
-    let mut entries = vec![];
+/// Scan all directories in parallel, returning the entries and whether every directory
+/// finished within the budget
+fn get_desktop_entries(app_dirs: &[PathBuf], budget: Duration) -> (Vec<Entry>, bool) {
+    let results = scan_directories(app_dirs, budget, scan_directory);
+    let complete = results.iter().all(Option::is_some);
 
-    for app_dir in app_dirs {
-        let dir = match std::fs::read_dir(app_dir) {
-            Ok(dir) => dir,
-            Err(_) => continue, // Skip if the directory doesn't exist
-        };
+    // Directories keep their order, so a desktop file ID found again in a later one
+    // is overridden, e.g. a system app by the user's copy of it
+    let mut seen = HashSet::new();
+    let entries = results
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter(|(id, _)| seen.insert(id.clone()))
+        .filter_map(|(_, entry)| entry)
+        .collect();
+    (entries, complete)
+}
 
-        let new_entries: Vec<Entry> = dir
-            .collect::<Vec<_>>()
-            .into_par_iter()
-            .filter_map(|file| {
-                let file = file.ok()?;
-                let path = file.path();
-                parse_desktop_entry(&path).ok()
-            })
-            .collect();
+/// The desktop files in a directory and its subdirectories by their desktop file ID,
+/// None for hidden ones. Files that fail to parse don't count, so they can't override
+/// a working one.
+fn scan_directory(app_dir: &Path) -> Vec<(String, Option<Entry>)> {
+    let mut files = vec![];
+    find_desktop_files(app_dir, &mut files);
+
+    files
+        .into_par_iter()
+        .filter_map(|path| {
+            let entry = read_desktop_entry(&path).ok()?;
+            // The spec names nested files by their path, e.g. kde/foo.desktop is kde-foo.desktop
+            let id = path
+                .strip_prefix(app_dir)
+                .ok()?
+                .to_string_lossy()
+                .replace('/', "-");
+            let entry = entry.map(|entry| Entry {
+                id: id.clone(),
+                ..entry
+            });
+            Some((id, entry))
+        })
+        .collect()
+}
+
+/// Every desktop file below `dir`. Symlinked directories aren't followed, so a loop
+/// can't run away; symlinked files are, many packages link their desktop files.
+fn find_desktop_files(dir: &Path, files: &mut Vec<PathBuf>) {
+    // Skip directories that don't exist
+    let Ok(read) = std::fs::read_dir(dir) else {
+        return;
+    };
+
+    for file in read.flatten() {
+        match file.file_type() {
+            Ok(file_type) if file_type.is_dir() => find_desktop_files(&file.path(), files),
+            Ok(_) if file.path().extension().is_some_and(|ext| ext == "desktop") => {
+                files.push(file.path())
+            }
+            _ => {}
+        }
+    }
+}
 
-        entries.extend(new_entries);
+/// Run `scan` on each directory in its own thread and wait at most `budget` for all of them.
+/// Directories that don't finish in time, e.g. a hung NFS mount, give None; their threads
+/// are left behind to finish or hang on their own.
+fn scan_directories<T, F>(dirs: &[PathBuf], budget: Duration, scan: F) -> Vec<Option<T>>
+where
+    T: Send + 'static,
+    F: Fn(&Path) -> T + Send + Sync + 'static,
+{
+    let deadline = Instant::now() + budget;
+    let scan = Arc::new(scan);
+    let (sender, receiver) = mpsc::channel();
+
+    for (index, dir) in dirs.iter().enumerate() {
+        let scan = scan.clone();
+        let sender = sender.clone();
+        let dir = dir.clone();
+        std::thread::spawn(move || {
+            // The receiver is gone once the deadline passed
+            let _ = sender.send((index, scan(&dir)));
+        });
     }
+    drop(sender);
 
-    Ok(entries)
+    let mut results: Vec<Option<T>> = dirs.iter().map(|_| None).collect();
+    while let Some(remaining) = deadline.checked_duration_since(Instant::now()) {
+        match receiver.recv_timeout(remaining) {
+            Ok((index, result)) => results[index] = Some(result),
+            // Timed out, or every directory has answered
+            Err(_) => break,
+        }
+    }
+
+    results
+}
+
+pub fn parse_desktop_entry(path: &PathBuf) -> Result<Entry> {
+    read_desktop_entry(path)?.ok_or_else(|| LauncherError::DesktopEntry("Hidden entry".to_string()))
 }
 
-fn parse_desktop_entry(path: &PathBuf) -> Result<Entry> {
+/// None for `Hidden=true`, which the spec treats as the app being deleted
+fn read_desktop_entry(path: &PathBuf) -> Result<Option<Entry>> {
     let desktop_file = DesktopEntry::from_path(path, None::<&[&str]>)
         .map_err(|e| LauncherError::DesktopEntry(format!("Failed to parse desktop file: {}", e)))?;
 
+    if desktop_file.hidden() {
+        return Ok(None);
+    }
+
     let name = desktop_file
         .name(&[""])
         .ok_or_else(|| LauncherError::DesktopEntry("Missing name field".to_string()))?
@@ -85,112 +385,806 @@ fn parse_desktop_entry(path: &PathBuf) -> Result<Entry> {
         return Err(LauncherError::DesktopEntry("Empty name field".to_string()));
     }
 
-    // Resolve icon path properly
-    let icon_name = desktop_file.icon().unwrap_or(icon::get_fallback_icon());
-    let icon =
-        icon::resolve_icon_path(icon_name).unwrap_or_else(|| icon::get_fallback_icon().to_string());
+    // Resolve icon path properly, entries without one get a letter avatar
+    let icon = desktop_file
+        .icon()
+        .and_then(icon::resolve_icon_path)
+        .unwrap_or_default();
+
+    let id = path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
 
-    Ok(Entry {
+    Ok(Some(Entry {
+        id,
         name,
         exec: desktop_file.exec().unwrap_or_default().to_string(),
         icon,
+        startup_notify: desktop_file.startup_notify(),
+        mime_types: desktop_file
+            .mime_type()
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|mime| !mime.is_empty())
+            .map(|mime| mime.to_string())
+            .collect(),
+        category: categories::main_category(desktop_file.categories().unwrap_or_default()),
+        keywords: desktop_file
+            .keywords(&[""])
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|keyword| !keyword.is_empty())
+            .map(|keyword| keyword.to_string())
+            .collect(),
+        generic_name: desktop_file
+            .generic_name(&[""])
+            .map(|name| name.to_string())
+            .unwrap_or_default(),
+        comment: desktop_file
+            .comment(&[""])
+            .map(|comment| comment.to_string())
+            .unwrap_or_default(),
         open_type: if desktop_file.terminal() {
             OpenType::Terminal
         } else {
             OpenType::Graphical
         },
-    })
+        desktop_file: Some(path.clone()),
+    }))
 }
 
-fn get_window_entries() -> Result<Vec<Entry>> {
-    let mut entries = vec![];
+/// Open windows to switch to, only those on the focused workspace or output
+/// when `scope` says so. There are none outside niri and Hyprland.
+pub fn get_window_entries(scope: WindowScope) -> Result<Vec<Entry>> {
+    let Some(backend) = compositor::backend() else {
+        return Ok(vec![]);
+    };
+
+    Ok(backend
+        .windows(scope)?
+        .into_iter()
+        .filter(|window| !window.title.is_empty() && !window.app_id.is_empty())
+        .map(|window| window_entry(window.app_id, window.title, window.id))
+        .collect())
+}
+
+/// The entry for a window, `exec` is how the compositor addresses it
+fn window_entry(app_id: String, name: String, exec: String) -> Entry {
+    // Resolve window icon
+    let icon = icon::resolve_icon_path(&app_id).unwrap_or_else(|| app_id.clone());
+
+    Entry {
+        id: app_id,
+        name,
+        exec,
+        icon,
+        open_type: OpenType::Window,
+        startup_notify: false,
+        mime_types: vec![],
+        category: String::new(),
+        keywords: vec![],
+        generic_name: String::new(),
+        comment: String::new(),
+        desktop_file: None,
+    }
+}
 
-    let mut soc = niri_ipc::socket::Socket::connect()
-        .map_err(|e| LauncherError::NiriConnection(format!("Failed to connect: {}", e)))?;
+/// Session actions offered alongside applications and windows
+pub fn get_power_entries() -> Vec<Entry> {
+    [
+        ("poweroff", "Power Off", "system-shutdown-symbolic"),
+        ("reboot", "Restart", "system-reboot-symbolic"),
+        ("suspend", "Suspend", "weather-clear-night-symbolic"),
+        ("logout", "Log Out", "system-log-out-symbolic"),
+    ]
+    .into_iter()
+    .map(|(exec, name, icon)| Entry {
+        id: exec.to_string(),
+        name: name.to_string(),
+        exec: exec.to_string(),
+        icon: icon.to_string(),
+        open_type: OpenType::Power,
+        startup_notify: false,
+        mime_types: vec![],
+        category: String::new(),
+        keywords: vec![],
+        generic_name: String::new(),
+        comment: String::new(),
+        desktop_file: None,
+    })
+    .collect()
+}
 
-    let reply = soc
-        .send(Request::Windows)
-        .map_err(|e| LauncherError::NiriRequest(format!("Failed to send request: {}", e)))?;
+pub fn launch_entry(entry: &Entry, config: &Config) -> Result<()> {
+    let is_app = matches!(entry.open_type, OpenType::Graphical | OpenType::Terminal);
+    let app_override = config.apps.get(&entry.id).filter(|_| is_app);
 
-    let response = reply.map_err(|e| LauncherError::NiriRequest(format!("Niri error: {}", e)))?;
+    let overridden;
+    let entry = match app_override {
+        Some(app_override) => {
+            overridden = app_override.apply(entry);
+            &overridden
+        }
+        None => entry,
+    };
 
-    let windows = match response {
-        Response::Windows(windows) => windows,
-        _ => {
-            return Err(LauncherError::NiriRequest(
-                "Unexpected response type".to_string(),
-            ));
+    let (mut command, description) = match entry.open_type {
+        OpenType::Terminal => {
+            let mut command = config.terminal_command();
+            if entry.id.ends_with(".desktop") {
+                let desktop_file = entry_file(entry);
+                let context = exec::Context::for_entry(entry, desktop_file.as_deref());
+                command.extend(exec::arguments(&entry.exec, &context));
+            } else {
+                // Shell history and man pages are shell lines, run as typed
+                command.push(entry.exec.clone());
+            }
+            (command, "spawn terminal")
+        }
+        OpenType::Graphical => {
+            let desktop_file = entry_file(entry);
+            let context = exec::Context::for_entry(entry, desktop_file.as_deref());
+            (exec::arguments(&entry.exec, &context), "spawn application")
+        }
+        OpenType::Window => return require_backend("switch windows")?.focus_window(&entry.exec),
+        OpenType::Power if entry.exec == "logout" => {
+            return require_backend("log out")?.log_out();
+        }
+        OpenType::Power => (
+            vec!["systemctl".to_string(), entry.exec.clone()],
+            "run power action",
+        ),
+        OpenType::Text | OpenType::Variable => {
+            // Plain text choices are "launched" by handing them back to the caller
+            println!("{}", entry.exec);
+            return Ok(());
+        }
+        OpenType::MimeType => {
+            return Err(LauncherError::Launch(format!(
+                "{} is a MIME type, pick an application for it instead",
+                entry.exec
+            )));
+        }
+        OpenType::Help => {
+            return Err(LauncherError::Launch(format!(
+                "{} is a help entry, not something to launch",
+                entry.name
+            )));
+        }
+        OpenType::Folder => {
+            return Err(LauncherError::Launch(format!(
+                "{} is a folder, launch one of its apps instead",
+                entry.name
+            )));
+        }
+        OpenType::Category => {
+            return Err(LauncherError::Launch(format!(
+                "{} is a category, launch one of its apps instead",
+                entry.exec
+            )));
+        }
+        OpenType::Autostart => {
+            let enabled = autostart::toggle(&entry.id)?;
+            println!(
+                "{} {}",
+                entry.id,
+                if enabled { "enabled" } else { "disabled" }
+            );
+            return Ok(());
         }
     };
 
-    for window in windows {
-        let name = window.title.unwrap_or_default();
-        if name.is_empty() {
-            continue;
+    check_spawnable(&command)?;
+
+    if is_app {
+        let extra_env = app_override.map(|app_override| &app_override.env);
+        command = config.env.wrap_command(&entry.id, extra_env, command);
+        hooks::run(config.hooks.pre_launch.as_deref(), entry);
+    }
+
+    // New windows open on the focused workspace, so switch there first
+    let workspace = app_override.and_then(|app_override| app_override.workspace.as_deref());
+    if let Some(workspace) = workspace
+        && let Some(backend) = compositor::backend()
+    {
+        match backend.focus_workspace(workspace) {
+            // The app still starts, only on the current workspace
+            Err(e) if e.is_compositor_timeout() => {
+                eprintln!("Failed to switch workspace: {:#}", e)
+            }
+            switched => switched?,
         }
+    }
+
+    spawn(&command, description)?;
+
+    if is_app {
+        hooks::run(config.hooks.post_launch.as_deref(), entry);
+    }
+
+    Ok(())
+}
+
+/// The window rules configured for the app, if it has any
+pub fn window_rules<'a>(entry: &Entry, config: &'a Config) -> Option<&'a WindowRules> {
+    config
+        .apps
+        .get(&entry.id)
+        .map(|app_override| &app_override.window)
+        .filter(|rules| !rules.is_empty())
+}
+
+/// Apply an app's window rules to the window it opened
+pub fn apply_window_rules(id: &str, rules: &WindowRules) -> Result<()> {
+    require_backend("apply window rules")?.apply_window_rules(id, rules)
+}
+
+/// The compositor's backend, for what only works inside one.
+/// `action` completes "Can't …", e.g. "close windows".
+fn require_backend(action: &str) -> Result<Arc<dyn Backend>> {
+    compositor::backend()
+        .ok_or_else(|| LauncherError::Launch(format!("Can't {} outside niri and Hyprland", action)))
+}
+
+/// niri reports success for any spawn, even when the program doesn't exist,
+/// so check up front that there is something to run
+pub fn check_spawnable(command: &[String]) -> Result<()> {
+    let program = command
+        .first()
+        .ok_or_else(|| LauncherError::Launch("Empty command".to_string()))?;
+
+    let found = if program.contains('/') {
+        is_executable(Path::new(program))
+    } else {
+        std::env::var_os("PATH")
+            .map(|path| std::env::split_paths(&path).any(|dir| is_executable(&dir.join(program))))
+            .unwrap_or(false)
+    };
+
+    if found {
+        Ok(())
+    } else {
+        Err(LauncherError::ExecNotFound(program.clone()))
+    }
+}
+
+fn is_executable(path: &Path) -> bool {
+    path.metadata()
+        .map(|metadata| metadata.is_file() && metadata.permissions().mode() & 0o111 != 0)
+        .unwrap_or(false)
+}
+
+/// Watches the compositor for windows that weren't open when it started
+pub struct WindowWatcher {
+    events: Box<dyn Events>,
+}
 
-        let app_id = match window.app_id {
-            Some(id) => id,
-            None => continue,
+impl WindowWatcher {
+    /// Subscribe to the compositor's window events, None outside niri and Hyprland.
+    /// Start it before launching, so a fast app's window can't be mistaken for an old one.
+    pub fn start() -> Result<Option<Self>> {
+        let Some(backend) = compositor::backend() else {
+            return Ok(None);
         };
+        Ok(Some(Self {
+            events: backend.events()?,
+        }))
+    }
 
-        // Resolve window icon
-        let icon = icon::resolve_icon_path(&app_id).unwrap_or_else(|| app_id.clone());
+    /// Block until a new window of the launched app opens or the timeout passes,
+    /// returning its ID. Windows of other apps opening meanwhile, e.g. a notification,
+    /// are passed over, see `opened_by`.
+    pub fn wait(
+        mut self,
+        timeout: Duration,
+        launched: impl Fn(&Window) -> bool,
+    ) -> Result<Option<String>> {
+        let deadline = Instant::now() + timeout;
 
-        entries.push(Entry {
-            name,
-            exec: window.id.to_string(),
-            icon,
-            open_type: OpenType::Window,
-        });
+        while Instant::now() < deadline {
+            let event = self
+                .events
+                .next(Some(deadline.saturating_duration_since(Instant::now())))?;
+
+            match event {
+                Some(WindowEvent::Opened(window)) if launched(&window) => {
+                    return Ok(Some(window.id));
+                }
+                Some(WindowEvent::Opened(_) | WindowEvent::Closed(_)) => {}
+                None => break,
+            }
+        }
+
+        Ok(None)
     }
+}
 
-    Ok(entries)
+/// Whether a window belongs to the launched entry. Most apps name their windows
+/// after their desktop file or their program, some only in lowercase, and terminal
+/// apps open a window of the terminal.
+pub fn opened_by(entry: &Entry, window: &Window, config: &Config) -> bool {
+    if window.app_id.is_empty() {
+        return false;
+    }
+
+    let program = match entry.open_type {
+        OpenType::Terminal => config.terminal_command().into_iter().next(),
+        _ => entry.exec.split_whitespace().next().map(str::to_string),
+    };
+    let program = program
+        .as_deref()
+        .and_then(|program| Path::new(program).file_name())
+        .and_then(|name| name.to_str());
+
+    entry.open_type != OpenType::Terminal
+        && entry
+            .id
+            .strip_suffix(".desktop")
+            .is_some_and(|id| id.eq_ignore_ascii_case(&window.app_id))
+        || program.is_some_and(|program| program.eq_ignore_ascii_case(&window.app_id))
+}
+
+/// Close a window by the ID or address its entry's exec holds
+pub fn close_window(id: &str) -> Result<()> {
+    require_backend("close windows")?.close_window(id)
+}
+
+/// Kill the process owning a window, for clients that ignore close requests
+pub fn kill_window_process(id: &str) -> Result<()> {
+    let pid = require_backend("find a window's process")?
+        .window_pid(id)?
+        .ok_or_else(|| LauncherError::Launch(format!("No process found for window {}", id)))?;
+
+    spawn(&["kill".to_string(), pid.to_string()], "kill process")
+}
+
+/// Uninstall an application; only Flatpak apps can be removed without root
+pub fn uninstall_entry(entry: &Entry) -> Result<()> {
+    let app_id = flatpak_app_id(entry).ok_or_else(|| {
+        LauncherError::DesktopEntry(format!("{} is not a Flatpak application", entry.name))
+    })?;
+
+    spawn(
+        &[
+            "flatpak".to_string(),
+            "uninstall".to_string(),
+            "--noninteractive".to_string(),
+            app_id.to_string(),
+        ],
+        "uninstall application",
+    )
 }
 
-pub fn launch_entry(entry: &Entry) -> Result<()> {
-    let mut soc = niri_ipc::socket::Socket::connect()
-        .map_err(|e| LauncherError::NiriConnection(format!("Failed to connect: {}", e)))?;
+/// Flatpak exports desktop files named after the app ID and runs them via `flatpak run`
+pub fn flatpak_app_id(entry: &Entry) -> Option<&str> {
+    if !entry.exec.contains("flatpak run") {
+        return None;
+    }
+    entry.id.strip_suffix(".desktop")
+}
 
+/// The file a result stands for when dragged onto a dock, editor or file manager:
+/// an app's desktop file as found when scanning, or a text result naming an existing file
+pub fn entry_file(entry: &Entry) -> Option<PathBuf> {
     match entry.open_type {
-        OpenType::Terminal => {
-            let reply = soc
-                .send(Request::Action(Action::Spawn {
-                    command: vec!["ghostty".to_string(), "-c".to_string(), entry.exec.clone()],
-                }))
-                .map_err(|e| {
-                    LauncherError::NiriRequest(format!("Failed to spawn terminal: {}", e))
-                })?;
+        OpenType::Graphical | OpenType::Terminal => entry.desktop_file.clone(),
+        OpenType::Text => {
+            let path = PathBuf::from(&entry.exec);
+            (path.is_absolute() && path.exists()).then_some(path)
+        }
+        _ => None,
+    }
+}
+
+/// Type text into whichever window has focus once the launcher is gone, through wtype
+pub fn type_text(text: &str) -> Result<()> {
+    let command = [
+        "wtype".to_string(),
+        // Give the compositor time to hand focus back to the previous window
+        "-s".to_string(),
+        "150".to_string(),
+        "--".to_string(),
+        text.to_string(),
+    ];
+    check_spawnable(&command)?;
+    spawn_directly(&command)
+}
 
-            reply.map_err(|e| LauncherError::NiriRequest(format!("Niri error: {}", e)))?;
+/// Start a program through the compositor, so it isn't the launcher's child and gets
+/// the session's environment, or directly outside niri and Hyprland.
+/// `description` completes "Failed to …" in errors, e.g. "spawn application".
+fn spawn(command: &[String], description: &str) -> Result<()> {
+    let Some(backend) = compositor::backend() else {
+        return spawn_directly(command);
+    };
+    match backend.spawn(command, description) {
+        // The compositor may still get to it and start a second copy, which is
+        // less confusing than nothing starting at all
+        Err(e) if e.is_compositor_timeout() => {
+            eprintln!("Failed to {}, starting it directly: {:#}", description, e);
+            spawn_directly(command)
         }
-        OpenType::Graphical => {
-            let reply = soc
-                .send(Request::Action(Action::Spawn {
-                    command: entry
-                        .exec
-                        .split_whitespace()
-                        .map(|s| s.to_string())
-                        .filter(|s| !s.contains('%'))
-                        .collect(),
-                }))
-                .map_err(|e| {
-                    LauncherError::NiriRequest(format!("Failed to spawn application: {}", e))
-                })?;
+        sent => sent,
+    }
+}
+
+/// Start a program without the compositor, in its own process group so it outlives the launcher
+fn spawn_directly(command: &[String]) -> Result<()> {
+    let (program, args) = command
+        .split_first()
+        .ok_or_else(|| LauncherError::Launch("Empty command".to_string()))?;
+
+    let mut child = std::process::Command::new(program)
+        .args(args)
+        .process_group(0)
+        .spawn()
+        .map_err(|e| LauncherError::Launch(format!("Failed to start {}: {}", program, e)))?;
 
-            reply.map_err(|e| LauncherError::NiriRequest(format!("Niri error: {}", e)))?;
+    // Reap it once it exits, the service would collect zombies otherwise
+    std::thread::spawn(move || {
+        let _ = child.wait();
+    });
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compositor::mock::{self, Call, MockCompositor};
+    use crate::config::{AppOverride, Env, EnvOverrides};
+    use crate::usage::UsageTracker;
+
+    fn app(id: &str, exec: &str) -> Entry {
+        Entry {
+            id: id.to_string(),
+            name: id.to_string(),
+            exec: exec.to_string(),
+            ..Default::default()
         }
-        OpenType::Window => {
-            let id = entry.exec.parse::<u64>()?;
-            let reply = soc
-                .send(Request::Action(Action::FocusWindow { id }))
-                .map_err(|e| {
-                    LauncherError::NiriRequest(format!("Failed to focus window: {}", e))
-                })?;
+    }
 
-            reply.map_err(|e| LauncherError::NiriRequest(format!("Niri error: {}", e)))?;
+    /// The default config without environment changes, which wrap every command in `env`
+    fn plain_config() -> Config {
+        Config {
+            env: Env {
+                global: EnvOverrides::default(),
+                apps: Default::default(),
+            },
+            ..Default::default()
         }
     }
 
-    Ok(())
+    /// A temporary applications directory holding these desktop files
+    fn app_dir(name: &str, files: &[(&str, &str)]) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("adwlauncher-apps-{}-{}", std::process::id(), name));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        for (path, contents) in files {
+            let path = dir.join(path);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, contents).unwrap();
+        }
+        dir
+    }
+
+    #[test]
+    fn earlier_directories_override_the_same_desktop_file_id() {
+        let user = app_dir(
+            "user",
+            &[
+                (
+                    "editor.desktop",
+                    "[Desktop Entry]\nType=Application\nName=My Editor\nExec=editor --mine\n",
+                ),
+                (
+                    "ads.desktop",
+                    "[Desktop Entry]\nType=Application\nName=Ads\nHidden=true\n",
+                ),
+                ("broken.desktop", "not a desktop file"),
+            ],
+        );
+        let system = app_dir(
+            "system",
+            &[
+                (
+                    "editor.desktop",
+                    "[Desktop Entry]\nType=Application\nName=Editor\nExec=editor\n",
+                ),
+                (
+                    "ads.desktop",
+                    "[Desktop Entry]\nType=Application\nName=Ads\nExec=ads\n",
+                ),
+                (
+                    "broken.desktop",
+                    "[Desktop Entry]\nType=Application\nName=Broken\nExec=broken\n",
+                ),
+            ],
+        );
+
+        let (mut entries, complete) =
+            get_desktop_entries(&[user.clone(), system.clone()], Duration::from_secs(5));
+        entries.sort_by(|a, b| a.id.cmp(&b.id));
+        let _ = std::fs::remove_dir_all(user);
+        let _ = std::fs::remove_dir_all(system);
+
+        assert!(complete);
+        // The hidden copy removes the app, the unreadable one doesn't
+        let found: Vec<(&str, &str)> = entries
+            .iter()
+            .map(|entry| (entry.id.as_str(), entry.exec.as_str()))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                ("broken.desktop", "broken"),
+                ("editor.desktop", "editor --mine")
+            ]
+        );
+    }
+
+    #[test]
+    fn nested_desktop_files_are_named_by_their_path() {
+        let dir = app_dir(
+            "nested",
+            &[
+                (
+                    "foot.desktop",
+                    "[Desktop Entry]\nType=Application\nName=Foot\nExec=foot\n",
+                ),
+                (
+                    "kde/konsole.desktop",
+                    "[Desktop Entry]\nType=Application\nName=Konsole\nExec=konsole\n",
+                ),
+                ("mimeinfo.cache", "[MIME Cache]\n"),
+            ],
+        );
+
+        let (mut entries, _) =
+            get_desktop_entries(std::slice::from_ref(&dir), Duration::from_secs(5));
+        entries.sort_by(|a, b| a.id.cmp(&b.id));
+        let ids: Vec<&str> = entries.iter().map(|entry| entry.id.as_str()).collect();
+        let _ = std::fs::remove_dir_all(&dir);
+
+        assert_eq!(ids, ["foot.desktop", "kde-konsole.desktop"]);
+        assert_eq!(
+            entries[1].desktop_file,
+            Some(dir.join("kde/konsole.desktop"))
+        );
+    }
+
+    #[test]
+    fn kiosk_mode_only_lists_desktop_file_apps() {
+        let mut config = Config::default();
+        config.kiosk.enabled = true;
+
+        let history = Entry {
+            id: "history-rm -rf ~/lab".to_string(),
+            open_type: OpenType::Terminal,
+            ..app("rm -rf ~/lab", "rm -rf ~/lab")
+        };
+        assert!(!config.kiosk.allows(&history));
+        assert!(
+            !config
+                .kiosk
+                .allows(&app("steam-570", "steam steam://rungameid/570"))
+        );
+        assert!(
+            config
+                .kiosk
+                .allows(&app("org.gnome.TextEditor.desktop", "gnome-text-editor"))
+        );
+
+        for mode in [Mode::Run, Mode::Env, Mode::Man] {
+            assert!(!config.kiosk.allows_mode(mode));
+            assert!(
+                get_entries_for_mode(Some(mode), &config)
+                    .unwrap()
+                    .is_empty()
+            );
+        }
+        assert!(config.kiosk.allows_mode(Mode::Apps));
+
+        config.kiosk.allowed_apps = vec!["org.gnome.Calculator.desktop".to_string()];
+        assert!(
+            !config
+                .kiosk
+                .allows(&app("org.gnome.TextEditor.desktop", "gnome-text-editor"))
+        );
+    }
+
+    #[test]
+    fn windows_are_only_listed_inside_a_compositor() {
+        let config = Config::default();
+        let windows = || get_entries_for_mode(Some(Mode::Windows), &config).unwrap();
+        assert!(windows().is_empty());
+
+        let _compositor = MockCompositor::start(vec![mock::window(1, "foot", "Terminal")]);
+        assert_eq!(windows().len(), 1);
+    }
+
+    #[test]
+    fn windows_without_a_title_or_app_id_are_skipped() {
+        let _compositor = MockCompositor::start(vec![
+            mock::window(1, "org.gnome.Nautilus", "Home"),
+            mock::window(2, "foot", ""),
+            mock::window(3, "", "Untitled"),
+        ]);
+
+        let entries = get_window_entries(WindowScope::All).unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].id, "org.gnome.Nautilus");
+        assert_eq!(entries[0].exec, "1");
+        assert_eq!(entries[0].open_type, OpenType::Window);
+    }
+
+    #[test]
+    fn searching_and_activating_a_window_focuses_it() {
+        let compositor = MockCompositor::start(vec![
+            mock::window(7, "firefox", "Mozilla Firefox"),
+            mock::window(8, "foot", "Terminal"),
+        ]);
+
+        let entries = get_window_entries(WindowScope::All).unwrap();
+        let ranked = search::rank_entries(
+            &entries,
+            "term",
+            &UsageTracker::new(),
+            SearchOptions::default(),
+        );
+        let (_, selected) = ranked.first().unwrap();
+        launch_entry(selected, &Config::default()).unwrap();
+
+        assert_eq!(compositor.calls(), [Call::FocusWindow("8".to_string())]);
+    }
+
+    #[test]
+    fn providers_are_merged_on_one_scale() {
+        let of = |id: &str, open_type: OpenType| Entry {
+            open_type,
+            ..app(id, "")
+        };
+        let options = SearchOptions::default();
+        let best = search::best_score("firefox", options);
+        // The only, and barely, matching session action stays behind the apps
+        let mut ranked = vec![
+            (best * 0.6, of("firefox", OpenType::Window)),
+            (best * 0.6, app("firefox.desktop", "firefox")),
+            (best * 0.4, app("firefox-esr.desktop", "firefox-esr")),
+            (best * 0.1, of("logout", OpenType::Power)),
+        ];
+
+        merge_ranked(&mut ranked, "firefox", options);
+        let merged: Vec<&str> = ranked.iter().map(|(_, entry)| entry.id.as_str()).collect();
+        assert_eq!(
+            merged,
+            [
+                "firefox.desktop",
+                "firefox",
+                "firefox-esr.desktop",
+                "logout"
+            ]
+        );
+        assert!(ranked.iter().all(|(score, _)| *score <= 1.0));
+
+        let ranked = search::rank_entries(
+            &[app("Firefox", "firefox")],
+            "firefox",
+            &UsageTracker::new(),
+            options,
+        );
+        assert!(ranked[0].0 <= best);
+    }
+
+    #[test]
+    fn apps_are_spawned_without_field_codes() {
+        let compositor = MockCompositor::start(vec![]);
+
+        launch_entry(&app("true.desktop", "true --flag %U"), &plain_config()).unwrap();
+
+        assert!(matches!(
+            compositor.calls().as_slice(),
+            [Call::Spawn(command)] if command == &["true", "--flag"]
+        ));
+    }
+
+    #[test]
+    fn terminal_apps_are_spawned_without_field_codes() {
+        let compositor = MockCompositor::start(vec![]);
+        let config = Config {
+            terminal: "sh -c".to_string(),
+            ..plain_config()
+        };
+
+        let htop = Entry {
+            open_type: OpenType::Terminal,
+            ..app("htop.desktop", "htop --tree %F")
+        };
+        let history = Entry {
+            open_type: OpenType::Terminal,
+            ..app("history-ls %F | wc -l", "ls %F | wc -l")
+        };
+        launch_entry(&htop, &config).unwrap();
+        launch_entry(&history, &config).unwrap();
+
+        assert!(matches!(
+            compositor.calls().as_slice(),
+            [Call::Spawn(app), Call::Spawn(line)]
+                if app == &["sh", "-c", "htop", "--tree"] && line == &["sh", "-c", "ls %F | wc -l"]
+        ));
+    }
+
+    #[test]
+    fn workspace_overrides_switch_before_spawning() {
+        let compositor = MockCompositor::start(vec![]);
+        let mut config = Config::default();
+        config.apps.insert(
+            "true.desktop".to_string(),
+            AppOverride {
+                workspace: Some("games".to_string()),
+                ..Default::default()
+            },
+        );
+
+        launch_entry(&app("true.desktop", "true"), &config).unwrap();
+
+        assert!(matches!(
+            compositor.calls().as_slice(),
+            [Call::FocusWorkspace(name), Call::Spawn(_)] if name == "games"
+        ));
+    }
+
+    #[test]
+    fn the_watcher_reports_the_launched_apps_window() {
+        let _compositor = MockCompositor::start(vec![mock::window(1, "foot", "Terminal")]);
+        let config = Config::default();
+
+        // Another window opening meanwhile, e.g. a notification, isn't the launched app's
+        let watcher = WindowWatcher::start().unwrap().unwrap();
+        launch_entry(&app("false.desktop", "false"), &config).unwrap();
+        let entry = app("true.desktop", "true");
+        launch_entry(&entry, &config).unwrap();
+
+        let window = watcher
+            .wait(Duration::from_secs(5), |window| {
+                opened_by(&entry, window, &config)
+            })
+            .unwrap();
+        assert_eq!(window, Some((mock::FIRST_SPAWNED_ID + 1).to_string()));
+    }
+
+    #[test]
+    fn windows_belong_to_the_app_or_terminal_they_were_launched_with() {
+        let config = Config {
+            terminal: "/usr/bin/foot -e".to_string(),
+            ..Default::default()
+        };
+        let window = |app_id: &str| mock::window(1, app_id, "");
+
+        let nautilus = app("org.gnome.Nautilus.desktop", "nautilus --new-window");
+        assert!(opened_by(&nautilus, &window("org.gnome.Nautilus"), &config));
+        assert!(opened_by(&nautilus, &window("nautilus"), &config));
+        assert!(!opened_by(&nautilus, &window("firefox"), &config));
+        assert!(!opened_by(&nautilus, &window(""), &config));
+
+        let htop = Entry {
+            open_type: OpenType::Terminal,
+            ..app("htop.desktop", "htop")
+        };
+        assert!(opened_by(&htop, &window("foot"), &config));
+        assert!(!opened_by(&htop, &window("htop"), &config));
+    }
+
+    #[test]
+    fn window_actions_need_a_compositor() {
+        let window = Entry {
+            open_type: OpenType::Window,
+            ..app("foot", "1")
+        };
+        assert!(launch_entry(&window, &Config::default()).is_err());
+        assert!(close_window("1").is_err());
+        assert!(WindowWatcher::start().unwrap().is_none());
+    }
 }
@@ -0,0 +1,141 @@
+use crate::error::{LauncherError, Result};
+use crate::icon;
+use crate::types::{Entry, OpenType};
+use freedesktop_desktop_entry::DesktopEntry;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+
+const SYSTEM_AUTOSTART_DIR: &str = "/etc/xdg/autostart";
+
+fn user_autostart_dir() -> Result<PathBuf> {
+    dirs::config_dir()
+        .map(|dir| dir.join("autostart"))
+        .ok_or_else(|| {
+            LauncherError::Io(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                "Could not find config directory",
+            ))
+        })
+}
+
+/// The desktop file in effect for each autostart ID, the user's shadowing the system one
+fn autostart_files() -> BTreeMap<String, PathBuf> {
+    let mut files = BTreeMap::new();
+    let dirs = [
+        Ok(PathBuf::from(SYSTEM_AUTOSTART_DIR)),
+        user_autostart_dir(),
+    ];
+
+    for dir in dirs.into_iter().flatten() {
+        let Ok(read_dir) = fs::read_dir(&dir) else {
+            continue;
+        };
+        for path in read_dir
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+        {
+            if path.extension().is_some_and(|ext| ext == "desktop")
+                && let Some(id) = path.file_name()
+            {
+                files.insert(id.to_string_lossy().to_string(), path);
+            }
+        }
+    }
+
+    files
+}
+
+fn is_enabled(desktop_file: &DesktopEntry) -> bool {
+    !desktop_file.hidden()
+        && desktop_file.desktop_entry("X-GNOME-Autostart-enabled") != Some("false")
+}
+
+/// Everything that starts at login, named with whether it is enabled
+pub fn get_autostart_entries() -> Result<Vec<Entry>> {
+    let mut entries = vec![];
+
+    for (id, path) in autostart_files() {
+        let desktop_file = match DesktopEntry::from_path(&path, None::<&[&str]>) {
+            Ok(desktop_file) => desktop_file,
+            Err(e) => {
+                eprintln!("Failed to parse {}: {}", path.display(), e);
+                continue;
+            }
+        };
+
+        let name = desktop_file
+            .name(&[""])
+            .map(|name| name.to_string())
+            .filter(|name| !name.is_empty())
+            .unwrap_or_else(|| id.trim_end_matches(".desktop").to_string());
+        let state = if is_enabled(&desktop_file) {
+            "enabled"
+        } else {
+            "disabled"
+        };
+
+        entries.push(Entry {
+            name: format!("{} ({})", name, state),
+            exec: desktop_file.exec().unwrap_or_default().to_string(),
+            icon: desktop_file
+                .icon()
+                .and_then(icon::resolve_icon_path)
+                .unwrap_or_default(),
+            open_type: OpenType::Autostart,
+            id,
+            ..Default::default()
+        });
+    }
+
+    Ok(entries)
+}
+
+/// Enable or disable an autostart entry, returning whether it is now enabled.
+/// System entries are disabled through a copy in the user's autostart directory
+/// with `Hidden=true`, as the XDG autostart spec describes.
+pub fn toggle(id: &str) -> Result<bool> {
+    let source = autostart_files()
+        .remove(id)
+        .ok_or_else(|| LauncherError::DesktopEntry(format!("No autostart entry {}", id)))?;
+
+    let desktop_file = DesktopEntry::from_path(&source, None::<&[&str]>)
+        .map_err(|e| LauncherError::DesktopEntry(format!("Failed to parse desktop file: {}", e)))?;
+    let enable = !is_enabled(&desktop_file);
+
+    let content = with_hidden(&fs::read_to_string(&source)?, !enable);
+    let user_dir = user_autostart_dir()?;
+    fs::create_dir_all(&user_dir)?;
+    fs::write(user_dir.join(id), content)?;
+
+    Ok(enable)
+}
+
+/// Rewrite a desktop file with `Hidden` set, dropping the GNOME-specific switch
+/// so the two can't disagree
+fn with_hidden(content: &str, hidden: bool) -> String {
+    let mut lines = vec![];
+    let mut in_desktop_entry = false;
+
+    for line in content.lines() {
+        let trimmed = line.trim();
+        if trimmed.starts_with('[') {
+            in_desktop_entry = trimmed == "[Desktop Entry]";
+            lines.push(line.to_string());
+            if in_desktop_entry {
+                lines.push(format!("Hidden={}", hidden));
+            }
+            continue;
+        }
+
+        let key = trimmed.split('=').next().unwrap_or_default().trim();
+        if in_desktop_entry && (key == "Hidden" || key == "X-GNOME-Autostart-enabled") {
+            continue;
+        }
+        lines.push(line.to_string());
+    }
+
+    let mut content = lines.join("\n");
+    content.push('\n');
+    content
+}
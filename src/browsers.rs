@@ -0,0 +1,149 @@
+use crate::exec::quote_argument;
+use crate::network;
+use crate::types::Entry;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Desktop file IDs of Firefox and its Flatpak, with where each keeps its profiles
+const FIREFOX: &[(&str, &str)] = &[
+    ("firefox.desktop", ".mozilla/firefox"),
+    ("firefox-esr.desktop", ".mozilla/firefox"),
+    (
+        "org.mozilla.firefox.desktop",
+        ".var/app/org.mozilla.firefox/.mozilla/firefox",
+    ),
+];
+
+/// The same for Chromium based browsers, whose profile directory has a "Local State" file
+const CHROMIUM: &[(&str, &str)] = &[
+    ("google-chrome.desktop", ".config/google-chrome"),
+    (
+        "com.google.Chrome.desktop",
+        ".var/app/com.google.Chrome/config/google-chrome",
+    ),
+    ("chromium.desktop", ".config/chromium"),
+    ("chromium-browser.desktop", ".config/chromium"),
+    (
+        "org.chromium.Chromium.desktop",
+        ".var/app/org.chromium.Chromium/config/chromium",
+    ),
+    (
+        "brave-browser.desktop",
+        ".config/BraveSoftware/Brave-Browser",
+    ),
+    (
+        "com.brave.Browser.desktop",
+        ".var/app/com.brave.Browser/config/BraveSoftware/Brave-Browser",
+    ),
+];
+
+/// One entry per profile of the browser the app is, starting it with that
+/// profile, e.g. "Firefox (work)". The default profile comes first. Profiles on a
+/// network filesystem are only read when `network`, the config's flag, allows that.
+pub fn profile_entries(app: &Entry, network: bool) -> Vec<Entry> {
+    let home = dirs::home_dir().unwrap_or_else(|| PathBuf::from("/tmp"));
+    let config_dir = |table: &[(&str, &str)]| {
+        table
+            .iter()
+            .find(|(id, _)| *id == app.id)
+            .map(|(_, dir)| home.join(dir))
+            .filter(|dir| network || !network::is_remote(dir))
+    };
+
+    let profiles: Vec<(String, String)> = if let Some(dir) = config_dir(FIREFOX) {
+        firefox_profiles(&dir)
+            .into_iter()
+            .map(|name| (name.clone(), format!("-P {}", quote_argument(&name))))
+            .collect()
+    } else if let Some(dir) = config_dir(CHROMIUM) {
+        chromium_profiles(&dir)
+            .into_iter()
+            .map(|(directory, name)| {
+                let flag = format!("--profile-directory={}", directory);
+                (name, quote_argument(&flag))
+            })
+            .collect()
+    } else {
+        vec![]
+    };
+
+    profiles
+        .into_iter()
+        .map(|(name, arguments)| Entry {
+            name: format!("{} ({})", app.name, name),
+            exec: format!("{} {}", app.exec, arguments),
+            ..app.clone()
+        })
+        .collect()
+}
+
+/// Profile names from profiles.ini, the default one first
+fn firefox_profiles(dir: &Path) -> Vec<String> {
+    let Ok(data) = fs::read_to_string(dir.join("profiles.ini")) else {
+        return vec![];
+    };
+
+    // [ProfileN] sections name the profiles, an [Install…] section may pick the default
+    let mut profiles: Vec<(String, String, bool)> = vec![];
+    let mut install_default = None;
+    let mut section = String::new();
+    for line in data.lines().map(str::trim) {
+        if let Some(name) = line
+            .strip_prefix('[')
+            .and_then(|line| line.strip_suffix(']'))
+        {
+            section = name.to_string();
+            if section.starts_with("Profile") {
+                profiles.push(Default::default());
+            }
+            continue;
+        }
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+
+        if section.starts_with("Install") && key == "Default" {
+            install_default = Some(value.to_string());
+        } else if section.starts_with("Profile")
+            && let Some((name, path, default)) = profiles.last_mut()
+        {
+            match key {
+                "Name" => *name = value.to_string(),
+                "Path" => *path = value.to_string(),
+                "Default" => *default = value == "1",
+                _ => {}
+            }
+        }
+    }
+
+    let is_default = |(_, path, default): &(String, String, bool)| match &install_default {
+        Some(install_default) => path == install_default,
+        None => *default,
+    };
+    profiles.retain(|(name, _, _)| !name.is_empty());
+    profiles.sort_by_key(|profile| !is_default(profile));
+    profiles.into_iter().map(|(name, _, _)| name).collect()
+}
+
+/// Profile directories with their names from "Local State", "Default" first
+fn chromium_profiles(dir: &Path) -> Vec<(String, String)> {
+    let Some(state) = fs::read_to_string(dir.join("Local State"))
+        .ok()
+        .and_then(|data| serde_json::from_str::<serde_json::Value>(&data).ok())
+    else {
+        return vec![];
+    };
+    let Some(cache) = state["profile"]["info_cache"].as_object() else {
+        return vec![];
+    };
+
+    let mut profiles: Vec<(String, String)> = cache
+        .iter()
+        .map(|(directory, info)| {
+            let name = info["name"].as_str().unwrap_or(directory).to_string();
+            (directory.clone(), name)
+        })
+        .collect();
+    profiles.sort_by_key(|(directory, _)| (directory != "Default", directory.clone()));
+    profiles
+}
@@ -0,0 +1,64 @@
+use crate::config::Config;
+use crate::error::{LauncherError, Result};
+use crate::state::State;
+use crate::usage::UsageTracker;
+use serde::{Deserialize, Serialize};
+
+/// Bumped whenever a bundle written by this version can't be read by older ones
+const BUNDLE_VERSION: u32 = 1;
+
+/// Everything needed to move a setup to another machine, as one TOML file
+/// that can also live in a dotfiles repository
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Bundle {
+    pub version: u32,
+    pub config: Config,
+    pub state: State,
+    /// Left out unless asked for, it records which apps were launched when
+    pub usage: Option<UsageTracker>,
+}
+
+impl Bundle {
+    /// Collect the current config and state, with usage data if `usage` is set
+    pub fn collect(usage: bool) -> Result<Self> {
+        Ok(Self {
+            version: BUNDLE_VERSION,
+            config: Config::load()?,
+            state: State::load(),
+            usage: if usage {
+                Some(UsageTracker::load()?)
+            } else {
+                None
+            },
+        })
+    }
+
+    pub fn to_toml(&self) -> Result<String> {
+        toml::to_string_pretty(self)
+            .map_err(|e| LauncherError::Config(format!("Failed to serialize bundle: {}", e)))
+    }
+
+    pub fn from_toml(data: &str) -> Result<Self> {
+        let bundle: Self = toml::from_str(data)
+            .map_err(|e| LauncherError::Config(format!("Failed to parse bundle: {}", e)))?;
+
+        if bundle.version > BUNDLE_VERSION {
+            return Err(LauncherError::Config(format!(
+                "Bundle version {} is newer than this launcher supports",
+                bundle.version
+            )));
+        }
+
+        Ok(bundle)
+    }
+
+    /// Replace the config and state, and the usage data when the bundle has any
+    pub fn apply(&self) -> Result<()> {
+        self.config.save()?;
+        self.state.save()?;
+        if let Some(usage) = &self.usage {
+            usage.save()?;
+        }
+        Ok(())
+    }
+}
@@ -1,32 +1,46 @@
 use crate::error::{LauncherError, Result};
+use crate::icon;
 use crate::types::Entry;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::ffi::OsStr;
 use std::fs;
 use std::path::{Path, PathBuf};
 use std::time::SystemTime;
 
+/// Bumped whenever the serialized layout of `CacheData` changes
+const CACHE_VERSION: u32 = 9;
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct CacheData {
+    pub version: u32,
     pub entries: Vec<Entry>,
     pub directory_timestamps: HashMap<PathBuf, SystemTime>,
+    /// Icon paths were resolved against these themes
+    pub icon_themes: Vec<String>,
 }
 
 impl CacheData {
     pub fn new() -> Self {
         Self {
+            version: CACHE_VERSION,
             entries: Vec::new(),
             directory_timestamps: HashMap::new(),
+            icon_themes: Vec::new(),
         }
     }
 }
 
+/// Marks a compressed cache file; uncompressed ones start with the postcard version
+const COMPRESSED_MAGIC: &[u8] = b"ADWLZ4";
+
 pub struct Cache {
     cache_path: PathBuf,
+    compress: bool,
 }
 
 impl Cache {
-    pub fn new() -> Result<Self> {
+    pub fn new(compress: bool) -> Result<Self> {
         let cache_dir = dirs::cache_dir()
             .ok_or_else(|| {
                 LauncherError::Io(std::io::Error::new(
@@ -40,6 +54,7 @@ impl Cache {
 
         Ok(Self {
             cache_path: cache_dir.join("entries.cache"),
+            compress,
         })
     }
 
@@ -48,70 +63,337 @@ impl Cache {
             return Ok(CacheData::new());
         }
 
-        let data = fs::read(&self.cache_path)?;
-        postcard::from_bytes(&data).map_err(|e| {
-            LauncherError::Io(std::io::Error::new(
-                std::io::ErrorKind::InvalidData,
-                format!("Failed to deserialize cache: {}", e),
-            ))
-        })
+        let mut data = fs::read(&self.cache_path)?;
+
+        // Either format is read, so toggling compression doesn't drop the cache
+        if let Some(compressed) = data.strip_prefix(COMPRESSED_MAGIC) {
+            data = lz4_flex::decompress_size_prepended(compressed).map_err(|e| {
+                LauncherError::CacheCorrupt(format!("Failed to decompress cache: {}", e))
+            })?;
+        }
+
+        // The version comes first, a cache from another version is rebuilt
+        // instead of failing to deserialize into the current layout
+        if postcard::take_from_bytes::<u32>(&data)
+            .is_ok_and(|(version, _)| version != CACHE_VERSION)
+        {
+            return Ok(CacheData::new());
+        }
+
+        postcard::from_bytes(&data)
+            .map_err(|e| LauncherError::CacheCorrupt(format!("Failed to deserialize cache: {}", e)))
     }
 
     pub fn save(&self, cache_data: &CacheData) -> Result<()> {
-        let data = postcard::to_allocvec(cache_data).map_err(|e| {
+        let mut data = postcard::to_allocvec(cache_data).map_err(|e| {
             LauncherError::Io(std::io::Error::new(
                 std::io::ErrorKind::InvalidData,
                 format!("Failed to serialize cache: {}", e),
             ))
         })?;
 
+        if self.compress {
+            let mut compressed = COMPRESSED_MAGIC.to_vec();
+            compressed.extend(lz4_flex::compress_prepend_size(&data));
+            data = compressed;
+        }
+
         fs::write(&self.cache_path, data)?;
         Ok(())
     }
 
-    pub fn is_valid(&self, cache_data: &CacheData, directories: &[PathBuf]) -> bool {
-        // Check if all directories have the same modification time
-        for dir in directories {
-            if !dir.exists() {
-                continue;
-            }
+    /// Remove the cache file so the next load starts over
+    pub fn clear(&self) -> Result<()> {
+        match fs::remove_file(&self.cache_path) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
 
-            let current_mtime = match get_dir_mtime(dir) {
-                Ok(mtime) => mtime,
-                Err(_) => return false,
-            };
+    /// `timestamps` holds the current modification time of every existing app directory
+    pub fn is_valid(
+        &self,
+        cache_data: &CacheData,
+        timestamps: &HashMap<PathBuf, SystemTime>,
+    ) -> bool {
+        if cache_data.version != CACHE_VERSION {
+            return false;
+        }
 
-            match cache_data.directory_timestamps.get(dir) {
-                Some(&cached_mtime) if cached_mtime == current_mtime => continue,
-                _ => return false,
-            }
+        // Switching icon themes changes which icon files entries resolve to
+        if cache_data.icon_themes != icon::icon_themes() {
+            return false;
         }
 
-        true
+        // Valid as long as no directory appeared, disappeared or changed
+        cache_data.directory_timestamps == *timestamps
     }
 }
 
-fn get_dir_mtime(path: &Path) -> Result<SystemTime> {
+pub fn get_dir_mtime(path: &Path) -> Result<SystemTime> {
     let metadata = fs::metadata(path)?;
     metadata.modified().map_err(|e| e.into())
 }
 
-pub fn get_app_directories() -> Vec<PathBuf> {
+/// The latest modification time of a directory and the directories below it, which
+/// changes whenever a file is added to or removed from any of them
+pub fn get_tree_mtime(path: &Path) -> Result<SystemTime> {
+    let mut latest = get_dir_mtime(path)?;
+    for entry in fs::read_dir(path)?.flatten() {
+        // Symlinked directories aren't followed, like when scanning
+        if entry.file_type().is_ok_and(|file_type| file_type.is_dir())
+            && let Ok(mtime) = get_tree_mtime(&entry.path())
+        {
+            latest = latest.max(mtime);
+        }
+    }
+    Ok(latest)
+}
+
+/// Where desktop files are looked for, in order of precedence: `$XDG_DATA_HOME`,
+/// then each of `$XDG_DATA_DIRS`, then Flatpak's exports in case the session didn't
+/// add them, then `extra` from the `app_directories` config option
+pub fn get_app_directories(extra: &[PathBuf]) -> Vec<PathBuf> {
     let home = std::env::var("HOME").unwrap_or_else(|_| String::from("/tmp"));
-    vec![
-        PathBuf::from("/usr/share/applications"),
-        PathBuf::from(format!("{}/.local/share/applications", home)),
-        PathBuf::from("/var/lib/flatpak/exports/share/applications/"),
-        PathBuf::from(format!(
-            "{}/.local/share/flatpak/exports/share/applications/",
-            home
-        )),
-    ]
+    let data_home = std::env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .filter(|path| path.is_absolute())
+        .unwrap_or_else(|| Path::new(&home).join(".local/share"));
+    let data_dirs = std::env::var_os("XDG_DATA_DIRS").unwrap_or_default();
+
+    let mut directories = app_directories(&data_home, &data_dirs);
+    for directory in extra {
+        if !directories.contains(directory) {
+            directories.push(directory.clone());
+        }
+    }
+    directories
+}
+
+fn app_directories(data_home: &Path, data_dirs: &OsStr) -> Vec<PathBuf> {
+    // Relative entries are invalid per the base directory spec and ignored
+    let mut data_dirs: Vec<PathBuf> = std::env::split_paths(data_dirs)
+        .filter(|path| path.is_absolute())
+        .collect();
+    if data_dirs.is_empty() {
+        data_dirs = vec![
+            PathBuf::from("/usr/local/share"),
+            PathBuf::from("/usr/share"),
+        ];
+    }
+
+    let mut directories: Vec<PathBuf> = vec![];
+    for data_dir in std::iter::once(data_home.to_path_buf())
+        .chain(data_dirs)
+        .chain([
+            data_home.join("flatpak/exports/share"),
+            PathBuf::from("/var/lib/flatpak/exports/share"),
+        ])
+    {
+        let directory = data_dir.join("applications");
+        if !directories.contains(&directory) {
+            directories.push(directory);
+        }
+    }
+    directories
 }
 
 pub fn collect_directory_timestamps(directories: &[PathBuf]) -> HashMap<PathBuf, SystemTime> {
     directories
         .iter()
-        .filter_map(|dir| get_dir_mtime(dir).ok().map(|mtime| (dir.clone(), mtime)))
+        .filter_map(|dir| get_tree_mtime(dir).ok().map(|mtime| (dir.clone(), mtime)))
         .collect()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::OpenType;
+    use proptest::prelude::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::{Duration, UNIX_EPOCH};
+
+    static FILES: AtomicUsize = AtomicUsize::new(0);
+
+    /// A cache in its own temporary file, removed again when dropped
+    struct TempCache(Cache);
+
+    impl TempCache {
+        fn new(compress: bool) -> Self {
+            let cache_path = std::env::temp_dir().join(format!(
+                "adwlauncher-cache-test-{}-{}",
+                std::process::id(),
+                FILES.fetch_add(1, Ordering::Relaxed)
+            ));
+            Self(Cache {
+                cache_path,
+                compress,
+            })
+        }
+    }
+
+    impl Drop for TempCache {
+        fn drop(&mut self) {
+            let _ = self.0.clear();
+        }
+    }
+
+    #[test]
+    fn app_directories_follow_xdg_data_dirs() {
+        let directories = app_directories(
+            Path::new("/home/me/.local/share"),
+            OsStr::new("/nix/var/nix/profiles/default/share:relative/share::/usr/share/"),
+        );
+        assert_eq!(
+            directories,
+            [
+                "/home/me/.local/share/applications",
+                "/nix/var/nix/profiles/default/share/applications",
+                "/usr/share/applications",
+                "/home/me/.local/share/flatpak/exports/share/applications",
+                "/var/lib/flatpak/exports/share/applications",
+            ]
+            .map(PathBuf::from)
+        );
+    }
+
+    #[test]
+    fn app_directories_fall_back_to_the_default_data_dirs() {
+        let directories = app_directories(Path::new("/home/me/.local/share"), OsStr::new(""));
+        assert_eq!(
+            directories[1..3],
+            [
+                PathBuf::from("/usr/local/share/applications"),
+                PathBuf::from("/usr/share/applications"),
+            ]
+        );
+    }
+
+    fn open_type() -> impl Strategy<Value = OpenType> {
+        prop_oneof![
+            Just(OpenType::Graphical),
+            Just(OpenType::Terminal),
+            Just(OpenType::Window),
+            Just(OpenType::Text),
+            Just(OpenType::Power),
+            Just(OpenType::MimeType),
+            Just(OpenType::Autostart),
+            Just(OpenType::Help),
+            Just(OpenType::Variable),
+            Just(OpenType::Folder),
+            Just(OpenType::Category),
+        ]
+    }
+
+    fn entry() -> impl Strategy<Value = Entry> {
+        (
+            any::<String>(),
+            open_type(),
+            any::<String>(),
+            any::<String>(),
+            any::<String>(),
+            any::<bool>(),
+            prop::collection::vec(any::<String>(), 0..4),
+            any::<String>(),
+            prop::collection::vec(any::<String>(), 0..4),
+            any::<String>(),
+            any::<String>(),
+            prop::option::of(any::<String>().prop_map(PathBuf::from)),
+        )
+            .prop_map(
+                |(
+                    id,
+                    open_type,
+                    exec,
+                    icon,
+                    name,
+                    startup_notify,
+                    mime_types,
+                    category,
+                    keywords,
+                    generic_name,
+                    comment,
+                    desktop_file,
+                )| Entry {
+                    id,
+                    open_type,
+                    exec,
+                    icon,
+                    name,
+                    startup_notify,
+                    mime_types,
+                    category,
+                    keywords,
+                    generic_name,
+                    comment,
+                    desktop_file,
+                },
+            )
+    }
+
+    fn cache_data() -> impl Strategy<Value = CacheData> {
+        (
+            prop::collection::vec(entry(), 0..32),
+            prop::collection::hash_map(any::<String>(), any::<u32>(), 0..4),
+            prop::collection::vec(any::<String>(), 0..3),
+        )
+            .prop_map(|(entries, timestamps, icon_themes)| CacheData {
+                version: CACHE_VERSION,
+                entries,
+                directory_timestamps: timestamps
+                    .into_iter()
+                    .map(|(dir, secs)| {
+                        (
+                            PathBuf::from(dir),
+                            UNIX_EPOCH + Duration::from_secs(secs.into()),
+                        )
+                    })
+                    .collect(),
+                icon_themes,
+            })
+    }
+
+    proptest! {
+        #[test]
+        fn saved_caches_load_unchanged(data in cache_data(), compress in any::<bool>()) {
+            let cache = TempCache::new(compress);
+            cache.0.save(&data).unwrap();
+            let loaded = cache.0.load().unwrap();
+
+            // Entry has no PartialEq, its Debug output covers every field
+            prop_assert_eq!(format!("{:?}", loaded.entries), format!("{:?}", data.entries));
+            prop_assert_eq!(loaded.directory_timestamps, data.directory_timestamps);
+            prop_assert_eq!(loaded.icon_themes, data.icon_themes);
+            prop_assert_eq!(loaded.version, CACHE_VERSION);
+        }
+
+        #[test]
+        fn caches_from_other_versions_start_over(
+            version in any::<u32>().prop_filter("another version", |v| *v != CACHE_VERSION),
+            rest in prop::collection::vec(any::<u8>(), 0..256),
+            compress in any::<bool>(),
+        ) {
+            let mut data = postcard::to_allocvec(&version).unwrap();
+            data.extend(rest);
+            if compress {
+                let mut compressed = COMPRESSED_MAGIC.to_vec();
+                compressed.extend(lz4_flex::compress_prepend_size(&data));
+                data = compressed;
+            }
+
+            let cache = TempCache::new(compress);
+            fs::write(&cache.0.cache_path, data).unwrap();
+            let loaded = cache.0.load().unwrap();
+
+            prop_assert!(loaded.entries.is_empty());
+            prop_assert_eq!(loaded.version, CACHE_VERSION);
+        }
+
+        #[test]
+        fn corrupt_caches_fail_without_panicking(data in prop::collection::vec(any::<u8>(), 0..512)) {
+            let cache = TempCache::new(false);
+            fs::write(&cache.0.cache_path, data).unwrap();
+            let _ = cache.0.load();
+        }
+    }
+}
@@ -0,0 +1,89 @@
+use crate::types::{Entry, OpenType};
+use std::collections::{BTreeMap, HashSet};
+
+/// The main categories of the desktop menu spec, with the section each shows in
+const MAIN_CATEGORIES: &[(&str, &str)] = &[
+    ("AudioVideo", "Sound & Video"),
+    ("Audio", "Sound & Video"),
+    ("Video", "Sound & Video"),
+    ("Development", "Development"),
+    ("Education", "Education"),
+    ("Game", "Games"),
+    ("Graphics", "Graphics"),
+    ("Network", "Internet"),
+    ("Office", "Office"),
+    ("Science", "Science"),
+    ("Settings", "Settings"),
+    ("System", "System"),
+    ("Utility", "Accessories"),
+];
+/// Apps without a main category, listed last
+const OTHER: &str = "Other";
+
+/// The first main category among a desktop file's Categories, empty without one.
+/// Additional categories like "IDE" only refine a main one, so they are skipped.
+pub fn main_category<'a>(categories: impl IntoIterator<Item = &'a str>) -> String {
+    categories
+        .into_iter()
+        .find(|category| MAIN_CATEGORIES.iter().any(|(main, _)| main == category))
+        .unwrap_or_default()
+        .to_string()
+}
+
+/// The section an app is listed in
+pub fn section(entry: &Entry) -> &'static str {
+    MAIN_CATEGORIES
+        .iter()
+        .find(|(main, _)| *main == entry.category)
+        .map_or(OTHER, |(_, section)| section)
+}
+
+/// The row heading a section, activating it shows or hides the section's apps
+fn section_entry(section: &str, count: usize, expanded: bool) -> Entry {
+    Entry {
+        id: format!("category-{}", section),
+        name: format!("{} ({})", section, count),
+        exec: section.to_string(),
+        icon: if expanded {
+            "pan-down-symbolic"
+        } else {
+            "pan-end-symbolic"
+        }
+        .to_string(),
+        open_type: OpenType::Category,
+        ..Default::default()
+    }
+}
+
+/// Sort the apps into sections by category, in alphabetical order with "Other" last.
+/// Only sections in `expanded` list their apps, in ranked order. Folders come first
+/// and other entries, like windows, after the sections.
+pub fn group(ranked: Vec<(f64, Entry)>, expanded: &HashSet<String>) -> Vec<(f64, Entry)> {
+    let mut leading = vec![];
+    let mut sections: BTreeMap<(bool, &'static str), Vec<(f64, Entry)>> = BTreeMap::new();
+    let mut trailing = vec![];
+    for (score, entry) in ranked {
+        match entry.open_type {
+            OpenType::Graphical | OpenType::Terminal => {
+                let section = section(&entry);
+                sections
+                    .entry((section == OTHER, section))
+                    .or_default()
+                    .push((score, entry));
+            }
+            OpenType::Folder => leading.push((score, entry)),
+            _ => trailing.push((score, entry)),
+        }
+    }
+
+    let mut grouped = leading;
+    for ((_, section), apps) in sections {
+        let is_expanded = expanded.contains(section);
+        grouped.push((0.0, section_entry(section, apps.len(), is_expanded)));
+        if is_expanded {
+            grouped.extend(apps);
+        }
+    }
+    grouped.extend(trailing);
+    grouped
+}
@@ -0,0 +1,307 @@
+use crate::config::Layout;
+use clap::{CommandFactory, Parser, Subcommand, ValueEnum};
+use clap_complete::Shell;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+#[derive(Parser, Debug)]
+#[command(
+    name = "adwlauncher",
+    version,
+    about = "A simple application launcher for niri"
+)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    /// Run as a GApplication service
+    #[arg(long)]
+    pub gapplication_service: bool,
+
+    /// Use a separate set of config, usage data and state, e.g. "work"
+    #[arg(long, global = true)]
+    pub profile: Option<String>,
+
+    /// Import settings from another launcher's config file and exit
+    #[arg(long, value_enum, value_name = "LAUNCHER")]
+    pub import_config: Option<ImportSource>,
+
+    /// Print a man page in roff format and exit
+    #[arg(long)]
+    pub generate_man: bool,
+
+    /// Print ranked entries without showing the window
+    #[arg(long)]
+    pub list: bool,
+
+    /// Only show entries of this kind, remembered for later runs without --mode
+    #[arg(long, value_enum)]
+    pub mode: Option<Mode>,
+
+    /// Filter and rank entries like typing in the search field would
+    #[arg(long, requires = "list")]
+    pub query: Option<String>,
+
+    /// Print entries as JSON instead of one name per line
+    #[arg(long, requires = "list")]
+    pub json: bool,
+
+    /// Read choices from stdin, one per line, and print the selected one
+    #[arg(long, conflicts_with = "mode")]
+    pub dmenu: bool,
+
+    /// Mask the typed text and print it instead of choosing from stdin
+    #[arg(long, requires = "dmenu")]
+    pub password: bool,
+
+    /// Label shown in front of the search field
+    #[arg(long)]
+    pub prompt: Option<String>,
+
+    /// Icon name or path shown in front of the prompt
+    #[arg(long, value_name = "ICON")]
+    pub prompt_icon: Option<String>,
+
+    /// Always match case-insensitively
+    #[arg(long)]
+    pub ignore_case: bool,
+
+    /// Present the results this way instead of the configured layout
+    #[arg(long, value_enum)]
+    pub layout: Option<Layout>,
+}
+
+/// Options that only apply to the window opened by this invocation
+#[derive(Debug, Clone, Default)]
+pub struct RunOptions {
+    pub mode: Option<Mode>,
+    pub dmenu_lines: Option<Vec<String>>,
+    pub password: bool,
+    pub prompt: Option<String>,
+    pub prompt_icon: Option<String>,
+    pub ignore_case: bool,
+    pub layout: Option<Layout>,
+}
+
+impl RunOptions {
+    /// Whether this invocation needs its own instance instead of the shared service
+    pub fn is_standalone(&self) -> bool {
+        self.mode.is_some()
+            || self.dmenu_lines.is_some()
+            || self.prompt.is_some()
+            || self.prompt_icon.is_some()
+            || self.ignore_case
+            || self.layout.is_some()
+    }
+}
+
+/// Rewrite the supported subset of rofi flags into our own, so existing
+/// keybinds and scripts calling `rofi -show drun` or `rofi -dmenu` keep working.
+/// Only flags are translated, option values like a prompt of "-i" are kept as they are,
+/// and so is everything after `--` or a subcommand.
+pub fn translate_rofi_args(args: impl IntoIterator<Item = String>) -> Vec<String> {
+    let mut args = args.into_iter();
+    // The program name
+    let mut translated: Vec<String> = args.next().into_iter().collect();
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-show" => {
+                translated.push("--mode".to_string());
+                match args.next().as_deref() {
+                    Some("window") => translated.push("windows".to_string()),
+                    // drun, run and anything unknown show applications
+                    _ => translated.push("apps".to_string()),
+                }
+            }
+            "-dmenu" => translated.push("--dmenu".to_string()),
+            "-password" => translated.push("--password".to_string()),
+            "-i" => translated.push("--ignore-case".to_string()),
+            "-p" => {
+                translated.push("--prompt".to_string());
+                translated.extend(args.next());
+            }
+            flag if takes_value(flag) => {
+                translated.push(arg);
+                translated.extend(args.next());
+            }
+            // What follows isn't ours to translate
+            flag if flag == "--" || !flag.starts_with('-') => {
+                translated.push(arg);
+                break;
+            }
+            _ => translated.push(arg),
+        }
+    }
+
+    translated.extend(args);
+    translated
+}
+
+/// Whether one of our own options is given with its value in the next argument
+fn takes_value(flag: &str) -> bool {
+    let Some(long) = flag.strip_prefix("--") else {
+        return false;
+    };
+    Cli::command()
+        .get_arguments()
+        .any(|arg| arg.get_long() == Some(long) && arg.get_action().takes_values())
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Launch an application by its desktop file ID, e.g. "firefox.desktop"
+    Launch { desktop_id: String },
+    /// Print a shell completion script
+    Completions { shell: Shell },
+    /// Check the niri connection, config, cache and other dependencies
+    Doctor,
+    /// Rebuild the application cache from scratch, resolving icons again
+    Refresh,
+    /// Print the cached application index, refreshing it first if it is stale
+    DumpCache {
+        /// Print a JSON array instead of tab-separated lines
+        #[arg(long)]
+        json: bool,
+    },
+    /// Bundle the config and remembered state into one file, e.g. for dotfiles
+    ExportState {
+        /// Where to write the bundle, standard output when not given
+        file: Option<PathBuf>,
+        /// Include usage data, which records when each app was launched
+        #[arg(long)]
+        usage: bool,
+    },
+    /// Replace the config and state, and usage data if included, with an exported bundle
+    ImportState { file: PathBuf },
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy)]
+pub enum ImportSource {
+    Rofi,
+    Wofi,
+    Fuzzel,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Mode {
+    /// Applications, windows and session actions together
+    All,
+    Apps,
+    Windows,
+    Power,
+    /// Programs started at login, to enable or disable them
+    Autostart,
+    /// MIME types, to change which app opens them
+    MimeTypes,
+    /// Installed games in a large grid, for couch and controller use
+    Games,
+    /// Manual pages, opened in the terminal or Yelp
+    Man,
+    /// Commands from the shell history, run again in the terminal
+    Run,
+    /// Environment variables, to copy their values
+    Env,
+}
+
+impl Mode {
+    /// Shown in the header while searching within the mode
+    pub fn label(&self) -> &'static str {
+        match self {
+            Mode::All => "All",
+            Mode::Apps => "Apps",
+            Mode::Windows => "Windows",
+            Mode::Power => "Session",
+            Mode::Autostart => "Autostart",
+            Mode::MimeTypes => "Default Apps",
+            Mode::Games => "Games",
+            Mode::Man => "Manual Pages",
+            Mode::Run => "Run",
+            Mode::Env => "Environment",
+        }
+    }
+
+    /// Typed in front of a query, followed by a space, to search within the mode.
+    /// The short ones start with a colon, so searching for "w" or "p" isn't taken as one.
+    pub fn prefix(&self) -> Option<&'static str> {
+        match self {
+            Mode::All => None,
+            Mode::Apps => Some(":a"),
+            Mode::Windows => Some(":w"),
+            Mode::Power => Some(":p"),
+            Mode::Autostart => Some("autostart"),
+            Mode::MimeTypes => Some("default for"),
+            Mode::Games => Some("games"),
+            Mode::Man => Some("man"),
+            Mode::Run => Some("run"),
+            Mode::Env => Some("env"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn translate(args: &[&str]) -> Vec<String> {
+        translate_rofi_args(
+            std::iter::once("adwlauncher")
+                .chain(args.iter().copied())
+                .map(String::from),
+        )
+    }
+
+    #[test]
+    fn rofi_flags_are_translated() {
+        assert_eq!(
+            translate(&["-show", "drun", "-i"]),
+            ["adwlauncher", "--mode", "apps", "--ignore-case"]
+        );
+        assert_eq!(
+            translate(&["-dmenu", "-p", "Pick"]),
+            ["adwlauncher", "--dmenu", "--prompt", "Pick"]
+        );
+    }
+
+    #[test]
+    fn option_values_are_kept_as_they_are() {
+        assert_eq!(
+            translate(&["-dmenu", "-p", "-dmenu", "-i"]),
+            [
+                "adwlauncher",
+                "--dmenu",
+                "--prompt",
+                "-dmenu",
+                "--ignore-case"
+            ]
+        );
+        assert_eq!(
+            translate(&["--list", "--query", "-i"]),
+            ["adwlauncher", "--list", "--query", "-i"]
+        );
+    }
+
+    #[test]
+    fn subcommands_are_left_alone() {
+        assert_eq!(
+            translate(&["import-state", "-p"]),
+            ["adwlauncher", "import-state", "-p"]
+        );
+        assert_eq!(
+            translate(&["--", "-dmenu"]),
+            ["adwlauncher", "--", "-dmenu"]
+        );
+    }
+
+    #[test]
+    fn prefixes_are_not_single_letters() {
+        for prefix in Mode::value_variants().iter().filter_map(Mode::prefix) {
+            assert!(
+                prefix.len() > 1,
+                "{:?} would take over one-letter searches",
+                prefix
+            );
+        }
+    }
+}
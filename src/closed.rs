@@ -0,0 +1,76 @@
+use crate::compositor::{Backend, WindowEvent};
+use crate::error::Result;
+use crate::profile;
+use crate::types::{Entry, OpenType};
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Duration;
+
+/// App IDs of the windows closed while the service runs, most recent first
+static CLOSED: Mutex<VecDeque<String>> = Mutex::new(VecDeque::new());
+/// More than any list shows, so closing the same app repeatedly doesn't push others out
+const REMEMBERED: usize = 20;
+/// Wait before following the events again, e.g. while the compositor restarts
+const RETRY: Duration = Duration::from_secs(5);
+
+/// Note every window closed from now on, in a thread following the compositor's events
+pub fn watch(backend: Arc<dyn Backend>) {
+    std::thread::spawn(move || {
+        loop {
+            if let Err(e) = follow(backend.as_ref()) {
+                eprintln!("Stopped tracking closed windows: {:#}", e);
+            }
+            std::thread::sleep(RETRY);
+        }
+    });
+}
+
+fn follow(backend: &dyn Backend) -> Result<()> {
+    let mut events = backend.events()?;
+    let own_id = profile::application_id();
+    loop {
+        match events.next(None)? {
+            // An app opened again no longer needs reopening
+            Some(WindowEvent::Opened(window)) => forget(&window.app_id),
+            Some(WindowEvent::Closed(window))
+                if !window.app_id.is_empty() && window.app_id != own_id =>
+            {
+                record(window.app_id)
+            }
+            _ => {}
+        }
+    }
+}
+
+fn record(app_id: String) {
+    let mut closed = CLOSED.lock().unwrap_or_else(PoisonError::into_inner);
+    closed.retain(|other| *other != app_id);
+    closed.push_front(app_id);
+    closed.truncate(REMEMBERED);
+}
+
+fn forget(app_id: &str) {
+    let mut closed = CLOSED.lock().unwrap_or_else(PoisonError::into_inner);
+    closed.retain(|other| other != app_id);
+}
+
+/// The apps owning the last `count` closed windows, launching one opens it again.
+/// Windows of apps without a desktop file can't be reopened and are skipped.
+pub fn entries(apps: &[Entry], count: usize) -> Vec<Entry> {
+    let closed = CLOSED.lock().unwrap_or_else(PoisonError::into_inner);
+    closed
+        .iter()
+        .filter_map(|app_id| apps.iter().find(|entry| owns(entry, app_id)))
+        .take(count)
+        .cloned()
+        .collect()
+}
+
+/// Most apps name their windows after their desktop file, some only in lowercase
+fn owns(entry: &Entry, app_id: &str) -> bool {
+    matches!(entry.open_type, OpenType::Graphical | OpenType::Terminal)
+        && entry
+            .id
+            .strip_suffix(".desktop")
+            .is_some_and(|id| id.eq_ignore_ascii_case(app_id))
+}
@@ -0,0 +1,197 @@
+use crate::app_discovery::{
+    get_desktop_entries_cached, get_entries_for_mode, launch_entry, merge_ranked,
+    refresh_desktop_entries,
+};
+use crate::bundle::Bundle;
+use crate::cli::{Cli, ImportSource, Mode};
+use crate::config::Config;
+use crate::duplicates;
+use crate::error::{LauncherError, Result};
+use crate::import;
+use crate::notify;
+use crate::rules;
+use crate::search::{self, SearchOptions};
+use crate::state::State;
+use crate::types::Entry;
+use crate::usage::UsageTracker;
+use clap::CommandFactory;
+use clap_complete::Shell;
+use serde::Serialize;
+use std::fs;
+use std::path::Path;
+
+#[derive(Serialize)]
+struct ListItem<'a> {
+    #[serde(flatten)]
+    entry: &'a Entry,
+    score: f64,
+}
+
+pub fn import_config(source: ImportSource) -> Result<()> {
+    let mut config = Config::load()?;
+    let imported = import::import_config(source, &mut config)?;
+
+    if imported.is_empty() {
+        println!("No recognizable options found");
+        return Ok(());
+    }
+
+    config.save()?;
+    println!("Imported: {}", imported.join(", "));
+    Ok(())
+}
+
+/// Run discovery and matching without the GUI and print the ranked entries
+pub fn list(mode: Option<Mode>, query: &str, json: bool) -> Result<()> {
+    let config = Config::load()?;
+    let entries = get_entries_for_mode(mode, &config)?;
+
+    let usage_tracker = UsageTracker::load().unwrap_or_else(|e| {
+        eprintln!("Failed to load usage tracker: {:#}", e);
+        UsageTracker::new()
+    });
+
+    let options = SearchOptions::from_config(&config);
+    let mut ranked = search::rank_entries(&entries, query, &usage_tracker, options);
+    if matches!(mode, None | Some(Mode::All)) && !query.is_empty() {
+        merge_ranked(&mut ranked, query, options);
+    }
+    if let Some(now) = rules::now() {
+        rules::apply(&config.rules, now, &mut ranked);
+    }
+    duplicates::prefer_launched(&mut ranked, &State::load().preferred_copies);
+
+    if json {
+        let items: Vec<ListItem> = ranked
+            .iter()
+            .map(|(score, entry)| ListItem {
+                entry,
+                score: *score,
+            })
+            .collect();
+
+        let output = serde_json::to_string_pretty(&items).map_err(|e| {
+            LauncherError::Io(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("Failed to serialize entries: {}", e),
+            ))
+        })?;
+        println!("{}", output);
+    } else {
+        for (_score, entry) in &ranked {
+            println!("{}", entry.name);
+        }
+    }
+
+    Ok(())
+}
+
+/// Launch an application by desktop file ID without showing any UI
+pub fn launch(desktop_id: &str) -> Result<()> {
+    let config = Config::load()?;
+    notify::set_enabled(config.notifications);
+    let entries = get_desktop_entries_cached(&config)?;
+
+    let entry = entries
+        .iter()
+        .filter(|entry| config.kiosk.allows(entry))
+        .find(|entry| {
+            entry.id == desktop_id || entry.id.strip_suffix(".desktop") == Some(desktop_id)
+        })
+        .ok_or_else(|| {
+            LauncherError::DesktopEntry(format!("No application with ID {}", desktop_id))
+        })?;
+
+    // Nothing shows stderr when this runs from a compositor keybind
+    if let Err(e) = launch_entry(entry, &config) {
+        notify::notify(&format!("Failed to launch {}", entry.name), &e.to_string());
+        return Err(e);
+    }
+
+    if config.usage_tracking {
+        let mut usage_tracker = UsageTracker::load()?;
+        usage_tracker.record_launch(&entry.name);
+        usage_tracker.record_recent(entry);
+        usage_tracker.save()?;
+        duplicates::record_launch(entry, &entries);
+    }
+
+    Ok(())
+}
+
+/// Print the application index for status bars and scripts.
+/// Fields are id, name, exec, icon and open_type, see the README.
+pub fn dump_cache(json: bool) -> Result<()> {
+    let config = Config::load()?;
+    let entries = get_desktop_entries_cached(&config)?;
+
+    if json {
+        let output = serde_json::to_string_pretty(&entries).map_err(|e| {
+            LauncherError::Io(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("Failed to serialize entries: {}", e),
+            ))
+        })?;
+        println!("{}", output);
+    } else {
+        for entry in &entries {
+            println!(
+                "{}\t{}\t{}\t{}",
+                entry.id, entry.name, entry.exec, entry.icon
+            );
+        }
+    }
+
+    Ok(())
+}
+
+pub fn export_state(file: Option<&Path>, usage: bool) -> Result<()> {
+    let data = Bundle::collect(usage)?.to_toml()?;
+
+    match file {
+        Some(path) => {
+            fs::write(path, data)?;
+            eprintln!("Exported to {}", path.display());
+        }
+        None => print!("{}", data),
+    }
+
+    Ok(())
+}
+
+pub fn import_state(file: &Path) -> Result<()> {
+    let data = fs::read_to_string(file)
+        .map_err(|e| LauncherError::Config(format!("Failed to read {}: {}", file.display(), e)))?;
+    let bundle = Bundle::from_toml(&data)?;
+    bundle.apply()?;
+
+    println!(
+        "Imported config and state{}",
+        if bundle.usage.is_some() {
+            " with usage data"
+        } else {
+            ""
+        }
+    );
+    Ok(())
+}
+
+pub fn refresh() -> Result<()> {
+    let config = Config::load()?;
+    println!("Rescanning application directories…");
+    let entries = refresh_desktop_entries(&config)?;
+    println!("Indexed {} applications", entries.len());
+    Ok(())
+}
+
+pub fn completions(shell: Shell) -> Result<()> {
+    let mut command = Cli::command();
+    clap_complete::generate(shell, &mut command, "adwlauncher", &mut std::io::stdout());
+    Ok(())
+}
+
+pub fn generate_man() -> Result<()> {
+    let man = clap_mangen::Man::new(Cli::command());
+    man.render(&mut std::io::stdout())?;
+    Ok(())
+}
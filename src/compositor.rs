@@ -0,0 +1,122 @@
+use crate::config::{WindowRules, WindowScope};
+use crate::error::Result;
+use std::sync::Arc;
+use std::time::Duration;
+
+#[cfg(test)]
+pub mod mock;
+
+/// The compositor whose IPC lists windows and starts apps
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Compositor {
+    Niri,
+    Hyprland,
+}
+
+impl Compositor {
+    /// The one the launcher runs in, from the variables each sets for its clients.
+    /// niri wins when both are set, e.g. for a nested session. None outside both.
+    pub fn detect() -> Option<Self> {
+        if std::env::var_os("NIRI_SOCKET").is_some() {
+            Some(Compositor::Niri)
+        } else if std::env::var_os("HYPRLAND_INSTANCE_SIGNATURE").is_some() {
+            Some(Compositor::Hyprland)
+        } else {
+            None
+        }
+    }
+}
+
+/// A window as the compositor lists it
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Window {
+    /// How the compositor addresses it: niri's numeric ID or Hyprland's hex address
+    pub id: String,
+    /// Empty when the client didn't set one
+    pub app_id: String,
+    pub title: String,
+}
+
+/// A window opening or closing
+#[derive(Debug, Clone, PartialEq)]
+pub enum WindowEvent {
+    Opened(Window),
+    /// The window as it was last seen, closing only names it
+    Closed(Window),
+}
+
+/// Window events from the moment of subscribing, windows open before then
+/// don't count as opened
+pub trait Events: Send {
+    /// The next window opening or closing, waiting at most `timeout`, or however
+    /// long it takes without one. None when it passed, the stream can't be read from after that.
+    fn next(&mut self, timeout: Option<Duration>) -> Result<Option<WindowEvent>>;
+}
+
+/// What the launcher asks of the compositor it runs in. Windows are named by
+/// `Window::id`, and `description` completes "Failed to …" in errors.
+pub trait Backend: Send + Sync {
+    /// The compositor's name and version
+    fn version(&self) -> Result<String>;
+
+    /// Open windows, only those on the focused workspace or output when `scope` says so
+    fn windows(&self, scope: WindowScope) -> Result<Vec<Window>>;
+
+    fn focus_window(&self, id: &str) -> Result<()>;
+
+    fn close_window(&self, id: &str) -> Result<()>;
+
+    /// The process owning a window, None when the compositor doesn't know it
+    fn window_pid(&self, id: &str) -> Result<Option<u32>>;
+
+    /// Start a program, so it isn't the launcher's child and gets the session's environment
+    fn spawn(&self, command: &[String], description: &str) -> Result<()>;
+
+    /// Switch to a workspace by name, or by index when the name is a number
+    fn focus_workspace(&self, workspace: &str) -> Result<()>;
+
+    /// Apply an app's window rules to the window it opened
+    fn apply_window_rules(&self, id: &str, rules: &WindowRules) -> Result<()>;
+
+    fn log_out(&self) -> Result<()>;
+
+    /// The logical size of the focused output, where the launcher opens
+    fn focused_output_size(&self) -> Result<(i32, i32)>;
+
+    /// Subscribe to window events. Subscribe before launching, so a fast app's
+    /// window can't be mistaken for an old one.
+    fn events(&self) -> Result<Box<dyn Events>>;
+}
+
+/// The backend for the compositor the launcher runs in, None outside niri and Hyprland
+#[cfg(not(test))]
+pub fn backend() -> Option<Arc<dyn Backend>> {
+    static BACKEND: std::sync::OnceLock<Option<Arc<dyn Backend>>> = std::sync::OnceLock::new();
+    BACKEND
+        .get_or_init(|| {
+            let backend: Arc<dyn Backend> = match Compositor::detect()? {
+                Compositor::Niri => Arc::new(crate::niri::Niri::from_env()?),
+                Compositor::Hyprland => Arc::new(crate::hyprland::Hyprland::from_env()?),
+            };
+            Some(backend)
+        })
+        .clone()
+}
+
+/// Tests never reach the compositor the tests run in, only the one a test installs
+#[cfg(test)]
+pub fn backend() -> Option<Arc<dyn Backend>> {
+    TEST_BACKEND.with_borrow(Clone::clone)
+}
+
+#[cfg(test)]
+thread_local! {
+    static TEST_BACKEND: std::cell::RefCell<Option<Arc<dyn Backend>>> =
+        const { std::cell::RefCell::new(None) };
+}
+
+/// Make `backend()` return this on the current thread, so tests can run side by side
+#[cfg(test)]
+pub fn set_test_backend(backend: Option<Arc<dyn Backend>>) {
+    TEST_BACKEND.set(backend);
+}
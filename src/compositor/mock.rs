@@ -0,0 +1,175 @@
+//! A stand-in for the compositor's backend, so discovery and launching can be
+//! tested without a running compositor or its IPC. The flows behind the window's
+//! messages, e.g. searching and activating an entry, are tested on this level, the
+//! GTK window itself isn't driven.
+
+use super::{Backend, Events, Window, WindowEvent};
+use crate::config::{WindowRules, WindowScope};
+use crate::error::{LauncherError, Result};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::time::Duration;
+
+/// The first window opened by a spawn gets this ID, later ones count up from it
+pub const FIRST_SPAWNED_ID: u64 = 1000;
+
+/// What the launcher asked the compositor to do
+#[derive(Debug, Clone, PartialEq)]
+pub enum Call {
+    FocusWindow(String),
+    CloseWindow(String),
+    Spawn(Vec<String>),
+    FocusWorkspace(String),
+    ApplyWindowRules(String, WindowRules),
+    LogOut,
+}
+
+#[derive(Default)]
+struct State {
+    windows: Vec<Window>,
+    calls: Vec<Call>,
+    spawned: u64,
+    /// Event subscribers that haven't gone away
+    streams: Vec<Sender<WindowEvent>>,
+}
+
+impl State {
+    fn broadcast(&mut self, event: WindowEvent) {
+        self.streams
+            .retain(|stream| stream.send(event.clone()).is_ok());
+    }
+}
+
+struct MockBackend {
+    state: Mutex<State>,
+}
+
+impl MockBackend {
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn record(&self, call: Call) -> Result<()> {
+        self.lock().calls.push(call);
+        Ok(())
+    }
+}
+
+/// The compositor `compositor::backend()` returns on the current thread until dropped
+pub struct MockCompositor {
+    backend: Arc<MockBackend>,
+}
+
+impl MockCompositor {
+    /// Start with these windows open
+    pub fn start(windows: Vec<Window>) -> Self {
+        let backend = Arc::new(MockBackend {
+            state: Mutex::new(State {
+                windows,
+                ..Default::default()
+            }),
+        });
+        super::set_test_backend(Some(backend.clone()));
+        Self { backend }
+    }
+
+    /// Every call so far, in order
+    pub fn calls(&self) -> Vec<Call> {
+        self.backend.lock().calls.clone()
+    }
+}
+
+impl Drop for MockCompositor {
+    fn drop(&mut self) {
+        super::set_test_backend(None);
+    }
+}
+
+/// A window as the backend lists it
+pub fn window(id: u64, app_id: &str, title: &str) -> Window {
+    Window {
+        id: id.to_string(),
+        app_id: app_id.to_string(),
+        title: title.to_string(),
+    }
+}
+
+impl Backend for MockBackend {
+    fn version(&self) -> Result<String> {
+        Ok("mock".to_string())
+    }
+
+    /// Every window, the scope is left to the real backends
+    fn windows(&self, _scope: WindowScope) -> Result<Vec<Window>> {
+        Ok(self.lock().windows.clone())
+    }
+
+    fn focus_window(&self, id: &str) -> Result<()> {
+        self.record(Call::FocusWindow(id.to_string()))
+    }
+
+    fn close_window(&self, id: &str) -> Result<()> {
+        let mut state = self.lock();
+        state.calls.push(Call::CloseWindow(id.to_string()));
+        if let Some(index) = state.windows.iter().position(|window| window.id == id) {
+            let closed = state.windows.remove(index);
+            state.broadcast(WindowEvent::Closed(closed));
+        }
+        Ok(())
+    }
+
+    fn window_pid(&self, _id: &str) -> Result<Option<u32>> {
+        Ok(None)
+    }
+
+    /// The spawned app opens a window right away, named after the last argument
+    fn spawn(&self, command: &[String], _description: &str) -> Result<()> {
+        let mut state = self.lock();
+        state.calls.push(Call::Spawn(command.to_vec()));
+
+        let app_id = command.last().cloned().unwrap_or_default();
+        let opened = window(FIRST_SPAWNED_ID + state.spawned, &app_id, &app_id);
+        state.spawned += 1;
+        state.windows.push(opened.clone());
+        state.broadcast(WindowEvent::Opened(opened));
+        Ok(())
+    }
+
+    fn focus_workspace(&self, workspace: &str) -> Result<()> {
+        self.record(Call::FocusWorkspace(workspace.to_string()))
+    }
+
+    fn apply_window_rules(&self, id: &str, rules: &WindowRules) -> Result<()> {
+        self.record(Call::ApplyWindowRules(id.to_string(), rules.clone()))
+    }
+
+    fn log_out(&self) -> Result<()> {
+        self.record(Call::LogOut)
+    }
+
+    fn focused_output_size(&self) -> Result<(i32, i32)> {
+        Ok((1920, 1080))
+    }
+
+    fn events(&self) -> Result<Box<dyn Events>> {
+        let (sender, receiver) = mpsc::channel();
+        self.lock().streams.push(sender);
+        Ok(Box::new(MockEvents(receiver)))
+    }
+}
+
+struct MockEvents(Receiver<WindowEvent>);
+
+impl Events for MockEvents {
+    fn next(&mut self, timeout: Option<Duration>) -> Result<Option<WindowEvent>> {
+        let stopped = || LauncherError::Launch("The mock compositor stopped".to_string());
+        match timeout {
+            Some(timeout) => match self.0.recv_timeout(timeout) {
+                Ok(event) => Ok(Some(event)),
+                Err(RecvTimeoutError::Timeout) => Ok(None),
+                Err(RecvTimeoutError::Disconnected) => Err(stopped()),
+            },
+            None => self.0.recv().map(Some).map_err(|_| stopped()),
+        }
+    }
+}
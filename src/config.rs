@@ -0,0 +1,692 @@
+use crate::cli::Mode;
+use crate::error::{LauncherError, Result};
+use crate::profile;
+use crate::types::{Entry, OpenType};
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Position {
+    #[default]
+    Center,
+    Top,
+    Bottom,
+}
+
+impl Position {
+    pub const ALL: &'static [Position] = &[Position::Center, Position::Top, Position::Bottom];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Position::Center => "Center",
+            Position::Top => "Top",
+            Position::Bottom => "Bottom",
+        }
+    }
+}
+
+/// How the launcher presents its results
+#[derive(Serialize, Deserialize, ValueEnum, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Layout {
+    /// A window with the results listed below the search
+    #[default]
+    List,
+    /// A single row along the top edge, with the results as chips next to the search
+    Bar,
+    /// The whole screen, with the search centered at the top above pages of app tiles
+    Overview,
+}
+
+/// How large the window is, relative to the output it opens on
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum SizePreset {
+    /// `width` and `height`, in pixels
+    #[default]
+    Custom,
+    Small,
+    Medium,
+    Large,
+    /// The whole output, apart from panels that reserve space
+    Fullscreen,
+}
+
+impl SizePreset {
+    /// The share of the output's width and height the window takes, None for `Custom`
+    pub fn fraction(self) -> Option<(f64, f64)> {
+        match self {
+            SizePreset::Custom => None,
+            SizePreset::Small => Some((0.35, 0.4)),
+            SizePreset::Medium => Some((0.45, 0.55)),
+            SizePreset::Large => Some((0.6, 0.7)),
+            SizePreset::Fullscreen => Some((1.0, 1.0)),
+        }
+    }
+}
+
+/// Which windows the window results include
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum WindowScope {
+    #[default]
+    All,
+    /// Only those on the focused workspace
+    Workspace,
+    /// Only those on the focused monitor's workspaces
+    Output,
+}
+
+impl WindowScope {
+    pub fn label(&self) -> &'static str {
+        match self {
+            WindowScope::All => "All windows",
+            WindowScope::Workspace => "Windows on this workspace",
+            WindowScope::Output => "Windows on this monitor",
+        }
+    }
+
+    /// The scope the toggle key switches to
+    pub fn next(self) -> Self {
+        match self {
+            WindowScope::All => WindowScope::Workspace,
+            WindowScope::Workspace => WindowScope::Output,
+            WindowScope::Output => WindowScope::All,
+        }
+    }
+}
+
+/// Where manual pages open
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ManViewer {
+    /// `man` in the configured terminal, paged by `$MANPAGER` or `$PAGER`
+    #[default]
+    Terminal,
+    /// GNOME's help browser
+    Yelp,
+}
+
+/// Key bindings in GTK accelerator syntax, e.g. "<Control>k" or "Up"
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct Keybinds {
+    pub up: Vec<String>,
+    pub down: Vec<String>,
+    pub accept: Vec<String>,
+    pub cancel: Vec<String>,
+    pub close_window: Vec<String>,
+    pub kill_process: Vec<String>,
+    pub uninstall: Vec<String>,
+    pub next_page: Vec<String>,
+    pub previous_page: Vec<String>,
+    pub refresh: Vec<String>,
+    /// Type the selected text result into the previously focused window
+    pub type_text: Vec<String>,
+    /// Launch or focus the selected entry but keep the launcher open.
+    /// Plain Space would take the spaces out of searches, so it needs a modifier.
+    pub peek: Vec<String>,
+    /// Show the selected file result in a pane below the list
+    pub preview: Vec<String>,
+    /// Pick the app to open the selected file result with
+    pub open_with: Vec<String>,
+    /// Switch between all windows and those on the current workspace or monitor
+    pub window_scope: Vec<String>,
+}
+
+impl Default for Keybinds {
+    fn default() -> Self {
+        Self {
+            up: vec!["Up".to_string(), "k".to_string()],
+            down: vec!["Down".to_string(), "j".to_string()],
+            accept: vec!["Return".to_string(), "KP_Enter".to_string()],
+            cancel: vec!["Escape".to_string()],
+            close_window: vec!["<Control>w".to_string()],
+            kill_process: vec!["<Control>Delete".to_string()],
+            uninstall: vec!["<Shift>Delete".to_string()],
+            next_page: vec!["<Control>Page_Down".to_string()],
+            previous_page: vec!["<Control>Page_Up".to_string()],
+            refresh: vec!["<Control>r".to_string()],
+            type_text: vec!["<Shift>Return".to_string()],
+            peek: vec!["<Control>space".to_string()],
+            preview: vec!["<Control>p".to_string()],
+            open_with: vec!["<Control>o".to_string()],
+            window_scope: vec!["<Alt>w".to_string()],
+        }
+    }
+}
+
+/// Environment changes for launched apps
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(default)]
+pub struct EnvOverrides {
+    pub set: BTreeMap<String, String>,
+    pub unset: Vec<String>,
+}
+
+/// Environment changes applied to every launched app, plus per desktop file ID,
+/// e.g. `[env.apps."firefox.desktop"]`
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct Env {
+    #[serde(flatten)]
+    pub global: EnvOverrides,
+    pub apps: BTreeMap<String, EnvOverrides>,
+}
+
+impl Default for Env {
+    fn default() -> Self {
+        Self {
+            global: EnvOverrides {
+                set: BTreeMap::new(),
+                // Debugging and preload baggage shouldn't reach every app
+                unset: [
+                    "LD_PRELOAD",
+                    "GTK_DEBUG",
+                    "GDK_DEBUG",
+                    "GSK_DEBUG",
+                    "G_MESSAGES_DEBUG",
+                ]
+                .into_iter()
+                .map(String::from)
+                .collect(),
+            },
+            apps: BTreeMap::new(),
+        }
+    }
+}
+
+impl Env {
+    /// Prefix a command with `env` to apply the global and per-app changes,
+    /// followed by `extra` from the app's launch overrides. Later changes win,
+    /// so an app can unset a variable set globally and the other way around.
+    /// Commands are left alone when there is nothing to change. Unsets are always
+    /// passed on, apps get the compositor's environment and not the launcher's.
+    pub fn wrap_command(
+        &self,
+        app_id: &str,
+        extra: Option<&EnvOverrides>,
+        command: Vec<String>,
+    ) -> Vec<String> {
+        // None unsets the variable
+        let mut changes: BTreeMap<&str, Option<&str>> = BTreeMap::new();
+        for overrides in std::iter::once(&self.global)
+            .chain(self.apps.get(app_id))
+            .chain(extra)
+        {
+            for name in &overrides.unset {
+                changes.insert(name, None);
+            }
+            for (name, value) in &overrides.set {
+                changes.insert(name, Some(value));
+            }
+        }
+
+        if changes.is_empty() {
+            return command;
+        }
+
+        // env takes its options before the assignments
+        let mut wrapped = vec!["env".to_string()];
+        for (name, _) in changes.iter().filter(|(_, value)| value.is_none()) {
+            wrapped.push("-u".to_string());
+            wrapped.push(name.to_string());
+        }
+        for (name, value) in &changes {
+            if let Some(value) = value {
+                wrapped.push(format!("{}={}", name, value));
+            }
+        }
+        wrapped.extend(command);
+
+        wrapped
+    }
+}
+
+/// Launch settings for one desktop file ID, e.g. `[apps."steam.desktop"]`
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct AppOverride {
+    /// Command to run instead of the entry's Exec line
+    pub exec: Option<String>,
+    /// Run in the terminal or not, whatever the entry's Terminal key says
+    pub terminal: Option<bool>,
+    /// niri workspace name or index to switch to before launching
+    pub workspace: Option<String>,
+    pub env: EnvOverrides,
+    pub window: WindowRules,
+}
+
+/// Window rules for an app's new window, applied once it appears,
+/// e.g. `[apps."thunderbird.desktop".window]`
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(default)]
+pub struct WindowRules {
+    /// Open floating, or tiled when false
+    pub floating: Option<bool>,
+    /// Workspace name or index to move the window to, without following it there
+    pub workspace: Option<String>,
+    /// Width like `niri msg action set-window-width` takes it, e.g. "50%" or "800".
+    /// niri only.
+    pub column_width: Option<String>,
+}
+
+impl WindowRules {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+impl AppOverride {
+    /// The entry as it should be launched
+    pub fn apply(&self, entry: &Entry) -> Entry {
+        let mut entry = entry.clone();
+        if let Some(exec) = &self.exec {
+            entry.exec = exec.clone();
+        }
+        match self.terminal {
+            Some(true) => entry.open_type = OpenType::Terminal,
+            Some(false) => entry.open_type = OpenType::Graphical,
+            None => {}
+        }
+        entry
+    }
+}
+
+/// Shell commands run in the background around app launches, where `{id}`,
+/// `{name}` and `{exec}` stand for the launched entry
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct Hooks {
+    /// Started right before the app, without waiting for it to finish
+    pub pre_launch: Option<String>,
+    /// Started once the app was launched successfully
+    pub post_launch: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum RuleAction {
+    /// Leave the entries out of the results
+    #[default]
+    Hide,
+    /// List the entries after everything else
+    Demote,
+}
+
+/// Hides or demotes apps during a time window, e.g. games on weekdays from 9 to 17
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct EntryRule {
+    /// Desktop file IDs the rule applies to
+    pub apps: Vec<String>,
+    /// Days it applies on, e.g. ["mon", "tue"], every day when empty
+    pub days: Vec<String>,
+    /// Local time the rule starts at as "HH:MM", midnight when empty
+    pub from: String,
+    /// Local time the rule ends at, before `from` to span midnight
+    pub to: String,
+    pub action: RuleAction,
+}
+
+/// Lockdown for shared machines like lab computers
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct Kiosk {
+    /// Only list applications and offer no row or destructive actions
+    pub enabled: bool,
+    /// Desktop file IDs that may be listed, every application when empty
+    pub allowed_apps: Vec<String>,
+}
+
+impl Kiosk {
+    /// Whether an entry may be shown and launched
+    pub fn allows(&self, entry: &Entry) -> bool {
+        if !self.enabled {
+            return true;
+        }
+
+        // Shell history opens in a terminal too, only desktop files are applications
+        entry.id.ends_with(".desktop")
+            && matches!(entry.open_type, OpenType::Graphical | OpenType::Terminal)
+            && (self.allowed_apps.is_empty() || self.allowed_apps.contains(&entry.id))
+    }
+
+    /// Whether a mode may be shown. Shell history, variables and manual pages
+    /// would offer a way around the allowed apps.
+    pub fn allows_mode(&self, mode: Mode) -> bool {
+        !self.enabled || !matches!(mode, Mode::Run | Mode::Env | Mode::Man)
+    }
+}
+
+/// Which destructive actions ask for confirmation first
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct Confirm {
+    pub power: bool,
+    pub close_window: bool,
+    pub kill_process: bool,
+    pub uninstall: bool,
+}
+
+impl Default for Confirm {
+    fn default() -> Self {
+        Self {
+            power: true,
+            close_window: true,
+            kill_process: true,
+            uninstall: true,
+        }
+    }
+}
+
+/// How much a match in each desktop file field counts, relative to the others.
+/// A word matches where it scores highest, 0 leaves a field out of the search.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(default)]
+pub struct FieldWeights {
+    pub name: f64,
+    pub generic_name: f64,
+    pub keywords: f64,
+    pub comment: f64,
+}
+
+impl Default for FieldWeights {
+    fn default() -> Self {
+        Self {
+            name: 1.0,
+            generic_name: 0.8,
+            keywords: 0.7,
+            comment: 0.3,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct Config {
+    /// Command prefix used to run `Terminal=true` entries, e.g. "kitty -e"
+    pub terminal: String,
+    pub position: Position,
+    /// `bar` ignores the position, width and height, `overview` also the size
+    pub layout: Layout,
+    /// Anything but `custom` sizes the window from the focused output and ignores width and height
+    pub size: SizePreset,
+    pub width: i32,
+    pub height: i32,
+    /// Pixel size of the icons in the result list
+    pub icon_size: i32,
+    /// Set to false for a text-only list
+    pub show_icons: bool,
+    /// Action buttons on the selected or hovered row, e.g. run in terminal
+    pub row_actions: bool,
+    /// List apps in collapsible sections by category until something is typed
+    pub group_by_category: bool,
+    /// Leave out windows on other workspaces or monitors, for many persistent windows
+    pub window_scope: WindowScope,
+    /// Bigger rows, a close button and room for an on-screen keyboard
+    pub touch: bool,
+    /// Refuse to launch the same app again within this many seconds unless
+    /// Shift is held, 0 to always allow it
+    pub relaunch_guard_secs: u64,
+    /// Keep the launcher up briefly after a launch with an "Undo" button
+    /// that closes the new window again
+    pub undo_launch: bool,
+    pub usage_tracking: bool,
+    /// Modes whose launches stay out of the usage data even with `usage_tracking`,
+    /// e.g. commands from the shell history that may contain secrets
+    pub untracked_modes: Vec<Mode>,
+    /// Searched for .desktop files after the standard directories, e.g. "/opt/apps/share/applications"
+    pub app_directories: Vec<PathBuf>,
+    /// How long to wait for application directories, so a hung network
+    /// mount can't freeze discovery
+    pub scan_timeout_ms: u64,
+    /// Compress the application cache, which helps with many Flatpak apps
+    pub compress_cache: bool,
+    /// Megabytes the service may keep slow sources like the games in memory, 0 to
+    /// always scan them again
+    pub index_budget_mb: usize,
+    /// Entries the service may keep in memory across those sources, 0 for no
+    /// limit besides `index_budget_mb`
+    pub index_max_entries: usize,
+    /// Match case-sensitively only when the query contains capitals,
+    /// otherwise case is always ignored
+    pub smart_case: bool,
+    /// Match accented letters by their base letter, e.g. "uber" finds "Über"
+    pub fold_diacritics: bool,
+    /// Weights of the fields searched besides the name, e.g. "browser" finds
+    /// Firefox through its generic name
+    pub search_weights: FieldWeights,
+    /// List Windows programs installed under Wine, Bottles or Lutris
+    pub wine_apps: bool,
+    /// Move through the games grid with a game controller's d-pad or left stick
+    pub gamepad: bool,
+    /// Open manual pages from the `man` prefix in the terminal or in Yelp
+    pub man_viewer: ManViewer,
+    /// Report background errors as desktop notifications, not just on stderr
+    pub notifications: bool,
+    /// Start the service again after it crashed
+    pub restart_on_crash: bool,
+    /// Keep the screen from blanking or locking while the launcher is shown
+    pub inhibit_idle: bool,
+    /// Hide the launcher and forget its results when the session locks or suspends
+    pub hide_on_lock: bool,
+    /// Let the launcher read icons, previews, thumbnails and browser profiles from
+    /// network filesystems. Apps it launches and launch hooks aren't affected.
+    pub network: bool,
+    /// How many of the most used apps to show above the list, 0 to hide them
+    pub top_apps: usize,
+    /// How many recent launches, of apps, files or commands, to list first, 0 to hide the section
+    pub recent_apps: usize,
+    /// Show a small bar on rows of launched entries, fuller the more they are used
+    pub usage_indicators: bool,
+    /// Show the number of results and how long the search took under the search field
+    pub result_stats: bool,
+    /// How many apps whose windows were closed to offer reopening, 0 to hide the section
+    pub recently_closed: usize,
+    /// Label shown in front of the search entry
+    pub prompt: Option<String>,
+    /// Icon name or path shown in front of the prompt
+    pub prompt_icon: Option<String>,
+    pub keybinds: Keybinds,
+    pub confirm: Confirm,
+    pub env: Env,
+    pub hooks: Hooks,
+    pub kiosk: Kiosk,
+    /// Named groups of desktop file IDs, shown as one entry until something is typed
+    pub folders: BTreeMap<String, Vec<String>>,
+    /// Launch overrides by desktop file ID
+    pub apps: BTreeMap<String, AppOverride>,
+    /// Time based rules, checked whenever the results are filtered
+    pub rules: Vec<EntryRule>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            terminal: "ghostty -c".to_string(),
+            position: Position::Center,
+            layout: Layout::List,
+            size: SizePreset::Custom,
+            width: 800,
+            height: 600,
+            icon_size: 32,
+            show_icons: true,
+            row_actions: true,
+            group_by_category: false,
+            window_scope: WindowScope::All,
+            touch: false,
+            relaunch_guard_secs: 0,
+            undo_launch: false,
+            usage_tracking: true,
+            untracked_modes: vec![Mode::Run],
+            app_directories: vec![],
+            scan_timeout_ms: 2000,
+            compress_cache: true,
+            index_budget_mb: 0,
+            index_max_entries: 0,
+            smart_case: true,
+            fold_diacritics: true,
+            search_weights: FieldWeights::default(),
+            wine_apps: true,
+            gamepad: true,
+            man_viewer: ManViewer::Terminal,
+            notifications: true,
+            restart_on_crash: false,
+            inhibit_idle: false,
+            hide_on_lock: true,
+            network: false,
+            top_apps: 8,
+            recent_apps: 5,
+            usage_indicators: true,
+            result_stats: true,
+            recently_closed: 5,
+            prompt: None,
+            prompt_icon: None,
+            keybinds: Keybinds::default(),
+            confirm: Confirm::default(),
+            env: Env::default(),
+            hooks: Hooks::default(),
+            kiosk: Kiosk::default(),
+            folders: BTreeMap::new(),
+            apps: BTreeMap::new(),
+            rules: vec![],
+        }
+    }
+}
+
+impl Config {
+    pub fn load() -> Result<Self> {
+        let path = Self::get_storage_path()?;
+
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let data = fs::read_to_string(&path)?;
+        toml::from_str(&data)
+            .map_err(|e| LauncherError::Config(format!("Failed to parse config: {}", e)))
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let path = Self::get_storage_path()?;
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let data = toml::to_string_pretty(self)
+            .map_err(|e| LauncherError::Config(format!("Failed to serialize config: {}", e)))?;
+
+        fs::write(&path, data)?;
+        Ok(())
+    }
+
+    /// Whether a config file has been written yet
+    pub fn exists() -> bool {
+        Self::get_storage_path()
+            .map(|path| path.exists())
+            .unwrap_or(false)
+    }
+
+    /// Icon size for the result list, or None when icons are hidden
+    pub fn list_icon_size(&self) -> Option<i32> {
+        self.show_icons.then_some(self.icon_size)
+    }
+
+    /// Split the terminal setting into the program and its arguments
+    pub fn terminal_command(&self) -> Vec<String> {
+        self.terminal
+            .split_whitespace()
+            .map(|s| s.to_string())
+            .collect()
+    }
+
+    pub fn get_storage_path() -> Result<PathBuf> {
+        let config_dir = dirs::config_dir().ok_or_else(|| {
+            LauncherError::Io(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                "Could not find config directory",
+            ))
+        })?;
+
+        Ok(config_dir.join(profile::directory()).join("config.toml"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn overrides(set: &[(&str, &str)], unset: &[&str]) -> EnvOverrides {
+        EnvOverrides {
+            set: set
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect(),
+            unset: unset.iter().map(|name| name.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn app_env_changes_win_over_global_ones() {
+        let mut env = Env {
+            global: overrides(
+                &[("GDK_SCALE", "2"), ("QT_SCALE_FACTOR", "1")],
+                &["PATH", "LD_PRELOAD"],
+            ),
+            apps: BTreeMap::new(),
+        };
+        env.apps.insert(
+            "firefox.desktop".to_string(),
+            overrides(&[("PATH", "/opt/firefox")], &["GDK_SCALE"]),
+        );
+        let command = || vec!["firefox".to_string()];
+
+        // Unsets don't depend on the launcher's own environment
+        assert_eq!(
+            env.wrap_command("other.desktop", None, command()),
+            [
+                "env",
+                "-u",
+                "LD_PRELOAD",
+                "-u",
+                "PATH",
+                "GDK_SCALE=2",
+                "QT_SCALE_FACTOR=1",
+                "firefox"
+            ]
+        );
+        let launch = overrides(&[], &["QT_SCALE_FACTOR"]);
+        assert_eq!(
+            env.wrap_command("firefox.desktop", Some(&launch), command()),
+            [
+                "env",
+                "-u",
+                "GDK_SCALE",
+                "-u",
+                "LD_PRELOAD",
+                "-u",
+                "QT_SCALE_FACTOR",
+                "PATH=/opt/firefox",
+                "firefox"
+            ]
+        );
+    }
+
+    #[test]
+    fn commands_without_changes_are_left_alone() {
+        let env = Env {
+            global: overrides(&[], &[]),
+            apps: BTreeMap::new(),
+        };
+        assert_eq!(
+            env.wrap_command("foot.desktop", None, vec!["foot".to_string()]),
+            ["foot"]
+        );
+    }
+}
@@ -0,0 +1,130 @@
+use crate::profile;
+use gtk4_layer_shell::{KeyboardMode, LayerShell};
+use relm4::gtk::glib;
+use relm4::gtk::prelude::*;
+use std::cell::RefCell;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::os::unix::process::CommandExt;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+
+/// A service crashing sooner than this after starting would only crash again
+const MIN_UPTIME_FOR_RESTART: Duration = Duration::from_secs(10);
+/// The old process has to release its D-Bus name before the new one can take it
+const RESTART_DELAY_SECS: u32 = 1;
+
+static STARTED: OnceLock<Instant> = OnceLock::new();
+
+thread_local! {
+    static WINDOW: RefCell<Option<relm4::gtk::Window>> = const { RefCell::new(None) };
+}
+
+/// Log panics to crash.log in the cache directory, and start the service
+/// again after a crash of the main thread when `restart` is set
+pub fn install(restart: bool) {
+    STARTED.get_or_init(Instant::now);
+    let default_hook = std::panic::take_hook();
+
+    std::panic::set_hook(Box::new(move |info| {
+        default_hook(info);
+        // Panics elsewhere only end their own thread, the window keeps working
+        let fatal = glib::MainContext::default().is_owner();
+        if fatal {
+            release_keyboard();
+        }
+
+        match log_path() {
+            Some(path) => match write_log(&path, info) {
+                Ok(()) => eprintln!("Crash details were written to {}", path.display()),
+                Err(e) => eprintln!("Failed to write {}: {}", path.display(), e),
+            },
+            None => eprintln!("Could not find the cache directory for the crash log"),
+        }
+
+        if restart && fatal {
+            restart_service();
+        }
+    }));
+}
+
+/// The layer surface whose exclusive keyboard grab must not outlive a crash
+pub fn set_window(window: &impl IsA<relm4::gtk::Window>) {
+    WINDOW.with(|cell| *cell.borrow_mut() = Some(window.clone().upcast()));
+}
+
+/// A panic on the main thread aborts at the GTK boundary, and the compositor
+/// keeps sending every key to the surface until the process is gone, e.g. while a
+/// core dump is written. Dropping the grab first gives the keyboard back right away.
+fn release_keyboard() {
+    let _ = WINDOW.try_with(|cell| {
+        if let Ok(window) = cell.try_borrow()
+            && let Some(window) = window.as_ref()
+        {
+            if gtk4_layer_shell::is_supported() {
+                window.set_keyboard_mode(KeyboardMode::None);
+            }
+            window.set_visible(false);
+        }
+    });
+}
+
+fn log_path() -> Option<PathBuf> {
+    Some(
+        dirs::cache_dir()?
+            .join(profile::directory())
+            .join("crash.log"),
+    )
+}
+
+fn write_log(path: &Path, info: &std::panic::PanicHookInfo) -> std::io::Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+
+    let time = glib::DateTime::now_local()
+        .ok()
+        .and_then(|now| now.format_iso8601().ok())
+        .map(|time| time.to_string())
+        .unwrap_or_default();
+    let thread = std::thread::current();
+
+    writeln!(
+        file,
+        "{} adwlauncher {} panicked in thread '{}': {}",
+        time,
+        env!("CARGO_PKG_VERSION"),
+        thread.name().unwrap_or("<unnamed>"),
+        info
+    )?;
+    writeln!(file, "{}\n", std::backtrace::Backtrace::force_capture())
+}
+
+/// Start the same command again once this process is gone, unless it keeps crashing
+fn restart_service() {
+    let uptime = STARTED.get().map(Instant::elapsed).unwrap_or_default();
+    if uptime < MIN_UPTIME_FOR_RESTART {
+        eprintln!("Not restarting, the launcher crashed right after starting");
+        return;
+    }
+    let Ok(exe) = std::env::current_exe() else {
+        return;
+    };
+
+    // Detached through sh, so the restart survives this process exiting
+    let result = Command::new("sh")
+        .arg("-c")
+        .arg(format!("sleep {}; exec \"$0\" \"$@\"", RESTART_DELAY_SECS))
+        .arg(exe)
+        .args(std::env::args_os().skip(1))
+        .stdin(Stdio::null())
+        .process_group(0)
+        .spawn();
+    match result {
+        Ok(_) => eprintln!("Restarting the launcher"),
+        Err(e) => eprintln!("Failed to restart the launcher: {}", e),
+    }
+}
@@ -0,0 +1,254 @@
+use crate::app_discovery;
+use crate::cache::{self, Cache};
+use crate::compositor::{self, Compositor};
+use crate::config::Config;
+use crate::error::Result;
+use crate::icon;
+use crate::index;
+use crate::usage::UsageTracker;
+
+/// Prints check results as they come in and counts the failures
+#[derive(Default)]
+struct Report {
+    failures: usize,
+}
+
+impl Report {
+    fn ok(&mut self, name: &str, detail: &str) {
+        println!("ok    {}: {}", name, detail);
+    }
+
+    fn warn(&mut self, name: &str, detail: &str, hint: &str) {
+        println!("warn  {}: {}", name, detail);
+        println!("      {}", hint);
+    }
+
+    fn fail(&mut self, name: &str, detail: &str, hint: &str) {
+        self.failures += 1;
+        println!("FAIL  {}: {}", name, detail);
+        println!("      {}", hint);
+    }
+}
+
+/// Check everything the launcher depends on, returning whether all checks passed
+pub fn run() -> Result<bool> {
+    let mut report = Report::default();
+
+    let config = check_config(&mut report);
+    check_compositor(&mut report);
+    check_app_directories(&mut report, &config);
+    check_cache(&mut report, &config);
+    check_index(&mut report, &config);
+    check_usage(&mut report);
+    check_terminal(&mut report, &config);
+    check_icons(&mut report);
+    check_network(&mut report, &config);
+
+    if report.failures == 0 {
+        println!("\nEverything looks good");
+    } else {
+        println!("\n{} check(s) failed", report.failures);
+    }
+
+    Ok(report.failures == 0)
+}
+
+fn check_config(report: &mut Report) -> Config {
+    let path = Config::get_storage_path()
+        .map(|path| path.display().to_string())
+        .unwrap_or_default();
+
+    match Config::load() {
+        Ok(config) if Config::exists() => {
+            report.ok("config", &format!("loaded {}", path));
+            config
+        }
+        Ok(config) => {
+            report.ok("config", "no config file, using defaults");
+            config
+        }
+        Err(e) => {
+            report.fail(
+                "config",
+                &format!("{:#}", e),
+                &format!("Fix or remove {}; the remaining checks use defaults", path),
+            );
+            Config::default()
+        }
+    }
+}
+
+fn check_compositor(report: &mut Report) {
+    let hint = match Compositor::detect() {
+        Some(Compositor::Niri) => "Check that NIRI_SOCKET points at the running niri instance",
+        Some(Compositor::Hyprland) => {
+            "Check that HYPRLAND_INSTANCE_SIGNATURE names the running Hyprland instance"
+        }
+        None => {
+            return report.warn(
+                "compositor",
+                "neither NIRI_SOCKET nor HYPRLAND_INSTANCE_SIGNATURE is set",
+                "Outside niri and Hyprland apps are started directly and windows can't be listed",
+            );
+        }
+    };
+
+    let version = compositor::backend().map(|backend| backend.version());
+    match version {
+        Some(Ok(version)) => report.ok("compositor", &format!("connected to {}", version)),
+        Some(Err(e)) => report.fail("compositor", &format!("{:#}", e), hint),
+        None => report.fail("compositor", "can't find its socket", hint),
+    }
+}
+
+fn check_app_directories(report: &mut Report, config: &Config) {
+    let mut found_any = false;
+
+    // Missing directories are normal, e.g. without Flatpak
+    for dir in cache::get_app_directories(&config.app_directories)
+        .iter()
+        .filter(|dir| dir.exists())
+    {
+        found_any = true;
+
+        match std::fs::read_dir(dir) {
+            Ok(entries) => {
+                let count = entries
+                    .filter_map(|entry| entry.ok())
+                    .filter(|entry| entry.path().extension().is_some_and(|ext| ext == "desktop"))
+                    .count();
+                report.ok(
+                    "applications",
+                    &format!("{} desktop files in {}", count, dir.display()),
+                );
+            }
+            Err(e) => report.fail(
+                "applications",
+                &format!("can't read {}: {}", dir.display(), e),
+                "Check the directory's permissions",
+            ),
+        }
+    }
+
+    if !found_any {
+        report.fail(
+            "applications",
+            "none of the application directories exist",
+            "Install some applications, or check that HOME is set",
+        );
+    }
+}
+
+fn check_cache(report: &mut Report, config: &Config) {
+    let cache = match Cache::new(config.compress_cache) {
+        Ok(cache) => cache,
+        Err(e) => {
+            report.fail(
+                "cache",
+                &format!("{:#}", e),
+                "Check that the cache directory is writable",
+            );
+            return;
+        }
+    };
+
+    let timestamps =
+        cache::collect_directory_timestamps(&cache::get_app_directories(&config.app_directories));
+
+    match cache.load() {
+        Ok(data) if cache.is_valid(&data, &timestamps) => report.ok(
+            "cache",
+            &format!("{} entries, up to date", data.entries.len()),
+        ),
+        Ok(_) => report.ok("cache", "stale, will be rebuilt on the next start"),
+        Err(e) => report.warn(
+            "cache",
+            &format!("{:#}", e),
+            "The cache is rebuilt automatically; if this persists, delete it",
+        ),
+    }
+}
+
+fn check_index(report: &mut Report, config: &Config) {
+    if config.index_budget_mb == 0 {
+        report.ok("index", "off");
+        return;
+    }
+
+    match index::read_stats() {
+        Some(stats) => {
+            let sources = stats
+                .sources
+                .iter()
+                .map(|source| format!("{} {}", source.entries, source.name))
+                .collect::<Vec<String>>()
+                .join(", ");
+            let entries = if stats.max_entries == 0 {
+                String::new()
+            } else {
+                let used: usize = stats.sources.iter().map(|source| source.entries).sum();
+                format!(", {} of {} entries", used, stats.max_entries)
+            };
+            report.ok(
+                "index",
+                &format!(
+                    "{} KiB of {} MiB in use{}{}",
+                    stats.used_bytes / 1024,
+                    stats.budget_bytes / (1024 * 1024),
+                    entries,
+                    if sources.is_empty() {
+                        String::new()
+                    } else {
+                        format!(" ({})", sources)
+                    }
+                ),
+            );
+        }
+        None => report.ok(
+            "index",
+            "empty, the service fills it the first time games are shown",
+        ),
+    }
+}
+
+fn check_usage(report: &mut Report) {
+    match UsageTracker::load() {
+        Ok(_) => report.ok("usage data", "readable"),
+        Err(e) => report.fail(
+            "usage data",
+            &format!("{:#}", e),
+            "Delete usage.dat in the adwlauncher cache directory to reset it",
+        ),
+    }
+}
+
+fn check_terminal(report: &mut Report, config: &Config) {
+    match app_discovery::check_spawnable(&config.terminal_command()) {
+        Ok(()) => report.ok("terminal", &config.terminal),
+        Err(e) => report.fail(
+            "terminal",
+            &format!("{:#}", e),
+            "Set `terminal` in config.toml to an installed terminal, e.g. \"kitty -e\"",
+        ),
+    }
+}
+
+fn check_network(report: &mut Report, config: &Config) {
+    report.ok("network", if config.network { "allowed" } else { "off" });
+}
+
+fn check_icons(report: &mut Report) {
+    let themes = icon::icon_themes().join(", ");
+
+    // Any reasonably complete theme has this one
+    match icon::resolve_icon_path("utilities-terminal") {
+        Some(path) if path.starts_with('/') => {
+            report.ok("icons", &format!("resolving from {}", themes))
+        }
+        _ => report.warn(
+            "icons",
+            &format!("no common icons found in {}", themes),
+            "Install an icon theme such as adwaita-icon-theme",
+        ),
+    }
+}
@@ -0,0 +1,95 @@
+use crate::app_discovery;
+use crate::state::State;
+use crate::types::{Entry, OpenType};
+use std::collections::{BTreeMap, HashMap};
+
+/// Remember which copy of an app installed twice is the one in use. Returns
+/// whether the entry has such a twin.
+pub fn record_launch(entry: &Entry, entries: &[Entry]) -> bool {
+    if !has_twin(entry, entries) {
+        return false;
+    }
+    State::update(|state| {
+        state
+            .preferred_copies
+            .insert(entry.name.clone(), entry.id.clone());
+    });
+    true
+}
+
+/// Whether another app has the same name but comes from a different source,
+/// e.g. the Flatpak of a natively installed app
+fn has_twin(entry: &Entry, entries: &[Entry]) -> bool {
+    is_app(entry)
+        && entries.iter().any(|other| {
+            is_app(other)
+                && other.name.eq_ignore_ascii_case(&entry.name)
+                && is_flatpak(other) != is_flatpak(entry)
+        })
+}
+
+/// Where an app installed both natively and as a Flatpak matched twice, put the copy
+/// launched last first, or the native one when neither was. Returns a badge naming
+/// the origin of each other copy by entry ID, so the two can be told apart.
+pub fn prefer_launched(
+    ranked: &mut [(f64, Entry)],
+    launched: &BTreeMap<String, String>,
+) -> HashMap<String, &'static str> {
+    let mut copies: HashMap<String, Vec<usize>> = HashMap::new();
+    for (index, (_, entry)) in ranked.iter().enumerate() {
+        if is_app(entry) {
+            copies
+                .entry(entry.name.to_lowercase())
+                .or_default()
+                .push(index);
+        }
+    }
+
+    let mut badges = HashMap::new();
+    let mut swaps = vec![];
+    for indices in copies.values() {
+        let mixed = indices.iter().any(|&index| is_flatpak(&ranked[index].1))
+            && indices.iter().any(|&index| !is_flatpak(&ranked[index].1));
+        if !mixed {
+            continue;
+        }
+
+        let name = &ranked[indices[0]].1.name;
+        let preferred = indices
+            .iter()
+            .copied()
+            .find(|&index| launched.get(name) == Some(&ranked[index].1.id))
+            .or_else(|| {
+                indices
+                    .iter()
+                    .copied()
+                    .find(|&index| !is_flatpak(&ranked[index].1))
+            })
+            .unwrap_or(indices[0]);
+
+        for &index in indices.iter().filter(|&&index| index != preferred) {
+            let entry = &ranked[index].1;
+            let origin = if is_flatpak(entry) {
+                "Flatpak"
+            } else {
+                "Native"
+            };
+            badges.insert(entry.id.clone(), origin);
+        }
+        swaps.push((preferred, indices[0]));
+    }
+
+    // The preferred copy takes the place of the best ranked one
+    for (preferred, first) in swaps {
+        ranked.swap(preferred, first);
+    }
+    badges
+}
+
+fn is_app(entry: &Entry) -> bool {
+    matches!(entry.open_type, OpenType::Graphical | OpenType::Terminal)
+}
+
+fn is_flatpak(entry: &Entry) -> bool {
+    app_discovery::flatpak_app_id(entry).is_some()
+}
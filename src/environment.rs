@@ -0,0 +1,41 @@
+use crate::types::{Entry, OpenType};
+
+/// Longer values are cut short in the list, copying gives the whole value
+const MAX_SHOWN_CHARS: usize = 120;
+
+/// The launcher's environment variables, sorted by name. Started from niri's
+/// config or the session, it has the environment that launched apps get.
+pub fn get_variable_entries() -> Vec<Entry> {
+    let mut variables: Vec<(String, String)> = std::env::vars_os()
+        .map(|(name, value)| {
+            (
+                name.to_string_lossy().to_string(),
+                value.to_string_lossy().to_string(),
+            )
+        })
+        .collect();
+    variables.sort();
+
+    variables
+        .into_iter()
+        .map(|(name, value)| {
+            let shown = match value.char_indices().nth(MAX_SHOWN_CHARS) {
+                Some((end, _)) => format!("{}…", &value[..end]),
+                None => value.clone(),
+            };
+            Entry {
+                id: format!("env-{}", name),
+                name: format!("{}={}", name, shown),
+                exec: value,
+                icon: "utilities-terminal-symbolic".to_string(),
+                open_type: OpenType::Variable,
+                ..Default::default()
+            }
+        })
+        .collect()
+}
+
+/// "PATH" for the entry of $PATH
+pub fn variable_name(entry: &Entry) -> &str {
+    entry.id.strip_prefix("env-").unwrap_or(&entry.id)
+}
@@ -4,17 +4,79 @@ use std::fmt;
 pub enum LauncherError {
     NiriConnection(String),
     NiriRequest(String),
+    /// niri took too long to answer, so it is probably stuck
+    NiriTimeout(String),
+    HyprlandConnection(String),
+    HyprlandRequest(String),
+    /// Hyprland took too long to answer
+    HyprlandTimeout(String),
     DesktopEntry(String),
+    Config(String),
+    Launch(String),
+    /// The program a command would run isn't installed or not executable
+    ExecNotFound(String),
+    /// The application cache can't be read back
+    CacheCorrupt(String),
     Io(std::io::Error),
     ParseInt(std::num::ParseIntError),
 }
 
+impl LauncherError {
+    /// A stable name for the kind of error, for logs and bug reports.
+    /// These are never renamed, new kinds get new codes.
+    pub fn code(&self) -> &'static str {
+        match self {
+            LauncherError::NiriConnection(_) => "compositor-unavailable",
+            LauncherError::NiriRequest(_) => "compositor-request-failed",
+            LauncherError::NiriTimeout(_) => "compositor-timeout",
+            LauncherError::HyprlandConnection(_) => "compositor-unavailable",
+            LauncherError::HyprlandRequest(_) => "compositor-request-failed",
+            LauncherError::HyprlandTimeout(_) => "compositor-timeout",
+            LauncherError::DesktopEntry(_) => "desktop-entry-invalid",
+            LauncherError::Config(_) => "config-invalid",
+            LauncherError::Launch(_) => "launch-failed",
+            LauncherError::ExecNotFound(_) => "exec-not-found",
+            LauncherError::CacheCorrupt(_) => "cache-corrupt",
+            LauncherError::Io(_) => "io",
+            LauncherError::ParseInt(_) => "parse-failed",
+        }
+    }
+
+    /// Whether niri or Hyprland didn't answer in time, as opposed to refusing
+    pub fn is_compositor_timeout(&self) -> bool {
+        matches!(
+            self,
+            LauncherError::NiriTimeout(_) | LauncherError::HyprlandTimeout(_)
+        )
+    }
+}
+
+/// `{:#}` puts the code in front, e.g. "[exec-not-found] Launch error: foo not found",
+/// plain `{}` is the message alone, as shown in the window
 impl fmt::Display for LauncherError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if f.alternate() {
+            write!(f, "[{}] ", self.code())?;
+        }
+
         match self {
             LauncherError::NiriConnection(msg) => write!(f, "Niri connection error: {}", msg),
             LauncherError::NiriRequest(msg) => write!(f, "Niri request error: {}", msg),
+            LauncherError::NiriTimeout(msg) => write!(f, "Niri isn't responding: {}", msg),
+            LauncherError::HyprlandConnection(msg) => {
+                write!(f, "Hyprland connection error: {}", msg)
+            }
+            LauncherError::HyprlandRequest(msg) => write!(f, "Hyprland request error: {}", msg),
+            LauncherError::HyprlandTimeout(msg) => {
+                write!(f, "Hyprland isn't responding: {}", msg)
+            }
             LauncherError::DesktopEntry(msg) => write!(f, "Desktop entry error: {}", msg),
+            LauncherError::Config(msg) => write!(f, "Config error: {}", msg),
+            LauncherError::Launch(msg) => write!(f, "Launch error: {}", msg),
+            LauncherError::ExecNotFound(program) => {
+                write!(f, "Launch error: {} not found", program)
+            }
+            LauncherError::CacheCorrupt(msg) => write!(f, "Cache error: {}", msg),
             LauncherError::Io(err) => write!(f, "IO error: {}", err),
             LauncherError::ParseInt(err) => write!(f, "Parse error: {}", err),
         }
@@ -0,0 +1,225 @@
+use crate::types::Entry;
+use std::path::Path;
+
+/// What the field codes of an Exec line expand to. Files and URLs are never
+/// passed, so %f, %F, %u and %U expand to nothing.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Context<'a> {
+    /// The app's name, for %c
+    pub name: &'a str,
+    /// The app's icon, for %i
+    pub icon: &'a str,
+    /// Where the desktop file is, for %k
+    pub desktop_file: Option<&'a Path>,
+}
+
+impl<'a> Context<'a> {
+    pub fn for_entry(entry: &'a Entry, desktop_file: Option<&'a Path>) -> Self {
+        Self {
+            name: &entry.name,
+            icon: &entry.icon,
+            desktop_file,
+        }
+    }
+}
+
+/// An argument while it is being read
+#[derive(Default)]
+struct Argument {
+    text: String,
+    /// Part of it was in double quotes, so it is kept even when empty
+    quoted: bool,
+    /// It held %i, which stands for two arguments of its own
+    icon: bool,
+    /// It held a file or URL code, which has nothing to expand to
+    file: bool,
+}
+
+/// Split an Exec line into arguments following the Desktop Entry Specification.
+/// Double quoted text is kept together and taken literally apart from backslash
+/// escapes, outside of quotes field codes are expanded and `%%` is a percent sign.
+pub fn arguments(exec: &str, context: &Context) -> Vec<String> {
+    let mut arguments = vec![];
+    let mut current: Option<Argument> = None;
+    let mut in_quotes = false;
+    let mut chars = exec.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => {
+                in_quotes = !in_quotes;
+                current.get_or_insert_with(Default::default).quoted = true;
+            }
+            '\\' => {
+                if let Some(escaped) = chars.next() {
+                    current
+                        .get_or_insert_with(Default::default)
+                        .text
+                        .push(escaped);
+                }
+            }
+            c if c.is_whitespace() && !in_quotes => {
+                finish(&mut arguments, current.take(), context);
+            }
+            // A percent sign before anything but a letter isn't a field code
+            '%' if !in_quotes
+                && chars
+                    .peek()
+                    .is_some_and(|next| *next == '%' || next.is_ascii_alphabetic()) =>
+            {
+                let argument = current.get_or_insert_with(Default::default);
+                match chars.next() {
+                    Some('%') => argument.text.push('%'),
+                    Some('c') => argument.text.push_str(context.name),
+                    Some('k') => {
+                        if let Some(path) = context.desktop_file {
+                            argument.text.push_str(&path.to_string_lossy());
+                        }
+                    }
+                    Some('i') => argument.icon = true,
+                    Some('f' | 'F' | 'u' | 'U') => argument.file = true,
+                    // Deprecated codes like %d and %m, and unknown ones, are dropped
+                    _ => {}
+                }
+            }
+            c => current.get_or_insert_with(Default::default).text.push(c),
+        }
+    }
+    finish(&mut arguments, current, context);
+
+    arguments
+}
+
+fn finish(arguments: &mut Vec<String>, argument: Option<Argument>, context: &Context) {
+    let Some(argument) = argument else {
+        return;
+    };
+
+    // Without files to open, e.g. "--open=%f" would be left asking for one
+    if argument.file {
+        return;
+    }
+    // "%i" becomes "--icon <Icon>", or nothing without an icon
+    if argument.icon && argument.text.is_empty() && !argument.quoted {
+        if !context.icon.is_empty() {
+            arguments.push("--icon".to_string());
+            arguments.push(context.icon.to_string());
+        }
+        return;
+    }
+    // Field codes that expanded to nothing leave no empty argument behind
+    if argument.text.is_empty() && !argument.quoted {
+        return;
+    }
+    arguments.push(argument.text);
+}
+
+/// Double quote an argument for an Exec line, where quoted arguments keep
+/// their percent signs and escaped characters
+pub fn quote_argument(argument: &str) -> String {
+    let mut quoted = String::from('"');
+    for c in argument.chars() {
+        if matches!(c, '"' | '\\' | '`' | '$') {
+            quoted.push('\\');
+        }
+        quoted.push(c);
+    }
+    quoted.push('"');
+    quoted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn split(exec: &str) -> Vec<String> {
+        let context = Context {
+            name: "Text Editor",
+            icon: "org.gnome.TextEditor",
+            desktop_file: Some(Path::new(
+                "/usr/share/applications/org.gnome.TextEditor.desktop",
+            )),
+        };
+        arguments(exec, &context)
+    }
+
+    #[test]
+    fn file_and_url_codes_are_dropped() {
+        assert_eq!(split("gedit %U"), ["gedit"]);
+        assert_eq!(split("gedit %f --new-window"), ["gedit", "--new-window"]);
+        assert_eq!(split("app --open=%u"), ["app"]);
+    }
+
+    #[test]
+    fn quoted_arguments_stay_together() {
+        assert_eq!(
+            split(r#"sh -c "echo 'hello world' > /tmp/out""#),
+            ["sh", "-c", "echo 'hello world' > /tmp/out"]
+        );
+        assert_eq!(
+            split(r#""/opt/My App/app" --flag"#),
+            ["/opt/My App/app", "--flag"]
+        );
+        assert_eq!(split(r#"app "" x"#), ["app", "", "x"]);
+        assert_eq!(split(r#"app --name="a b"c"#), ["app", "--name=a bc"]);
+    }
+
+    #[test]
+    fn escapes_inside_quotes() {
+        assert_eq!(
+            split(r#"sh -c "echo \"\$HOME\" \\ \`date\`""#),
+            ["sh", "-c", r#"echo "$HOME" \ `date`"#]
+        );
+    }
+
+    #[test]
+    fn quoted_percent_signs_are_literal() {
+        assert_eq!(
+            split(r#"xdg-open "/tmp/100%U done.txt""#),
+            ["xdg-open", "/tmp/100%U done.txt"]
+        );
+    }
+
+    #[test]
+    fn double_percent_is_a_percent_sign() {
+        assert_eq!(split("printf %%s"), ["printf", "%s"]);
+        assert_eq!(split("volume 50% up"), ["volume", "50%", "up"]);
+    }
+
+    #[test]
+    fn name_desktop_file_and_icon_expand() {
+        assert_eq!(split("app --class %c"), ["app", "--class", "Text Editor"]);
+        assert_eq!(
+            split("app --desktop-file=%k"),
+            [
+                "app",
+                "--desktop-file=/usr/share/applications/org.gnome.TextEditor.desktop"
+            ]
+        );
+        assert_eq!(
+            split("app %i %U"),
+            ["app", "--icon", "org.gnome.TextEditor"]
+        );
+        assert_eq!(arguments("app %i %c %k", &Context::default()), ["app"]);
+    }
+
+    #[test]
+    fn deprecated_and_unknown_codes_are_dropped() {
+        assert_eq!(split("app %d %D %n %N %v %m %z"), ["app"]);
+    }
+
+    #[test]
+    fn quoting_round_trips() {
+        for argument in [
+            "plain",
+            "with space",
+            r#"q"uote"#,
+            "$HOME",
+            r"back\slash",
+            "50%",
+        ] {
+            let exec = format!("app {}", quote_argument(argument));
+            assert_eq!(split(&exec), ["app", argument]);
+        }
+    }
+}
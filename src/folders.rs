@@ -0,0 +1,108 @@
+use crate::types::{Entry, OpenType};
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+/// Named groups of desktop IDs, as in the config's `[folders]` table
+pub type Folders = BTreeMap<String, Vec<String>>;
+
+/// The entry standing for a folder, its exec is the folder's name
+pub fn folder_entry(name: &str) -> Entry {
+    Entry {
+        id: format!("folder-{}", name),
+        name: name.to_string(),
+        exec: name.to_string(),
+        icon: "folder".to_string(),
+        open_type: OpenType::Folder,
+        ..Default::default()
+    }
+}
+
+/// The folder's apps among `entries`, in the order the folder lists them
+pub fn members(folder: &Entry, folders: &Folders, entries: &[Entry]) -> Vec<Entry> {
+    let Some(ids) = folders.get(&folder.exec) else {
+        return vec![];
+    };
+    ids.iter()
+        .filter_map(|id| {
+            entries
+                .iter()
+                .find(|entry| is_app(entry) && entry.id == *id)
+        })
+        .cloned()
+        .collect()
+}
+
+/// Replace the apps in folders with one entry per folder, ranked like its best app.
+/// Folders none of whose apps are installed don't show.
+pub fn group(ranked: Vec<(f64, Entry)>, folders: &Folders) -> Vec<(f64, Entry)> {
+    // An app listed in several folders shows in the first one
+    let mut folder_of: HashMap<&str, &str> = HashMap::new();
+    for (name, ids) in folders.iter().rev() {
+        for id in ids {
+            folder_of.insert(id, name);
+        }
+    }
+
+    let mut shown = HashSet::new();
+    let mut grouped = Vec::with_capacity(ranked.len());
+    for (score, entry) in ranked {
+        match folder_of.get(entry.id.as_str()).filter(|_| is_app(&entry)) {
+            Some(&name) => {
+                if shown.insert(name) {
+                    grouped.push((score, folder_entry(name)));
+                }
+            }
+            None => grouped.push((score, entry)),
+        }
+    }
+    grouped
+}
+
+/// Put an app dropped onto another result into a folder: the folder itself, the
+/// one the other app is in, or a new one holding both. An app is only ever in one
+/// folder, so it leaves its previous one. Returns the folder's name.
+pub fn add(folders: &mut Folders, dropped: &Entry, target: &Entry) -> Option<String> {
+    if !is_app(dropped) || dropped.id == target.id {
+        return None;
+    }
+
+    let name = if target.open_type == OpenType::Folder {
+        target.exec.clone()
+    } else if is_app(target) {
+        match folders
+            .iter()
+            .find(|(_, ids)| ids.contains(&target.id))
+            .map(|(name, _)| name.clone())
+        {
+            Some(name) => name,
+            None => {
+                let name = new_name(folders);
+                folders.insert(name.clone(), vec![target.id.clone()]);
+                name
+            }
+        }
+    } else {
+        return None;
+    };
+
+    for ids in folders.values_mut() {
+        ids.retain(|id| *id != dropped.id);
+    }
+    folders
+        .entry(name.clone())
+        .or_default()
+        .push(dropped.id.clone());
+    folders.retain(|_, ids| !ids.is_empty());
+    Some(name)
+}
+
+/// "Folder", or "Folder 2" and so on when that is taken; renamed in the config
+fn new_name(folders: &Folders) -> String {
+    std::iter::once("Folder".to_string())
+        .chain((2..).map(|number| format!("Folder {}", number)))
+        .find(|name| !folders.contains_key(name))
+        .unwrap_or_default()
+}
+
+fn is_app(entry: &Entry) -> bool {
+    matches!(entry.open_type, OpenType::Graphical | OpenType::Terminal)
+}
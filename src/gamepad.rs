@@ -0,0 +1,171 @@
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Controllers plugged in later are picked up within this long
+const RESCAN_INTERVAL: Duration = Duration::from_secs(2);
+/// How far a stick has to be pushed before it counts, out of 32767
+const STICK_THRESHOLD: i16 = 16384;
+
+/// joydev's struct js_event: time, value, type, number
+const EVENT_SIZE: usize = 8;
+const JS_EVENT_BUTTON: u8 = 0x01;
+const JS_EVENT_AXIS: u8 = 0x02;
+/// Set on the events describing the state when the device was opened
+const JS_EVENT_INIT: u8 = 0x80;
+
+/// What a controller asked for, mapped from its d-pad, left stick and face buttons
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Button {
+    Up,
+    Down,
+    Left,
+    Right,
+    /// A on Xbox layouts, cross on PlayStation ones
+    Activate,
+    /// B on Xbox layouts, circle on PlayStation ones
+    Back,
+}
+
+/// Call `on_press` from a background thread for every button pressed on any game
+/// controller, through the kernel's joystick devices like SDL's fallback does
+pub fn listen(on_press: impl Fn(Button) + Clone + Send + 'static) {
+    let open = Arc::new(Mutex::new(HashSet::new()));
+
+    std::thread::spawn(move || {
+        loop {
+            for path in devices() {
+                let Ok(mut opened) = open.lock() else {
+                    return;
+                };
+                if opened.contains(&path) {
+                    continue;
+                }
+                // Controllers the user may not read are skipped until they can
+                let Ok(file) = File::open(&path) else {
+                    continue;
+                };
+                opened.insert(path.clone());
+
+                let open = open.clone();
+                let on_press = on_press.clone();
+                std::thread::spawn(move || {
+                    read_events(file, on_press);
+                    // Unplugged, it may come back under the same name
+                    if let Ok(mut opened) = open.lock() {
+                        opened.remove(&path);
+                    }
+                });
+            }
+            std::thread::sleep(RESCAN_INTERVAL);
+        }
+    });
+}
+
+fn devices() -> Vec<PathBuf> {
+    let Ok(read_dir) = fs::read_dir("/dev/input") else {
+        return vec![];
+    };
+    read_dir
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_name().to_string_lossy().starts_with("js"))
+        .map(|entry| entry.path())
+        .collect()
+}
+
+fn read_events(mut file: File, on_press: impl Fn(Button)) {
+    let mut mapper = Mapper::default();
+    let mut event = [0; EVENT_SIZE];
+
+    while file.read_exact(&mut event).is_ok() {
+        let value = i16::from_ne_bytes([event[4], event[5]]);
+        if let Some(button) = mapper.map(event[6], event[7], value) {
+            on_press(button);
+        }
+    }
+}
+
+/// Turns joydev events into presses, once per push of the d-pad or stick
+#[derive(Default)]
+struct Mapper {
+    /// The direction each axis was last pushed in, -1, 0 or 1
+    axes: HashMap<u8, i8>,
+}
+
+impl Mapper {
+    fn map(&mut self, kind: u8, number: u8, value: i16) -> Option<Button> {
+        let init = kind & JS_EVENT_INIT != 0;
+
+        match kind & !JS_EVENT_INIT {
+            JS_EVENT_BUTTON if !init && value == 1 => match number {
+                0 => Some(Button::Activate),
+                1 => Some(Button::Back),
+                _ => None,
+            },
+            JS_EVENT_AXIS => {
+                let direction = match value {
+                    value if value <= -STICK_THRESHOLD => -1,
+                    value if value >= STICK_THRESHOLD => 1,
+                    _ => 0,
+                };
+                let previous = self.axes.insert(number, direction).unwrap_or(0);
+                if init || direction == previous {
+                    return None;
+                }
+
+                // Xbox and PlayStation pads report the left stick on axes 0 and 1
+                // and the d-pad on axes 6 and 7, each as x then y
+                match (number, direction) {
+                    (0 | 6, -1) => Some(Button::Left),
+                    (0 | 6, 1) => Some(Button::Right),
+                    (1 | 7, -1) => Some(Button::Up),
+                    (1 | 7, 1) => Some(Button::Down),
+                    _ => None,
+                }
+            }
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn face_buttons_activate_and_go_back_when_pressed() {
+        let mut mapper = Mapper::default();
+
+        assert_eq!(mapper.map(JS_EVENT_BUTTON, 0, 1), Some(Button::Activate));
+        assert_eq!(mapper.map(JS_EVENT_BUTTON, 0, 0), None);
+        assert_eq!(mapper.map(JS_EVENT_BUTTON, 1, 1), Some(Button::Back));
+        assert_eq!(mapper.map(JS_EVENT_BUTTON, 3, 1), None);
+    }
+
+    #[test]
+    fn holding_the_stick_moves_once() {
+        let mut mapper = Mapper::default();
+
+        assert_eq!(mapper.map(JS_EVENT_AXIS, 1, 20000), Some(Button::Down));
+        assert_eq!(mapper.map(JS_EVENT_AXIS, 1, 32767), None);
+        assert_eq!(mapper.map(JS_EVENT_AXIS, 1, 100), None);
+        assert_eq!(mapper.map(JS_EVENT_AXIS, 1, -32767), Some(Button::Up));
+        assert_eq!(mapper.map(JS_EVENT_AXIS, 6, -32767), Some(Button::Left));
+        assert_eq!(mapper.map(JS_EVENT_AXIS, 6, 32767), Some(Button::Right));
+    }
+
+    #[test]
+    fn the_state_at_opening_is_not_a_press() {
+        let mut mapper = Mapper::default();
+
+        assert_eq!(mapper.map(JS_EVENT_BUTTON | JS_EVENT_INIT, 0, 1), None);
+        assert_eq!(mapper.map(JS_EVENT_AXIS | JS_EVENT_INIT, 7, 32767), None);
+        // Still held, so only letting go and pushing again moves
+        assert_eq!(mapper.map(JS_EVENT_AXIS, 7, 32767), None);
+        assert_eq!(mapper.map(JS_EVENT_AXIS, 7, 0), None);
+        assert_eq!(mapper.map(JS_EVENT_AXIS, 7, 32767), Some(Button::Down));
+    }
+}
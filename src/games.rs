@@ -0,0 +1,249 @@
+use crate::error::Result;
+use crate::exec::quote_argument;
+use crate::icon;
+use crate::index;
+use crate::types::{Entry, OpenType};
+use crate::wine;
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const STEAM_FLATPAK_ID: &str = "com.valvesoftware.Steam";
+const HEROIC_FLATPAK_ID: &str = "com.heroicgameslauncher.hgl";
+/// Steam installs its runtimes and compatibility tools like games
+const STEAM_TOOL_PREFIXES: &[&str] = &[
+    "Proton",
+    "Steam Linux Runtime",
+    "Steamworks Common Redistributables",
+];
+
+/// Installed games from Steam and Heroic, plus the Windows programs from Wine and Lutris
+pub fn get_game_entries() -> Result<Vec<Entry>> {
+    let home = wine::home()?;
+    let mut entries = steam_entries(&home);
+    entries.extend(heroic_entries(&home));
+    entries.extend(wine::get_wine_entries()?);
+
+    let mut seen = HashSet::new();
+    entries.retain(|entry| seen.insert(entry.name.to_lowercase()));
+    Ok(entries)
+}
+
+/// Changes when a game is installed or removed: Steam adds and removes app manifests,
+/// Heroic rewrites its install lists
+pub fn fingerprint() -> String {
+    // Loading the games reports the missing home directory
+    let Ok(home) = wine::home() else {
+        return String::new();
+    };
+    let steam = steam_installations(&home)
+        .into_iter()
+        .flat_map(|(root, _)| steam_libraries(&root))
+        .map(|library| library.join("steamapps"));
+    let heroic = heroic_configs(&home).into_iter().flat_map(|config| {
+        [
+            config.join("legendaryConfig/legendary/installed.json"),
+            config.join("gog_store/installed.json"),
+        ]
+    });
+
+    index::modified_key(
+        steam
+            .chain(heroic)
+            .chain(wine::watched_directories().unwrap_or_default()),
+    )
+}
+
+/// Steam's root directory with the command that starts that installation
+fn steam_installations(home: &Path) -> Vec<(PathBuf, String)> {
+    let native = [home.join(".local/share/Steam"), home.join(".steam/steam")]
+        .into_iter()
+        // ~/.steam/steam usually links to the other one
+        .filter_map(|root| root.canonicalize().ok())
+        .fold(Vec::<PathBuf>::new(), |mut roots, root| {
+            if !roots.contains(&root) {
+                roots.push(root);
+            }
+            roots
+        })
+        .into_iter()
+        .map(|root| (root, "steam".to_string()));
+
+    let flatpak = home.join(format!(".var/app/{}/.local/share/Steam", STEAM_FLATPAK_ID));
+
+    native
+        .chain(
+            flatpak
+                .is_dir()
+                .then(|| (flatpak, format!("flatpak run {}", STEAM_FLATPAK_ID))),
+        )
+        .collect()
+}
+
+fn steam_entries(home: &Path) -> Vec<Entry> {
+    let mut entries = vec![];
+
+    for (root, command) in steam_installations(home) {
+        for library in steam_libraries(&root) {
+            let Ok(read_dir) = fs::read_dir(library.join("steamapps")) else {
+                continue;
+            };
+
+            for path in read_dir
+                .filter_map(|entry| entry.ok())
+                .map(|entry| entry.path())
+            {
+                let name = path.file_name().and_then(|name| name.to_str());
+                if !name.is_some_and(|name| name.starts_with("appmanifest_"))
+                    || path.extension().is_none_or(|ext| ext != "acf")
+                {
+                    continue;
+                }
+                let Ok(manifest) = fs::read_to_string(&path) else {
+                    continue;
+                };
+                let (Some(app_id), Some(name)) =
+                    (vdf_value(&manifest, "appid"), vdf_value(&manifest, "name"))
+                else {
+                    continue;
+                };
+                if STEAM_TOOL_PREFIXES
+                    .iter()
+                    .any(|prefix| name.starts_with(prefix))
+                {
+                    continue;
+                }
+
+                entries.push(Entry {
+                    id: format!("steam-{}", app_id),
+                    icon: steam_icon(&root, &app_id),
+                    exec: format!("{} steam://rungameid/{}", command, app_id),
+                    name,
+                    open_type: OpenType::Graphical,
+                    ..Default::default()
+                });
+            }
+        }
+    }
+
+    entries
+}
+
+/// The main library and every folder listed in libraryfolders.vdf
+fn steam_libraries(root: &Path) -> Vec<PathBuf> {
+    let mut libraries = vec![root.to_path_buf()];
+
+    if let Ok(folders) = fs::read_to_string(root.join("steamapps/libraryfolders.vdf")) {
+        for line in folders.lines() {
+            if let Some(path) = vdf_pair(line)
+                .filter(|(key, _)| *key == "path")
+                .map(|(_, value)| PathBuf::from(value))
+                && !libraries.contains(&path)
+            {
+                libraries.push(path);
+            }
+        }
+    }
+
+    libraries
+}
+
+/// Steam exports game icons into the hicolor theme when it creates desktop shortcuts,
+/// otherwise the one in its library cache is used
+fn steam_icon(root: &Path, app_id: &str) -> String {
+    if let Some(icon) = icon::resolve_icon_path(&format!("steam_icon_{}", app_id))
+        .filter(|icon| icon.starts_with('/'))
+    {
+        return icon;
+    }
+
+    let cached = root
+        .join("appcache/librarycache")
+        .join(format!("{}_icon.jpg", app_id));
+    if cached.exists() {
+        return cached.to_string_lossy().to_string();
+    }
+
+    "steam".to_string()
+}
+
+/// The value of the first `"key" "value"` line with this key
+fn vdf_value(vdf: &str, key: &str) -> Option<String> {
+    vdf.lines()
+        .filter_map(vdf_pair)
+        .find(|(k, _)| k.eq_ignore_ascii_case(key))
+        .map(|(_, value)| value.to_string())
+}
+
+/// A `"key"  "value"` line of a Valve KeyValues file
+fn vdf_pair(line: &str) -> Option<(&str, &str)> {
+    let mut parts = line
+        .trim()
+        .split('"')
+        .filter(|part| !part.trim().is_empty());
+    let key = parts.next()?;
+    let value = parts.next()?;
+    Some((key, value))
+}
+
+/// Heroic's config directory, natively installed and as a Flatpak
+fn heroic_configs(home: &Path) -> Vec<PathBuf> {
+    vec![
+        home.join(".config/heroic"),
+        home.join(format!(".var/app/{}/config/heroic", HEROIC_FLATPAK_ID)),
+    ]
+}
+
+/// Epic games installed through Legendary and GOG games, started through Heroic's URL scheme
+fn heroic_entries(home: &Path) -> Vec<Entry> {
+    let mut entries = vec![];
+
+    for config in heroic_configs(home) {
+        let legendary = read_json(&config.join("legendaryConfig/legendary/installed.json"));
+        if let Some(games) = legendary.as_ref().and_then(|json| json.as_object()) {
+            for (app_name, game) in games {
+                let title = game["title"].as_str().unwrap_or(app_name);
+                entries.push(heroic_entry("legendary", app_name, title));
+            }
+        }
+
+        let gog = read_json(&config.join("gog_store/installed.json"));
+        if let Some(games) = gog.as_ref().and_then(|json| json["installed"].as_array()) {
+            for game in games {
+                let Some(app_name) = game["appName"].as_str() else {
+                    continue;
+                };
+                // The install list doesn't carry titles, the folder is named after the game
+                let title = game["install_path"]
+                    .as_str()
+                    .and_then(|path| Path::new(path).file_name())
+                    .map(|name| name.to_string_lossy().to_string())
+                    .unwrap_or_else(|| app_name.to_string());
+                entries.push(heroic_entry("gog", app_name, &title));
+            }
+        }
+    }
+
+    entries
+}
+
+fn heroic_entry(runner: &str, app_name: &str, title: &str) -> Entry {
+    Entry {
+        id: format!("heroic-{}-{}", runner, app_name),
+        name: title.to_string(),
+        exec: format!(
+            "xdg-open {}",
+            quote_argument(&format!("heroic://launch/{}/{}", runner, app_name))
+        ),
+        icon: HEROIC_FLATPAK_ID.to_string(),
+        open_type: OpenType::Graphical,
+        ..Default::default()
+    }
+}
+
+fn read_json(path: &Path) -> Option<serde_json::Value> {
+    let data = fs::read_to_string(path).ok()?;
+    serde_json::from_str(&data)
+        .map_err(|e| eprintln!("Failed to parse {}: {}", path.display(), e))
+        .ok()
+}
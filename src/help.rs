@@ -0,0 +1,115 @@
+use crate::cli::Mode;
+use crate::config::Keybinds;
+use crate::types::{Entry, OpenType};
+use clap::ValueEnum;
+use relm4::gtk;
+
+/// Typed as the whole query, or in front of a word to filter the help
+pub const PREFIX: char = '?';
+
+/// Help lines matching the text after `?`, each with the header of its group
+pub fn entries(query: &str, keybinds: &Keybinds) -> Vec<(Entry, Option<&'static str>)> {
+    let filter = query.trim().to_lowercase();
+    let matches = |entry: &Entry| filter.is_empty() || entry.name.to_lowercase().contains(&filter);
+
+    let modes: Vec<Entry> = mode_entries().into_iter().filter(matches).collect();
+    let keys: Vec<Entry> = key_entries(keybinds).into_iter().filter(matches).collect();
+
+    let with_header = |entries: Vec<Entry>, header| {
+        entries
+            .into_iter()
+            .enumerate()
+            .map(move |(index, entry)| (entry, (index == 0).then_some(header)))
+    };
+    with_header(modes, "Modes")
+        .chain(with_header(keys, "Keys"))
+        .collect()
+}
+
+/// One line per mode with a prefix, activating it types the prefix
+fn mode_entries() -> Vec<Entry> {
+    Mode::value_variants()
+        .iter()
+        .filter_map(|mode| {
+            let prefix = mode.prefix()?;
+            Some(Entry {
+                id: format!("help-{}", prefix),
+                name: format!("{} — {}", prefix, mode.label()),
+                exec: format!("{} ", prefix),
+                icon: mode_icon(*mode).to_string(),
+                open_type: OpenType::Help,
+                ..Default::default()
+            })
+        })
+        .collect()
+}
+
+fn mode_icon(mode: Mode) -> &'static str {
+    match mode {
+        Mode::All | Mode::Apps => "view-app-grid-symbolic",
+        Mode::Windows => "focus-windows-symbolic",
+        Mode::Power => "system-shutdown-symbolic",
+        Mode::Autostart => "system-run-symbolic",
+        Mode::MimeTypes => "document-open-symbolic",
+        Mode::Games => "input-gaming-symbolic",
+        Mode::Man => "accessories-dictionary-symbolic",
+        Mode::Run => "utilities-terminal-symbolic",
+        Mode::Env => "preferences-system-symbolic",
+    }
+}
+
+/// The configured key bindings and the fixed keys, activating these does nothing
+fn key_entries(keybinds: &Keybinds) -> Vec<Entry> {
+    let configured = [
+        (&keybinds.accept, "Launch the selected entry"),
+        (&keybinds.cancel, "Close the launcher"),
+        (&keybinds.up, "Select the previous entry"),
+        (&keybinds.down, "Select the next entry"),
+        (&keybinds.next_page, "Next page"),
+        (&keybinds.previous_page, "Previous page"),
+        (&keybinds.close_window, "Close the selected window"),
+        (&keybinds.kill_process, "Kill the selected window's process"),
+        (&keybinds.uninstall, "Uninstall the selected Flatpak"),
+        (&keybinds.refresh, "Refresh the application cache"),
+        (&keybinds.type_text, "Type the selected text result"),
+        (&keybinds.peek, "Open the selected entry and keep searching"),
+        (&keybinds.preview, "Preview the selected file"),
+        (&keybinds.open_with, "Pick an app to open the selected file"),
+        (&keybinds.window_scope, "Switch which windows are listed"),
+    ]
+    .into_iter()
+    .filter(|(binds, _)| !binds.is_empty())
+    .map(|(binds, description)| {
+        let keys = binds
+            .iter()
+            .map(|bind| key_label(bind))
+            .collect::<Vec<String>>()
+            .join(", ");
+        (keys, description)
+    });
+
+    let fixed = [
+        ("Backspace", "Leave the mode on an empty search"),
+        ("Left, Right", "Select among the top apps"),
+    ]
+    .into_iter()
+    .map(|(keys, description)| (keys.to_string(), description));
+
+    configured
+        .chain(fixed)
+        .map(|(keys, description)| Entry {
+            id: format!("help-{}", description),
+            name: format!("{} — {}", keys, description),
+            icon: "input-keyboard-symbolic".to_string(),
+            open_type: OpenType::Help,
+            ..Default::default()
+        })
+        .collect()
+}
+
+/// "<Control>w" as GTK shows it, e.g. "Ctrl+W"
+fn key_label(bind: &str) -> String {
+    gtk::accelerator_parse(bind)
+        .map(|(key, modifier)| gtk::accelerator_get_label(key, modifier).to_string())
+        .unwrap_or_else(|| bind.to_string())
+}
@@ -0,0 +1,170 @@
+use crate::types::{Entry, OpenType};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+/// Only the most recent commands are offered
+const MAX_COMMANDS: usize = 500;
+
+/// A history line with when it ran, in seconds since the epoch
+type TimedCommand = (i64, String);
+/// A history line as its shell wrote it, with the time when the shell records one
+type ParsedCommand = (Option<i64>, String);
+
+/// Commands run in bash, zsh and fish, most recent first and each only once
+pub fn get_history_entries() -> Vec<Entry> {
+    let home = dirs::home_dir().unwrap_or_else(|| PathBuf::from("/tmp"));
+    let zdotdir = std::env::var_os("ZDOTDIR").map_or_else(|| home.clone(), PathBuf::from);
+
+    let mut commands = vec![];
+    commands.extend(read_history(&home.join(".bash_history"), parse_bash));
+    commands.extend(read_history(&zdotdir.join(".zsh_history"), parse_zsh));
+    if let Some(data_dir) = dirs::data_dir() {
+        commands.extend(read_history(
+            &data_dir.join("fish").join("fish_history"),
+            parse_fish,
+        ));
+    }
+
+    let mut latest: HashMap<String, i64> = HashMap::new();
+    for (time, command) in commands {
+        let latest_time = latest.entry(command).or_insert(time);
+        *latest_time = (*latest_time).max(time);
+    }
+
+    let mut commands: Vec<(String, i64)> = latest.into_iter().collect();
+    // Equal times keep a stable order between runs
+    commands.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    commands.truncate(MAX_COMMANDS);
+
+    commands
+        .into_iter()
+        .map(|(command, _)| Entry {
+            id: format!("history-{}", command),
+            name: command.clone(),
+            exec: command,
+            icon: "utilities-terminal-symbolic".to_string(),
+            open_type: OpenType::Terminal,
+            ..Default::default()
+        })
+        .collect()
+}
+
+/// The file's commands, oldest first. Lines without a time of their own are
+/// spread over the seconds before the file was last written, in their order.
+fn read_history(path: &Path, parse: fn(&[u8]) -> Vec<ParsedCommand>) -> Vec<TimedCommand> {
+    let Ok(data) = fs::read(path) else {
+        return vec![];
+    };
+    let modified = fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+        .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+        .map_or(0, |since| since.as_secs() as i64);
+
+    let lines = parse(&data);
+    let count = lines.len() as i64;
+    lines
+        .into_iter()
+        .enumerate()
+        .map(|(index, (time, command))| {
+            (time.unwrap_or(modified - (count - index as i64)), command)
+        })
+        .filter(|(_, command)| !command.trim().is_empty())
+        .collect()
+}
+
+/// zsh writes bytes above 0x82 as 0x83 followed by the byte xor 32
+fn unmetafy(data: &[u8]) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(data.len());
+    let mut iter = data.iter();
+    while let Some(&byte) = iter.next() {
+        match byte {
+            0x83 => bytes.extend(iter.next().map(|next| next ^ 32)),
+            byte => bytes.push(byte),
+        }
+    }
+    bytes
+}
+
+/// Plain lines, each preceded by "#<time>" when HISTTIMEFORMAT is set
+fn parse_bash(data: &[u8]) -> Vec<ParsedCommand> {
+    let mut commands = vec![];
+    let mut time = None;
+    for line in String::from_utf8_lossy(data).lines() {
+        if let Some(stamp) = line.strip_prefix('#')
+            && let Ok(stamp) = stamp.parse::<i64>()
+        {
+            time = Some(stamp);
+            continue;
+        }
+        commands.push((time.take(), line.to_string()));
+    }
+    commands
+}
+
+/// ": <time>:<duration>;<command>" with EXTENDED_HISTORY, plain lines otherwise.
+/// Multi-line commands continue after a trailing backslash.
+fn parse_zsh(data: &[u8]) -> Vec<ParsedCommand> {
+    let mut commands: Vec<ParsedCommand> = vec![];
+    let mut continued = false;
+    for line in String::from_utf8_lossy(&unmetafy(data)).lines() {
+        if continued && let Some((_, command)) = commands.last_mut() {
+            command.push('\n');
+            command.push_str(line.strip_suffix('\\').unwrap_or(line));
+            continued = line.ends_with('\\');
+            continue;
+        }
+
+        let (time, command) = match line
+            .strip_prefix(": ")
+            .and_then(|rest| rest.split_once(';'))
+            .and_then(|(meta, command)| Some((meta.split(':').next()?.parse().ok()?, command)))
+        {
+            Some((time, command)) => (Some(time), command),
+            None => (None, line),
+        };
+        continued = command.ends_with('\\');
+        commands.push((
+            time,
+            command.strip_suffix('\\').unwrap_or(command).to_string(),
+        ));
+    }
+    commands
+}
+
+/// "- cmd: <command>" followed by "  when: <time>", with newlines and
+/// backslashes in the command escaped
+fn parse_fish(data: &[u8]) -> Vec<ParsedCommand> {
+    let mut commands: Vec<ParsedCommand> = vec![];
+    for line in String::from_utf8_lossy(data).lines() {
+        if let Some(command) = line.strip_prefix("- cmd: ") {
+            commands.push((None, unescape_fish(command)));
+        } else if let Some(time) = line.trim_start().strip_prefix("when: ")
+            && let Some((command_time, _)) = commands.last_mut()
+        {
+            *command_time = time.trim().parse().ok();
+        }
+    }
+    commands
+}
+
+fn unescape_fish(command: &str) -> String {
+    let mut unescaped = String::with_capacity(command.len());
+    let mut chars = command.chars();
+    while let Some(char) = chars.next() {
+        match (char, chars.clone().next()) {
+            ('\\', Some('n')) => {
+                unescaped.push('\n');
+                chars.next();
+            }
+            ('\\', Some('\\')) => {
+                unescaped.push('\\');
+                chars.next();
+            }
+            (char, _) => unescaped.push(char),
+        }
+    }
+    unescaped
+}
@@ -0,0 +1,103 @@
+use crate::notify;
+use crate::types::Entry;
+use std::os::unix::process::CommandExt;
+use std::process::{Command, Stdio};
+
+/// Run a launch hook through `sh -c` in the background, so a slow hook can't hold up the launch.
+/// `{id}`, `{name}` and `{exec}` are replaced with the entry's shell quoted values.
+pub fn run(hook: Option<&str>, entry: &Entry) {
+    let Some(hook) = hook.filter(|hook| !hook.trim().is_empty()) else {
+        return;
+    };
+
+    let script = expand(hook, entry);
+
+    let child = Command::new("sh")
+        .arg("-c")
+        .arg(&script)
+        .stdin(Stdio::null())
+        .process_group(0)
+        .spawn();
+
+    match child {
+        // Reap it once it exits, the service would collect zombies otherwise
+        Ok(mut child) => {
+            std::thread::spawn(move || match child.wait() {
+                Ok(status) if !status.success() => {
+                    eprintln!(
+                        "Launch hook `{}` exited with {}",
+                        hook_name(&script),
+                        status
+                    );
+                }
+                Err(e) => eprintln!("Failed to wait for launch hook: {}", e),
+                _ => {}
+            });
+        }
+        Err(e) => {
+            eprintln!("Failed to run launch hook: {}", e);
+            notify::notify("Failed to run launch hook", &e.to_string());
+        }
+    }
+}
+
+/// Fill in the placeholders in one pass, so placeholders in the values themselves,
+/// e.g. a name containing "{exec}", stay inside their quotes
+fn expand(hook: &str, entry: &Entry) -> String {
+    let mut script = String::with_capacity(hook.len());
+    let mut rest = hook;
+    while let Some(start) = rest.find('{') {
+        script.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        let value = [
+            ("{id}", &entry.id),
+            ("{name}", &entry.name),
+            ("{exec}", &entry.exec),
+        ]
+        .into_iter()
+        .find(|(placeholder, _)| rest.starts_with(placeholder));
+        match value {
+            Some((placeholder, value)) => {
+                script.push_str(&shell_quote(value));
+                rest = &rest[placeholder.len()..];
+            }
+            None => {
+                script.push('{');
+                rest = &rest[1..];
+            }
+        }
+    }
+    script.push_str(rest);
+    script
+}
+
+/// The first word of a hook, enough to tell which one failed
+fn hook_name(script: &str) -> &str {
+    script.split_whitespace().next().unwrap_or(script)
+}
+
+/// Wrap a value in single quotes so the shell takes it literally
+fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', r"'\''"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn placeholders_in_values_stay_quoted() {
+        let entry = Entry {
+            id: "{exec}.desktop".to_string(),
+            name: "It's {name}".to_string(),
+            exec: "app; rm -rf ~".to_string(),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            expand("log {id} {name} {exec} {other}", &entry),
+            r"log '{exec}.desktop' 'It'\''s {name}' 'app; rm -rf ~' {other}"
+        );
+    }
+}
@@ -0,0 +1,441 @@
+use crate::compositor::{Backend, Events, Window, WindowEvent};
+use crate::config::{WindowRules, WindowScope};
+use crate::error::{LauncherError, Result};
+use serde::Deserialize;
+use serde::de::DeserializeOwned;
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, ErrorKind, Read, Write};
+use std::os::unix::net::UnixStream;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+/// Hyprland answers right away like niri, a request taking longer means it is stuck
+const TIMEOUT: Duration = Duration::from_millis(500);
+
+/// A window as `hyprctl clients -j` lists it
+#[derive(Debug, Deserialize)]
+pub struct Client {
+    /// Hex like "0x55d0c5a8e0b0", windows are addressed by it
+    pub address: String,
+    /// Unmapped clients aren't shown anywhere
+    #[serde(default)]
+    pub mapped: bool,
+    pub workspace: WorkspaceRef,
+    pub monitor: i64,
+    /// The app ID on Wayland, the WM_CLASS under Xwayland
+    pub class: String,
+    pub title: String,
+    pub pid: i64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct WorkspaceRef {
+    pub id: i64,
+}
+
+/// The focused workspace, as `hyprctl activeworkspace -j` reports it
+#[derive(Debug, Deserialize)]
+pub struct ActiveWorkspace {
+    pub id: i64,
+    #[serde(rename = "monitorID")]
+    pub monitor_id: i64,
+}
+
+/// A monitor as `hyprctl monitors -j` lists it
+#[derive(Debug, Deserialize)]
+struct Monitor {
+    name: String,
+    /// In pixels of the current mode
+    width: i32,
+    height: i32,
+    scale: f64,
+    /// wl_output's transform, odd ones rotate by 90 or 270 degrees
+    transform: i64,
+    focused: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct Version {
+    tag: String,
+}
+
+/// Hyprland, through the sockets in the directory HYPRLAND_INSTANCE_SIGNATURE names
+pub struct Hyprland {
+    dir: PathBuf,
+}
+
+impl Hyprland {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// The Hyprland instance the launcher runs in, None outside Hyprland. Its sockets
+    /// moved from /tmp into the runtime directory in 0.40.
+    pub fn from_env() -> Option<Self> {
+        let signature = std::env::var_os("HYPRLAND_INSTANCE_SIGNATURE")?;
+        let dirs: Vec<PathBuf> = std::env::var_os("XDG_RUNTIME_DIR")
+            .map(PathBuf::from)
+            .into_iter()
+            .chain([PathBuf::from("/tmp")])
+            .map(|dir| dir.join("hypr").join(&signature))
+            .collect();
+
+        // Without a socket anywhere, connecting fails with the first place looked at
+        let dir = dirs
+            .iter()
+            .find(|dir| dir.join(".socket.sock").exists())
+            .unwrap_or(&dirs[0]);
+        Some(Self::new(dir.clone()))
+    }
+
+    fn connect(&self, socket: &str, timeout: Option<Duration>) -> Result<UnixStream> {
+        let connect = || -> std::io::Result<UnixStream> {
+            let stream = UnixStream::connect(self.dir.join(socket))?;
+            stream.set_read_timeout(timeout)?;
+            stream.set_write_timeout(Some(TIMEOUT))?;
+            Ok(stream)
+        };
+        connect()
+            .map_err(|e| LauncherError::HyprlandConnection(format!("Failed to connect: {}", e)))
+    }
+
+    /// Send one request and read the answer. Hyprland closes each connection after
+    /// answering, so every request connects anew.
+    fn request(&self, message: &str, description: &str) -> Result<String> {
+        let mut stream = self.connect(".socket.sock", Some(TIMEOUT))?;
+
+        let mut exchange = || -> std::io::Result<String> {
+            stream.write_all(message.as_bytes())?;
+            let mut reply = String::new();
+            stream.read_to_string(&mut reply)?;
+            Ok(reply)
+        };
+        exchange().map_err(|e| request_error(e, description))
+    }
+
+    /// A query answered in JSON, e.g. "clients"
+    fn query<T: DeserializeOwned>(&self, command: &str, description: &str) -> Result<T> {
+        let reply = self.request(&format!("j/{}", command), description)?;
+        serde_json::from_str(&reply).map_err(|e| {
+            LauncherError::HyprlandRequest(format!("Unexpected answer to {}: {}", command, e))
+        })
+    }
+
+    pub fn clients(&self) -> Result<Vec<Client>> {
+        self.query("clients", "query windows")
+    }
+
+    pub fn active_workspace(&self) -> Result<ActiveWorkspace> {
+        self.query("activeworkspace", "query the workspace")
+    }
+
+    /// Run a dispatcher, e.g. "focuswindow address:0x55d0c5a8e0b0".
+    /// `description` completes "Failed to …" in errors.
+    pub fn dispatch(&self, dispatcher: &str, description: &str) -> Result<()> {
+        let reply = self.request(&format!("dispatch {}", dispatcher), description)?;
+        match reply.trim() {
+            "ok" => Ok(()),
+            error => Err(LauncherError::HyprlandRequest(format!(
+                "Failed to {}: {}",
+                description, error
+            ))),
+        }
+    }
+}
+
+impl Backend for Hyprland {
+    fn version(&self) -> Result<String> {
+        let version: Version = self.query("version", "query the version")?;
+        Ok(format!("Hyprland {}", version.tag))
+    }
+
+    fn windows(&self, scope: WindowScope) -> Result<Vec<Window>> {
+        let active = match scope {
+            WindowScope::All => None,
+            _ => Some(self.active_workspace()?),
+        };
+
+        Ok(self
+            .clients()?
+            .into_iter()
+            .filter(|client| client.mapped)
+            .filter(|client| match (&active, scope) {
+                (None, _) => true,
+                (Some(active), WindowScope::Output) => client.monitor == active.monitor_id,
+                (Some(active), _) => client.workspace.id == active.id,
+            })
+            .map(|client| Window {
+                id: client.address,
+                app_id: client.class,
+                title: client.title,
+            })
+            .collect())
+    }
+
+    fn focus_window(&self, id: &str) -> Result<()> {
+        self.dispatch(&format!("focuswindow address:{}", id), "focus window")
+    }
+
+    fn close_window(&self, id: &str) -> Result<()> {
+        self.dispatch(&format!("closewindow address:{}", id), "close window")
+    }
+
+    fn window_pid(&self, id: &str) -> Result<Option<u32>> {
+        Ok(self
+            .clients()?
+            .into_iter()
+            .find(|client| client.address == id && client.pid > 0)
+            .map(|client| client.pid as u32))
+    }
+
+    /// Hyprland hands the line to `sh -c`
+    fn spawn(&self, command: &[String], description: &str) -> Result<()> {
+        self.dispatch(&format!("exec {}", shell_line(command)), description)
+    }
+
+    fn focus_workspace(&self, workspace: &str) -> Result<()> {
+        self.dispatch(
+            &format!("workspace {}", workspace_selector(workspace)),
+            "switch workspace",
+        )
+    }
+
+    fn apply_window_rules(&self, id: &str, rules: &WindowRules) -> Result<()> {
+        match rules.floating {
+            Some(true) => self.dispatch(&format!("setfloating address:{}", id), "float window")?,
+            Some(false) => self.dispatch(&format!("settiled address:{}", id), "tile window")?,
+            None => {}
+        }
+
+        if let Some(workspace) = &rules.workspace {
+            self.dispatch(
+                &format!(
+                    "movetoworkspacesilent {},address:{}",
+                    workspace_selector(workspace),
+                    id
+                ),
+                "move window to workspace",
+            )?;
+        }
+
+        // Hyprland's layouts have no columns to size
+        if let Some(width) = &rules.column_width {
+            return Err(LauncherError::Config(format!(
+                "column_width {} only works in niri",
+                width
+            )));
+        }
+
+        Ok(())
+    }
+
+    fn log_out(&self) -> Result<()> {
+        self.dispatch("exit", "log out")
+    }
+
+    fn focused_output_size(&self) -> Result<(i32, i32)> {
+        let monitors: Vec<Monitor> = self.query("monitors", "query the monitors")?;
+        let monitor = monitors
+            .into_iter()
+            .find(|monitor| monitor.focused)
+            .ok_or_else(|| LauncherError::HyprlandRequest("No monitor is focused".to_string()))?;
+        if monitor.scale <= 0.0 {
+            return Err(LauncherError::HyprlandRequest(format!(
+                "Monitor {} has no scale",
+                monitor.name
+            )));
+        }
+
+        let width = (monitor.width as f64 / monitor.scale).round() as i32;
+        let height = (monitor.height as f64 / monitor.scale).round() as i32;
+        if monitor.transform % 2 == 1 {
+            Ok((height, width))
+        } else {
+            Ok((width, height))
+        }
+    }
+
+    fn events(&self) -> Result<Box<dyn Events>> {
+        // Reading waits as long as the caller asks
+        let stream = self.connect(".socket2.sock", None)?;
+        let open = self
+            .clients()?
+            .into_iter()
+            .map(|client| {
+                let window = Window {
+                    id: client.address,
+                    app_id: client.class,
+                    title: client.title,
+                };
+                (window.id.clone(), window)
+            })
+            .collect();
+
+        Ok(Box::new(HyprlandEvents {
+            stream: BufReader::new(stream),
+            open,
+        }))
+    }
+}
+
+/// Hyprland's event socket, one "EVENT>>DATA" line each. Closing only names
+/// the window, so the open ones are kept.
+struct HyprlandEvents {
+    stream: BufReader<UnixStream>,
+    open: HashMap<String, Window>,
+}
+
+impl Events for HyprlandEvents {
+    fn next(&mut self, timeout: Option<Duration>) -> Result<Option<WindowEvent>> {
+        let deadline = timeout.map(|timeout| Instant::now() + timeout);
+        loop {
+            // A zero timeout would mean waiting forever
+            let timeout = deadline.map(|deadline| {
+                deadline
+                    .saturating_duration_since(Instant::now())
+                    .max(Duration::from_millis(1))
+            });
+            let mut line = String::new();
+            let read = self
+                .stream
+                .get_ref()
+                .set_read_timeout(timeout)
+                .and_then(|()| self.stream.read_line(&mut line));
+            match read {
+                Ok(0) => return Err(read_error(ErrorKind::UnexpectedEof.into())),
+                Ok(_) => {}
+                Err(e) if is_timeout(&e) => return Ok(None),
+                Err(e) => return Err(read_error(e)),
+            }
+
+            match window_event(line.trim_end()) {
+                Some(WindowEvent::Opened(window)) => {
+                    // Hyprland may announce a window again, e.g. after remapping it
+                    let known = self.open.insert(window.id.clone(), window.clone());
+                    if known.is_none() {
+                        return Ok(Some(WindowEvent::Opened(window)));
+                    }
+                }
+                Some(WindowEvent::Closed(window)) => {
+                    if let Some(closed) = self.open.remove(&window.id) {
+                        return Ok(Some(WindowEvent::Closed(closed)));
+                    }
+                }
+                None => {}
+            }
+        }
+    }
+}
+
+/// The window event in a line from the event socket. Events leave the "0x" off
+/// addresses, and a closed window is only named.
+fn window_event(line: &str) -> Option<WindowEvent> {
+    let (event, data) = line.split_once(">>")?;
+    match event {
+        "openwindow" => {
+            // The title comes last, as it may hold commas
+            let mut fields = data.splitn(4, ',');
+            let address = fields.next()?;
+            let _workspace = fields.next()?;
+            Some(WindowEvent::Opened(Window {
+                id: format!("0x{}", address),
+                app_id: fields.next()?.to_string(),
+                title: fields.next().unwrap_or_default().to_string(),
+            }))
+        }
+        "closewindow" => Some(WindowEvent::Closed(Window {
+            id: format!("0x{}", data),
+            ..Default::default()
+        })),
+        _ => None,
+    }
+}
+
+/// Workspaces are named in the config, or given by their ID when the name is a number
+fn workspace_selector(workspace: &str) -> String {
+    match workspace.parse::<u32>() {
+        Ok(id) => id.to_string(),
+        Err(_) => format!("name:{}", workspace),
+    }
+}
+
+fn request_error(error: std::io::Error, description: &str) -> LauncherError {
+    if is_timeout(&error) {
+        LauncherError::HyprlandTimeout(format!(
+            "No answer to {} within {} ms",
+            description,
+            TIMEOUT.as_millis()
+        ))
+    } else {
+        LauncherError::HyprlandRequest(format!("Failed to {}: {}", description, error))
+    }
+}
+
+fn read_error(error: std::io::Error) -> LauncherError {
+    LauncherError::HyprlandRequest(format!("Failed to read event: {}", error))
+}
+
+fn is_timeout(error: &std::io::Error) -> bool {
+    matches!(error.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut)
+}
+
+/// Single quote every argument, so none is split or expanded by the shell
+fn shell_line(command: &[String]) -> String {
+    command
+        .iter()
+        .map(|argument| format!("'{}'", argument.replace('\'', r"'\''")))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clients_are_read_from_hyprctl_json() {
+        let reply = r#"[{
+            "address": "0x55d0c5a8e0b0", "mapped": true, "hidden": false,
+            "at": [10, 40], "size": [1900, 1030],
+            "workspace": {"id": 2, "name": "2"}, "floating": false,
+            "monitor": 0, "class": "org.gnome.Nautilus", "title": "Home",
+            "initialClass": "org.gnome.Nautilus", "initialTitle": "Home",
+            "pid": 4242, "xwayland": false, "pinned": false, "fullscreen": 0
+        }]"#;
+        let clients: Vec<Client> = serde_json::from_str(reply).unwrap();
+
+        assert_eq!(clients[0].address, "0x55d0c5a8e0b0");
+        assert_eq!(clients[0].class, "org.gnome.Nautilus");
+        assert_eq!(clients[0].workspace.id, 2);
+        assert_eq!(clients[0].pid, 4242);
+    }
+
+    #[test]
+    fn window_events_carry_the_full_address() {
+        assert_eq!(
+            window_event("openwindow>>55d0c5a8e0b0,2,foot,vim a, b"),
+            Some(WindowEvent::Opened(Window {
+                id: "0x55d0c5a8e0b0".to_string(),
+                app_id: "foot".to_string(),
+                title: "vim a, b".to_string(),
+            }))
+        );
+        assert_eq!(
+            window_event("closewindow>>55d0c5a8e0b0"),
+            Some(WindowEvent::Closed(Window {
+                id: "0x55d0c5a8e0b0".to_string(),
+                ..Default::default()
+            }))
+        );
+        assert_eq!(window_event("activewindow>>foot,vim"), None);
+    }
+
+    #[test]
+    fn commands_reach_the_shell_unchanged() {
+        let command = ["sh", "-c", "echo 'it''s' $HOME"].map(String::from);
+        assert_eq!(
+            shell_line(&command),
+            r#"'sh' '-c' 'echo '\''it'\'''\''s'\'' $HOME'"#
+        );
+    }
+}
@@ -1,14 +1,23 @@
+use crate::network;
+use relm4::gtk::gio;
+use relm4::gtk::gio::prelude::*;
+use std::fs;
 use std::path::{Path, PathBuf};
+use std::sync::{Mutex, PoisonError};
 
 const ICON_SIZES: &[u32] = &[256, 128, 96, 64, 48, 32, 24, 16];
+/// Searched after the user's theme, in case it doesn't inherit from them
 const ICON_THEMES: &[&str] = &["hicolor", "Adwaita", "gnome"];
+const INTERFACE_SCHEMA: &str = "org.gnome.desktop.interface";
 const ICON_EXTENSIONS: &[&str] = &[".png", ".svg", ".xpm"];
+const SYMBOLIC_SUFFIX: &str = "-symbolic";
 
 pub fn resolve_icon_path(icon_name: &str) -> Option<String> {
-    // If it's already an absolute path and exists, use it
+    // If it's already an absolute path and exists, use it. On a network filesystem
+    // checking would reach it, whether it is shown is up to the `network` flag.
     if icon_name.starts_with('/') {
         let path = Path::new(icon_name);
-        if path.exists() {
+        if network::is_remote(path) || path.exists() {
             return Some(icon_name.to_string());
         }
     }
@@ -53,16 +62,29 @@ fn find_in_icon_themes(icon_name: &str) -> Option<String> {
         .trim_end_matches(".svg")
         .trim_end_matches(".xpm");
 
-    let home = std::env::var("HOME").unwrap_or_default();
-    let icon_base_dirs = vec![
-        PathBuf::from("/usr/share/icons"),
-        PathBuf::from(format!("{}/.local/share/icons", home)),
-        PathBuf::from(format!("{}/.icons", home)),
-    ];
+    if let Some(path) = find_themed_icon(icon_base) {
+        return Some(path);
+    }
+
+    // Some apps only ship a symbolic variant
+    if !icon_base.ends_with(SYMBOLIC_SUFFIX) {
+        return find_themed_icon(&format!("{}{}", icon_base, SYMBOLIC_SUFFIX));
+    }
+
+    None
+}
+
+/// Whether an icon name or path refers to a symbolic icon, which needs recoloring
+pub fn is_symbolic(icon: &str) -> bool {
+    icon.trim_end_matches(".svg").ends_with(SYMBOLIC_SUFFIX)
+}
+
+fn find_themed_icon(icon_base: &str) -> Option<String> {
+    let themes = icon_themes();
 
     // Try each theme
-    for base_dir in &icon_base_dirs {
-        for theme in ICON_THEMES {
+    for base_dir in &icon_base_dirs() {
+        for theme in &themes {
             // Try each size (larger sizes first)
             for &size in ICON_SIZES {
                 let size_dirs = vec![
@@ -71,6 +93,7 @@ fn find_in_icon_themes(icon_name: &str) -> Option<String> {
                     format!("{size}x{size}/mimetypes"),
                     "scalable/apps".to_string(),
                     "scalable/places".to_string(),
+                    "symbolic/apps".to_string(),
                 ];
 
                 for size_dir in &size_dirs {
@@ -99,6 +122,91 @@ fn find_in_icon_themes(icon_name: &str) -> Option<String> {
     None
 }
 
-pub fn get_fallback_icon() -> &'static str {
-    "application-x-executable"
+fn icon_base_dirs() -> Vec<PathBuf> {
+    let home = std::env::var("HOME").unwrap_or_default();
+    vec![
+        PathBuf::from("/usr/share/icons"),
+        PathBuf::from(format!("{}/.local/share/icons", home)),
+        PathBuf::from(format!("{}/.icons", home)),
+    ]
+}
+
+/// Themes to search in order: the configured theme and everything it
+/// inherits from, then the hardcoded fallbacks
+pub fn icon_themes() -> Vec<String> {
+    let mut themes = THEMES.lock().unwrap_or_else(PoisonError::into_inner);
+    if themes.is_empty() {
+        *themes = load_icon_themes();
+    }
+    themes.clone()
+}
+
+/// Read the configured theme again on the next lookup, e.g. after switching themes
+pub fn reload_icon_themes() {
+    THEMES
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .clear();
+}
+
+/// Looked up once, the settings are only read again after `reload_icon_themes`
+static THEMES: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+fn load_icon_themes() -> Vec<String> {
+    let mut themes = vec![];
+
+    if let Some(theme) = configured_icon_theme() {
+        collect_theme_chain(&theme, &mut themes);
+    }
+
+    for theme in ICON_THEMES {
+        if !themes.iter().any(|t| t == theme) {
+            themes.push(theme.to_string());
+        }
+    }
+
+    themes
+}
+
+/// The icon theme picked in GNOME settings, if its schema is installed
+fn configured_icon_theme() -> Option<String> {
+    let schema = gio::SettingsSchemaSource::default()?.lookup(INTERFACE_SCHEMA, true)?;
+    let settings = gio::Settings::new_full(&schema, None::<&gio::SettingsBackend>, None);
+    let theme = settings.string("icon-theme").to_string();
+
+    if theme.is_empty() { None } else { Some(theme) }
+}
+
+fn collect_theme_chain(theme: &str, themes: &mut Vec<String>) {
+    // Guards against inheritance loops as well as duplicates
+    if themes.iter().any(|t| t == theme) {
+        return;
+    }
+    themes.push(theme.to_string());
+
+    for parent in theme_parents(theme) {
+        collect_theme_chain(&parent, themes);
+    }
+}
+
+/// The `Inherits` list from the theme's index.theme
+fn theme_parents(theme: &str) -> Vec<String> {
+    let Some(index) = icon_base_dirs()
+        .iter()
+        .find_map(|dir| fs::read_to_string(dir.join(theme).join("index.theme")).ok())
+    else {
+        return vec![];
+    };
+
+    index
+        .lines()
+        .find_map(|line| line.strip_prefix("Inherits="))
+        .map(|parents| {
+            parents
+                .split(',')
+                .map(|parent| parent.trim().to_string())
+                .filter(|parent| !parent.is_empty())
+                .collect()
+        })
+        .unwrap_or_default()
 }
@@ -0,0 +1,416 @@
+use crate::cli::ImportSource;
+use crate::config::Config;
+use crate::error::{LauncherError, Result};
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+
+/// Translate another launcher's config into ours, returning the options that were imported
+pub fn import_config(source: ImportSource, config: &mut Config) -> Result<Vec<String>> {
+    let path = config_path(source)?;
+    let data = fs::read_to_string(&path)
+        .map_err(|e| LauncherError::Config(format!("Failed to read {}: {}", path.display(), e)))?;
+
+    let imported = match source {
+        ImportSource::Rofi => import_rofi(&data, config),
+        ImportSource::Wofi => import_wofi(&data, config),
+        ImportSource::Fuzzel => import_fuzzel(&data, config),
+    };
+
+    Ok(imported)
+}
+
+fn config_path(source: ImportSource) -> Result<PathBuf> {
+    let config_dir = dirs::config_dir().ok_or_else(|| {
+        LauncherError::Io(std::io::Error::new(
+            std::io::ErrorKind::NotFound,
+            "Could not find config directory",
+        ))
+    })?;
+
+    Ok(match source {
+        ImportSource::Rofi => config_dir.join("rofi").join("config.rasi"),
+        ImportSource::Wofi => config_dir.join("wofi").join("config"),
+        ImportSource::Fuzzel => config_dir.join("fuzzel").join("fuzzel.ini"),
+    })
+}
+
+fn import_rofi(data: &str, config: &mut Config) -> Vec<String> {
+    let sections = parse_sections(&strip_rasi_comments(data), ':');
+    let mut imported = vec![];
+
+    if let Some(options) = sections.get("configuration") {
+        if let Some(terminal) = options.get("terminal") {
+            // rofi appends "-e" itself
+            config.terminal = format!("{} -e", terminal);
+            imported.push("terminal".to_string());
+        }
+
+        if let Some(prompt) = options
+            .get("display-drun")
+            .or_else(|| options.get("display-run"))
+        {
+            config.prompt = Some(prompt.clone());
+            imported.push("prompt".to_string());
+        }
+
+        let keybinds = [
+            ("kb-row-up", &mut config.keybinds.up),
+            ("kb-row-down", &mut config.keybinds.down),
+            ("kb-accept-entry", &mut config.keybinds.accept),
+            ("kb-cancel", &mut config.keybinds.cancel),
+        ];
+        for (key, binds) in keybinds {
+            if let Some(value) = options.get(key) {
+                *binds = value
+                    .split(',')
+                    .filter_map(|bind| to_accelerator(bind, '+'))
+                    .collect();
+                imported.push(key.to_string());
+            }
+        }
+    }
+
+    if let Some(width) = sections
+        .get("window")
+        .and_then(|window| window.get("width"))
+        .and_then(|width| parse_pixels(width))
+    {
+        config.width = width;
+        imported.push("width".to_string());
+    }
+
+    imported
+}
+
+fn import_wofi(data: &str, config: &mut Config) -> Vec<String> {
+    let sections = parse_sections(data, '=');
+    let mut imported = vec![];
+
+    let Some(options) = sections.get("") else {
+        return imported;
+    };
+
+    if let Some(terminal) = options.get("term") {
+        // wofi appends "-e" itself
+        config.terminal = format!("{} -e", terminal);
+        imported.push("term".to_string());
+    }
+
+    if let Some(prompt) = options.get("prompt") {
+        config.prompt = Some(prompt.clone());
+        imported.push("prompt".to_string());
+    }
+
+    if let Some(width) = options.get("width").and_then(|w| parse_pixels(w)) {
+        config.width = width;
+        imported.push("width".to_string());
+    }
+
+    if let Some(height) = options.get("height").and_then(|h| parse_pixels(h)) {
+        config.height = height;
+        imported.push("height".to_string());
+    }
+
+    let keybinds = [
+        ("key_up", &mut config.keybinds.up),
+        ("key_down", &mut config.keybinds.down),
+        ("key_submit", &mut config.keybinds.accept),
+        ("key_exit", &mut config.keybinds.cancel),
+    ];
+    for (key, binds) in keybinds {
+        if let Some(value) = options.get(key) {
+            *binds = value
+                .split(',')
+                .filter_map(|bind| to_accelerator(bind, '-'))
+                .collect();
+            imported.push(key.to_string());
+        }
+    }
+
+    imported
+}
+
+fn import_fuzzel(data: &str, config: &mut Config) -> Vec<String> {
+    let sections = parse_sections(data, '=');
+    let mut imported = vec![];
+
+    if let Some(options) = sections.get("main") {
+        // fuzzel's terminal option already includes the exec flag
+        if let Some(terminal) = options.get("terminal") {
+            config.terminal = match std::env::var("TERMINAL") {
+                Ok(value) => terminal.replace("$TERMINAL", &value),
+                Err(_) => terminal.clone(),
+            };
+            imported.push("terminal".to_string());
+        }
+
+        if let Some(prompt) = options.get("prompt") {
+            config.prompt = Some(prompt.clone());
+            imported.push("prompt".to_string());
+        }
+    }
+
+    if let Some(options) = sections.get("key-bindings") {
+        let keybinds = [
+            ("prev", &mut config.keybinds.up),
+            ("next", &mut config.keybinds.down),
+            ("execute", &mut config.keybinds.accept),
+            ("cancel", &mut config.keybinds.cancel),
+        ];
+        for (key, binds) in keybinds {
+            if let Some(value) = options.get(key) {
+                *binds = value
+                    .split_whitespace()
+                    .filter_map(|bind| to_accelerator(bind, '+'))
+                    .collect();
+                imported.push(key.to_string());
+            }
+        }
+    }
+
+    imported
+}
+
+/// Parse `key<sep>value` lines grouped by `[section]` or `section {` headers.
+/// Lines before any header end up in the "" section.
+fn parse_sections(data: &str, separator: char) -> HashMap<String, HashMap<String, String>> {
+    let mut sections: HashMap<String, HashMap<String, String>> = HashMap::new();
+    let mut current = String::new();
+
+    for line in data.lines() {
+        let line = line.trim();
+
+        if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+            continue;
+        }
+
+        if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            current = name.trim().to_string();
+            continue;
+        }
+
+        if let Some(name) = line.strip_suffix('{') {
+            current = name.trim().to_string();
+            continue;
+        }
+
+        if line == "}" {
+            current.clear();
+            continue;
+        }
+
+        if let Some((key, value)) = line.split_once(separator) {
+            let value = value.trim().trim_end_matches(';').trim().trim_matches('"');
+            sections
+                .entry(current.clone())
+                .or_default()
+                .insert(key.trim().to_string(), value.to_string());
+        }
+    }
+
+    sections
+}
+
+/// Drop `/* */` and `//` comments, leaving quoted values like "https://…" alone
+fn strip_rasi_comments(data: &str) -> String {
+    let mut result = String::new();
+    let mut chars = data.chars().peekable();
+    let mut quoted = false;
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' if quoted => {
+                result.push(c);
+                result.extend(chars.next());
+            }
+            '"' => {
+                quoted = !quoted;
+                result.push(c);
+            }
+            '/' if !quoted && chars.peek() == Some(&'*') => {
+                chars.next();
+                let mut previous = None;
+                for c in chars.by_ref() {
+                    if previous == Some('*') && c == '/' {
+                        break;
+                    }
+                    previous = Some(c);
+                }
+            }
+            '/' if !quoted && chars.peek() == Some(&'/') => {
+                // Keep the newline so the next line stays separate
+                while chars.next_if(|&c| c != '\n').is_some() {}
+            }
+            '\n' => {
+                // rasi strings can't span lines, so an unclosed quote ends here
+                quoted = false;
+                result.push(c);
+            }
+            _ => result.push(c),
+        }
+    }
+
+    result
+}
+
+/// Accepts "600" or "600px"; relative sizes like "50%" can't be translated
+fn parse_pixels(value: &str) -> Option<i32> {
+    value.trim().trim_end_matches("px").parse().ok()
+}
+
+/// Convert a binding like "Control+k" into GTK accelerator syntax ("<Control>k")
+fn to_accelerator(bind: &str, separator: char) -> Option<String> {
+    let bind = bind.trim();
+    if bind.is_empty() {
+        return None;
+    }
+
+    // A trailing separator means the separator itself is the key, e.g. "Control++"
+    let (modifiers, key) = match bind.strip_suffix(separator) {
+        Some(rest) => {
+            let key = match separator {
+                '+' => "plus",
+                '-' => "minus",
+                _ => return None,
+            };
+            // "Control+" alone has no key
+            let modifiers = match rest {
+                "" => rest,
+                _ => rest.strip_suffix(separator)?,
+            };
+            (modifiers, key)
+        }
+        None => bind.rsplit_once(separator).unwrap_or(("", bind)),
+    };
+
+    let mut accelerator = String::new();
+    for modifier in modifiers.split(separator).filter(|m| !m.is_empty()) {
+        let modifier = match modifier.to_lowercase().as_str() {
+            "control" | "ctrl" => "<Control>",
+            "shift" => "<Shift>",
+            "alt" | "mod1" => "<Alt>",
+            "super" | "mod4" => "<Super>",
+            _ => return None,
+        };
+        accelerator.push_str(modifier);
+    }
+    accelerator.push_str(key);
+
+    Some(accelerator)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn imports_rofi() {
+        let data = r#"
+/* Written by hand */
+configuration {
+    terminal: "kitty"; // the one I use
+    display-drun: "https://example.org // apps";
+    kb-row-up: "Up,Control+p";
+    kb-accept-entry: "Control++";
+}
+window {
+    width: 700px;
+}
+"#;
+        let mut config = Config::default();
+
+        let imported = import_rofi(data, &mut config);
+
+        assert_eq!(
+            imported,
+            [
+                "terminal",
+                "prompt",
+                "kb-row-up",
+                "kb-accept-entry",
+                "width"
+            ]
+        );
+        assert_eq!(config.terminal, "kitty -e");
+        assert_eq!(
+            config.prompt.as_deref(),
+            Some("https://example.org // apps")
+        );
+        assert_eq!(config.keybinds.up, ["Up", "<Control>p"]);
+        assert_eq!(config.keybinds.accept, ["<Control>plus"]);
+        assert_eq!(config.width, 700);
+    }
+
+    #[test]
+    fn imports_wofi() {
+        let data = "term=foot\nprompt=Run\nwidth=50%\nheight=400\nkey_down=Down,Ctrl-n\n";
+        let mut config = Config::default();
+        let width = config.width;
+
+        let imported = import_wofi(data, &mut config);
+
+        assert_eq!(imported, ["term", "prompt", "height", "key_down"]);
+        assert_eq!(config.terminal, "foot -e");
+        assert_eq!(config.prompt.as_deref(), Some("Run"));
+        assert_eq!(config.width, width);
+        assert_eq!(config.height, 400);
+        assert_eq!(config.keybinds.down, ["Down", "<Control>n"]);
+    }
+
+    #[test]
+    fn imports_fuzzel() {
+        let data = "\
+[main]
+terminal=alacritty -e
+prompt=\"> \"
+
+[key-bindings]
+cancel=Escape Control+g Control+c
+";
+        let mut config = Config::default();
+
+        let imported = import_fuzzel(data, &mut config);
+
+        assert_eq!(imported, ["terminal", "prompt", "cancel"]);
+        assert_eq!(config.terminal, "alacritty -e");
+        assert_eq!(config.prompt.as_deref(), Some("> "));
+        assert_eq!(
+            config.keybinds.cancel,
+            ["Escape", "<Control>g", "<Control>c"]
+        );
+    }
+
+    #[test]
+    fn parses_sections() {
+        let data = "top = 1\n; comment\n[one]\na = \"b\"\ntwo {\n  c = d;\n}\n";
+
+        let sections = parse_sections(data, '=');
+
+        assert_eq!(sections[""]["top"], "1");
+        assert_eq!(sections["one"]["a"], "b");
+        assert_eq!(sections["two"]["c"], "d");
+    }
+
+    #[test]
+    fn strips_rasi_comments_outside_quotes() {
+        let data = "a: \"x /* y */ // z\"; /* gone\nstill gone */ b: 1; // gone\nc: 2;";
+
+        assert_eq!(
+            strip_rasi_comments(data),
+            "a: \"x /* y */ // z\";  b: 1; \nc: 2;"
+        );
+    }
+
+    #[test]
+    fn converts_bindings_to_accelerators() {
+        assert_eq!(
+            to_accelerator("Control+Shift+k", '+').as_deref(),
+            Some("<Control><Shift>k")
+        );
+        assert_eq!(to_accelerator("Alt--", '-').as_deref(), Some("<Alt>minus"));
+        assert_eq!(to_accelerator("+", '+').as_deref(), Some("plus"));
+        assert_eq!(to_accelerator("Control+", '+'), None);
+        assert_eq!(to_accelerator("Hyper+k", '+'), None);
+    }
+}
@@ -0,0 +1,315 @@
+use crate::config::Config;
+use crate::error::{LauncherError, Result};
+use crate::profile;
+use crate::types::Entry;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::time::{Duration, Instant, UNIX_EPOCH};
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+/// In bytes, from the config of the latest request
+static BUDGET: AtomicUsize = AtomicUsize::new(0);
+/// Like `BUDGET`, 0 for no limit
+static MAX_ENTRIES: AtomicUsize = AtomicUsize::new(0);
+static INDEX: Mutex<Vec<Slot>> = Mutex::new(Vec::new());
+
+/// When a source's indexed entries are out of date, besides refreshing the cache
+#[derive(Clone, Copy)]
+pub struct Policy {
+    /// Load again once the entries are this old
+    pub ttl: Duration,
+    /// Changes whenever the source does, e.g. from the modification times of its files.
+    /// Meant to be far cheaper than loading the source.
+    pub key: fn() -> String,
+}
+
+/// How much the index may keep, from the config
+#[derive(Clone, Copy)]
+pub struct Limits {
+    /// 0 disables the index
+    pub budget_mb: usize,
+    /// Entries across all sources, 0 for no limit besides the budget
+    pub max_entries: usize,
+}
+
+impl Limits {
+    pub fn from_config(config: &Config) -> Self {
+        Self {
+            budget_mb: config.index_budget_mb,
+            max_entries: config.index_max_entries,
+        }
+    }
+
+    fn fit(&self, bytes: usize, entries: usize) -> bool {
+        bytes <= self.budget_mb * 1024 * 1024
+            && (self.max_entries == 0 || entries <= self.max_entries)
+    }
+}
+
+/// Entries of one slow source kept in memory, e.g. the installed games
+struct Slot {
+    name: &'static str,
+    entries: Vec<Entry>,
+    bytes: usize,
+    used: Instant,
+    loaded: Instant,
+    /// The policy's key when the entries were loaded
+    key: String,
+}
+
+/// What the service keeps in memory, written for `adwlauncher doctor`
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Stats {
+    pub budget_bytes: usize,
+    /// Written by services from before the entry limit
+    #[serde(default)]
+    pub max_entries: usize,
+    pub used_bytes: usize,
+    pub sources: Vec<SourceStats>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SourceStats {
+    pub name: String,
+    pub entries: usize,
+    pub bytes: usize,
+}
+
+/// Only the service lives long enough for the index to pay off, one-off runs load directly
+pub fn set_enabled(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+/// The source's indexed entries, loading and indexing them first if they aren't or
+/// the policy says they are out of date. Least recently used sources are evicted
+/// to stay within the limits.
+pub fn get_or_load(
+    name: &'static str,
+    limits: Limits,
+    policy: Policy,
+    load: impl FnOnce() -> Result<Vec<Entry>>,
+) -> Result<Vec<Entry>> {
+    if !ENABLED.load(Ordering::Relaxed) || limits.budget_mb == 0 {
+        return load();
+    }
+    BUDGET.store(limits.budget_mb * 1024 * 1024, Ordering::Relaxed);
+    MAX_ENTRIES.store(limits.max_entries, Ordering::Relaxed);
+    let key = (policy.key)();
+
+    if let Ok(mut slots) = INDEX.lock()
+        && let Some(slot) = slots
+            .iter_mut()
+            .find(|slot| slot.name == name && slot.key == key && slot.loaded.elapsed() < policy.ttl)
+    {
+        slot.used = Instant::now();
+        return Ok(slot.entries.clone());
+    }
+
+    let entries = load()?;
+    let bytes = entries.iter().map(entry_size).sum();
+
+    if let Ok(mut slots) = INDEX.lock() {
+        slots.retain(|slot| slot.name != name);
+        slots.push(Slot {
+            name,
+            entries: entries.clone(),
+            bytes,
+            used: Instant::now(),
+            loaded: Instant::now(),
+            key,
+        });
+        evict(&mut slots, limits);
+        write_stats(&slots);
+    }
+
+    Ok(entries)
+}
+
+/// Drop the least recently used sources until the rest fit, a source that
+/// doesn't fit on its own is never kept
+fn evict(slots: &mut Vec<Slot>, limits: Limits) {
+    while !limits.fit(
+        slots.iter().map(|slot| slot.bytes).sum(),
+        slots.iter().map(|slot| slot.entries.len()).sum(),
+    ) {
+        let Some(oldest) = (0..slots.len()).min_by_key(|&index| slots[index].used) else {
+            break;
+        };
+        slots.remove(oldest);
+    }
+}
+
+/// Forget everything, the next request for each source loads it again
+pub fn clear() {
+    if let Ok(mut slots) = INDEX.lock() {
+        slots.clear();
+        if ENABLED.load(Ordering::Relaxed) {
+            write_stats(&slots);
+        }
+    }
+}
+
+/// A key from the modification times of files and directories, for `Policy::key`.
+/// Missing ones count too, so one appearing changes the key.
+pub fn modified_key(paths: impl IntoIterator<Item = impl AsRef<Path>>) -> String {
+    paths
+        .into_iter()
+        .map(|path| {
+            fs::metadata(path)
+                .and_then(|metadata| metadata.modified())
+                .ok()
+                .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+                .map_or(0, |since| since.as_nanos())
+                .to_string()
+        })
+        .collect::<Vec<String>>()
+        .join(",")
+}
+
+/// Roughly the heap and inline memory an entry takes
+fn entry_size(entry: &Entry) -> usize {
+    std::mem::size_of::<Entry>()
+        + entry.id.capacity()
+        + entry.exec.capacity()
+        + entry.icon.capacity()
+        + entry.name.capacity()
+        + entry.category.capacity()
+        + entry.generic_name.capacity()
+        + entry.comment.capacity()
+        + entry.keywords.capacity() * std::mem::size_of::<String>()
+        + entry
+            .keywords
+            .iter()
+            .map(|keyword| keyword.capacity())
+            .sum::<usize>()
+        + entry.mime_types.capacity() * std::mem::size_of::<String>()
+        + entry
+            .mime_types
+            .iter()
+            .map(|mime| mime.capacity())
+            .sum::<usize>()
+        + entry
+            .desktop_file
+            .as_ref()
+            .map_or(0, |path| path.capacity())
+}
+
+fn stats_path() -> Result<PathBuf> {
+    let cache_dir = dirs::cache_dir().ok_or_else(|| {
+        LauncherError::Io(std::io::Error::new(
+            std::io::ErrorKind::NotFound,
+            "Could not find cache directory",
+        ))
+    })?;
+    Ok(cache_dir.join(profile::directory()).join("index.toml"))
+}
+
+fn write_stats(slots: &[Slot]) {
+    let stats = Stats {
+        budget_bytes: BUDGET.load(Ordering::Relaxed),
+        max_entries: MAX_ENTRIES.load(Ordering::Relaxed),
+        used_bytes: slots.iter().map(|slot| slot.bytes).sum(),
+        sources: slots
+            .iter()
+            .map(|slot| SourceStats {
+                name: slot.name.to_string(),
+                entries: slot.entries.len(),
+                bytes: slot.bytes,
+            })
+            .collect(),
+    };
+
+    let result = stats_path().and_then(|path| {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let data = toml::to_string_pretty(&stats).map_err(|e| {
+            LauncherError::Config(format!("Failed to serialize index stats: {}", e))
+        })?;
+        fs::write(path, data)?;
+        Ok(())
+    });
+    if let Err(e) = result {
+        eprintln!("Failed to write index stats: {:#}", e);
+    }
+}
+
+/// The stats the running service last wrote, if it keeps an index
+pub fn read_stats() -> Option<Stats> {
+    let data = fs::read_to_string(stats_path().ok()?).ok()?;
+    toml::from_str(&data).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn slot(name: &'static str, entries: usize, used: Instant) -> Slot {
+        let entries: Vec<Entry> = (0..entries)
+            .map(|index| Entry {
+                id: format!("{}-{}", name, index),
+                ..Default::default()
+            })
+            .collect();
+        Slot {
+            name,
+            bytes: entries.iter().map(entry_size).sum(),
+            entries,
+            used,
+            loaded: used,
+            key: String::new(),
+        }
+    }
+
+    fn names(slots: &[Slot]) -> Vec<&str> {
+        slots.iter().map(|slot| slot.name).collect()
+    }
+
+    #[test]
+    fn the_least_recently_used_sources_go_first() {
+        let start = Instant::now();
+        let mut slots = vec![
+            slot("man", 3, start + Duration::from_secs(2)),
+            slot("games", 3, start),
+            slot("bookmarks", 3, start + Duration::from_secs(1)),
+        ];
+        let limits = Limits {
+            budget_mb: 1,
+            max_entries: 6,
+        };
+
+        evict(&mut slots, limits);
+        assert_eq!(names(&slots), ["man", "bookmarks"]);
+    }
+
+    #[test]
+    fn sources_stay_within_the_budget() {
+        let start = Instant::now();
+        let mut slots = vec![
+            slot("games", 5000, start),
+            slot("man", 10, start + Duration::from_secs(1)),
+        ];
+        let limits = Limits {
+            budget_mb: 1,
+            max_entries: 0,
+        };
+
+        evict(&mut slots, limits);
+        assert_eq!(names(&slots), ["man"]);
+    }
+
+    #[test]
+    fn a_source_over_the_limit_on_its_own_is_not_kept() {
+        let mut slots = vec![slot("games", 20, Instant::now())];
+        let limits = Limits {
+            budget_mb: 1,
+            max_entries: 10,
+        };
+
+        evict(&mut slots, limits);
+        assert!(slots.is_empty());
+    }
+}
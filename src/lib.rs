@@ -0,0 +1,50 @@
+//! Discovery, search and launching, shared by the launcher and its fuzz targets
+
+// Suppress warnings from relm4 macro-generated code
+#![allow(unused_assignments)]
+
+pub mod actions;
+pub mod app_discovery;
+pub mod autostart;
+pub mod browsers;
+pub mod bundle;
+pub mod cache;
+pub mod categories;
+pub mod cli;
+pub mod closed;
+pub mod commands;
+pub mod compositor;
+pub mod config;
+pub mod crash;
+pub mod doctor;
+pub mod duplicates;
+pub mod environment;
+pub mod error;
+pub mod exec;
+pub mod folders;
+pub mod gamepad;
+pub mod games;
+pub mod help;
+pub mod history;
+pub mod hooks;
+pub mod hyprland;
+pub mod icon;
+pub mod import;
+pub mod index;
+pub mod man;
+pub mod mime;
+pub mod network;
+pub mod niri;
+pub mod notify;
+pub mod onboarding;
+pub mod origin;
+pub mod preview;
+pub mod profile;
+pub mod rules;
+pub mod search;
+pub mod session;
+pub mod state;
+pub mod thumbnail;
+pub mod types;
+pub mod usage;
+pub mod wine;
@@ -1,83 +1,550 @@
 // Suppress warnings from relm4 macro-generated code
 #![allow(unused_assignments)]
 
-mod app_discovery;
-mod cache;
-mod error;
-mod icon;
-mod types;
-mod usage;
-
-use app_discovery::{get_entries, launch_entry};
-use fuzzy_matcher::FuzzyMatcher;
-use fuzzy_matcher::skim::SkimMatcherV2;
+use adwlauncher::{
+    actions, app_discovery, autostart, browsers, categories, cli, closed, commands, compositor,
+    config, crash, doctor, duplicates, environment, error, folders, gamepad, help, icon, index,
+    mime, network, notify, onboarding, origin, preview, profile, rules, search, session, state,
+    thumbnail, types, usage,
+};
+
+use actions::DestructiveAction;
+use adw::prelude::AdwDialogExt;
+use app_discovery::{
+    Provider, WindowWatcher, get_desktop_entries_cached, get_entries_for_mode, launch_entry,
+    query_providers,
+};
+use clap::{Parser, ValueEnum};
+use cli::{Cli, Command, Mode, RunOptions};
+use config::{Config, Keybinds, Layout, Position, SizePreset};
 use gtk::prelude::WidgetExt;
 use gtk::prelude::*;
-use gtk4_layer_shell::{Layer, LayerShell};
+use gtk4_layer_shell::{Edge, Layer, LayerShell};
+use onboarding::{Onboarding, OnboardingOutput};
+use preview::Preview;
 use relm4::factory::FactoryVecDeque;
 use relm4::gtk::CssProvider;
 use relm4::prelude::*;
-use types::Entry;
+use search::{RankCache, SearchOptions};
+use state::State;
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use types::{Entry, OpenType};
 use usage::UsageTracker;
 
 #[derive(Debug)]
 struct EntryView {
     entry: Entry,
     selected: bool,
+    hovered: bool,
+    style: RowStyle,
+    /// The file the row stands for, see `app_discovery::entry_file`
+    file: Option<PathBuf>,
+    /// Section title shown above the row, for the first row of a section
+    header: Option<&'static str>,
+    /// Where the app comes from, for the less used of two copies of one app
+    badge: Option<&'static str>,
+    /// The usage boost and launch count, for entries launched before
+    usage: Option<(f64, u32)>,
+}
+
+/// How result rows are drawn, from the config
+#[derive(Debug, Clone, Copy)]
+struct RowStyle {
+    /// None hides the icon
+    icon_size: Option<i32>,
+    /// Show action buttons on the selected or hovered row
+    actions: bool,
+    /// Rows standing for a file can be dragged out as its URI
+    drag: bool,
+    /// Rows are chips in the command bar, without section headers
+    chips: bool,
+    /// Show how often and recently each entry was launched
+    usage: bool,
+    /// Files on network filesystems get thumbnails too
+    network: bool,
+}
+
+#[derive(Debug)]
+enum EntryMsg {
+    Select(bool),
+    Hover(bool),
+}
+
+/// Secondary actions offered as buttons at the end of a row,
+/// the same ones keyboard users reach through key bindings
+#[derive(Debug, Clone, Copy)]
+enum RowAction {
+    RunInTerminal,
+    CloseWindow,
+    KillProcess,
+    Uninstall,
+    OpenWith,
+}
+
+impl RowAction {
+    fn for_entry(entry: &Entry, file: Option<&Path>) -> Vec<RowAction> {
+        match entry.open_type {
+            OpenType::Graphical | OpenType::Terminal => {
+                let mut actions = vec![];
+                // Terminal apps already run in one
+                if entry.open_type == OpenType::Graphical {
+                    actions.push(RowAction::RunInTerminal);
+                }
+                if app_discovery::flatpak_app_id(entry).is_some() {
+                    actions.push(RowAction::Uninstall);
+                }
+                actions
+            }
+            OpenType::Window => vec![RowAction::CloseWindow, RowAction::KillProcess],
+            OpenType::Text if file.is_some() => {
+                vec![RowAction::OpenWith]
+            }
+            OpenType::Text
+            | OpenType::Power
+            | OpenType::MimeType
+            | OpenType::Autostart
+            | OpenType::Help
+            | OpenType::Variable
+            | OpenType::Folder
+            | OpenType::Category => vec![],
+        }
+    }
+
+    fn icon_name(&self) -> &'static str {
+        match self {
+            RowAction::RunInTerminal => "utilities-terminal-symbolic",
+            RowAction::CloseWindow => "window-close-symbolic",
+            RowAction::KillProcess => "process-stop-symbolic",
+            RowAction::Uninstall => "user-trash-symbolic",
+            RowAction::OpenWith => "document-open-symbolic",
+        }
+    }
+
+    fn tooltip(&self) -> &'static str {
+        match self {
+            RowAction::RunInTerminal => "Run in Terminal",
+            RowAction::CloseWindow => "Close Window",
+            RowAction::KillProcess => "Kill Process",
+            RowAction::Uninstall => "Uninstall",
+            RowAction::OpenWith => "Open With…",
+        }
+    }
+}
+
+impl EntryView {
+    fn usage_tooltip(&self) -> Option<String> {
+        self.usage.map(|(_, count)| match count {
+            1 => "Launched once".to_string(),
+            count => format!("Launched {} times", count),
+        })
+    }
 }
 
 #[relm4::factory]
 impl FactoryComponent for EntryView {
+    type ParentWidget = gtk::Box;
+    /// The thumbnail of the row's file
+    type CommandOutput = Option<PathBuf>;
+    type Input = EntryMsg;
+    type Output = (usize, RowAction);
+    type Init = (
+        Entry,
+        RowStyle,
+        Option<PathBuf>,
+        Option<&'static str>,
+        Option<&'static str>,
+        Option<(f64, u32)>,
+    );
+
+    view! {
+        #[root]
+        gtk::Box {
+            set_orientation: gtk::Orientation::Vertical,
+            gtk::Label {
+                set_label: self.header.unwrap_or_default(),
+                set_visible: self.header.is_some() && !self.style.chips,
+                set_halign: gtk::Align::Start,
+                set_margin_top: 6,
+                set_css_classes: &["heading", "dim-label"],
+            },
+            #[name = "root_box"]
+            gtk::Box {
+                set_spacing: 6,
+                #[name = "icon_overlay"]
+                gtk::Overlay {
+                    #[wrap(Some)]
+                    set_child = &gtk::Box {
+                        #[name = "icon_image"]
+                        gtk::Image {},
+                        #[name = "avatar"]
+                        adw::Avatar {
+                            set_show_initials: true,
+                        },
+                    },
+                },
+                gtk::Button {
+                    #[watch]
+                    set_css_classes: if self.selected { &["flat", "rounded", "selected"] } else { &["flat", "rounded"] },
+                    set_can_focus: false,
+                    set_focusable: false,
+                    set_hexpand: true,
+                    set_halign: gtk::Align::Start,
+                    gtk::Label {
+                        set_label: &self.entry.name,
+                        set_halign: gtk::Align::Start,
+                    },
+                },
+                gtk::Label {
+                    set_label: self.badge.unwrap_or_default(),
+                    set_visible: self.badge.is_some(),
+                    set_css_classes: &["caption", "origin-badge"],
+                },
+                // Why the entry ranks where it does, usage lifts it
+                gtk::LevelBar {
+                    set_visible: self.usage.is_some() && self.style.usage && !self.style.chips,
+                    set_value: self.usage.map_or(0.0, |(boost, _)| boost),
+                    set_valign: gtk::Align::Center,
+                    set_width_request: 24,
+                    set_tooltip_text: self.usage_tooltip().as_deref(),
+                    add_css_class: "usage-indicator",
+                },
+                #[name = "row_actions"]
+                gtk::Box {
+                    set_spacing: 2,
+                    #[watch]
+                    set_visible: self.style.actions && (self.selected || self.hovered),
+                },
+            }
+        }
+    }
+
+    fn init_model(
+        (entry, style, file, header, badge, usage): Self::Init,
+        _index: &DynamicIndex,
+        _sender: FactorySender<Self>,
+    ) -> Self {
+        Self {
+            entry,
+            selected: false,
+            hovered: false,
+            style,
+            file,
+            header,
+            badge,
+            usage,
+        }
+    }
+
+    fn init_widgets(
+        &mut self,
+        index: &DynamicIndex,
+        root: Self::Root,
+        _returned_widget: &<Self::ParentWidget as relm4::factory::FactoryView>::ReturnedWidget,
+        sender: FactorySender<Self>,
+    ) -> Self::Widgets {
+        let widgets = view_output!();
+
+        set_entry_icon(
+            &widgets.icon_image,
+            &widgets.avatar,
+            &self.entry,
+            self.style.icon_size,
+        );
+        add_origin_emblem(&widgets.icon_overlay, &self.entry, self.style.icon_size);
+
+        // File results show their type until the thumbnail is made off the main loop
+        if self.entry.open_type == OpenType::Text
+            && let (Some(size), Some(path)) = (self.style.icon_size, self.file.clone())
+        {
+            // Guessed from the name alone, so the file isn't read here
+            let (content_type, _uncertain) = gtk::gio::content_type_guess(Some(&path), None);
+            widgets
+                .icon_image
+                .set_from_gicon(&gtk::gio::content_type_get_icon(&content_type));
+            widgets.icon_image.set_pixel_size(size);
+            widgets.icon_image.set_visible(true);
+
+            let network = self.style.network;
+            sender.spawn_oneshot_command(move || {
+                (network || !network::is_remote(&path))
+                    .then(|| thumbnail::get(&path))
+                    .flatten()
+            });
+        }
+
+        if self.style.actions {
+            for action in RowAction::for_entry(&self.entry, self.file.as_deref()) {
+                let button = gtk::Button::from_icon_name(action.icon_name());
+                button.set_tooltip_text(Some(action.tooltip()));
+                button.set_css_classes(&["flat", "circular"]);
+                // Keep keyboard focus in the search entry
+                button.set_focusable(false);
+                let sender = sender.clone();
+                let index = index.clone();
+                button.connect_clicked(move |_| {
+                    let _ = sender.output((index.current_index(), action));
+                });
+                widgets.row_actions.append(&button);
+            }
+
+            let motion = gtk::EventControllerMotion::new();
+            let enter_sender = sender.input_sender().clone();
+            motion.connect_enter(move |_, _, _| enter_sender.emit(EntryMsg::Hover(true)));
+            let leave_sender = sender.input_sender().clone();
+            motion.connect_leave(move |_| leave_sender.emit(EntryMsg::Hover(false)));
+            widgets.root_box.add_controller(motion);
+        }
+
+        // Offered as text/uri-list, which docks and file managers accept
+        if self.style.drag
+            && let Some(path) = &self.file
+        {
+            let files = gtk::gdk::FileList::from_array(&[gtk::gio::File::for_path(path)]);
+            let source = gtk::DragSource::new();
+            source.set_actions(gtk::gdk::DragAction::COPY);
+            source.set_content(Some(&gtk::gdk::ContentProvider::for_value(
+                &files.to_value(),
+            )));
+            let icon = widgets.icon_image.clone();
+            source.connect_drag_begin(move |source, _| {
+                source.set_icon(Some(&gtk::WidgetPaintable::new(Some(&icon))), 0, 0);
+            });
+            widgets.root_box.add_controller(source);
+        }
+
+        widgets
+    }
+
+    fn update(&mut self, msg: Self::Input, _sender: FactorySender<Self>) {
+        match msg {
+            EntryMsg::Select(selected) => self.selected = selected,
+            EntryMsg::Hover(hovered) => self.hovered = hovered,
+        }
+    }
+
+    fn update_cmd_with_view(
+        &mut self,
+        widgets: &mut Self::Widgets,
+        thumbnail: Self::CommandOutput,
+        _sender: FactorySender<Self>,
+    ) {
+        if let Some(thumbnail) = thumbnail {
+            widgets.icon_image.set_from_file(Some(&thumbnail));
+        }
+    }
+}
+
+const TOP_APP_ICON_SIZE: i32 = 48;
+
+/// A big icon in the strip of most used apps shown above the list
+#[derive(Debug)]
+struct TopAppView {
+    entry: Entry,
+    selected: bool,
+    /// None hides the icon
+    icon_size: Option<i32>,
+}
+
+#[relm4::factory]
+impl FactoryComponent for TopAppView {
     type ParentWidget = gtk::Box;
     type CommandOutput = ();
     type Input = bool;
-    type Output = ();
-    type Init = Entry;
+    type Output = usize;
+    type Init = (Entry, Option<i32>);
 
     view! {
         #[root]
-        root_box = gtk::Box {
-            set_spacing: 6,
-            #[name = "icon_image"]
-            gtk::Image {
-                set_pixel_size: 32,
+        gtk::Button {
+            #[watch]
+            set_css_classes: if self.selected { &["flat", "top-app", "selected"] } else { &["flat", "top-app"] },
+            set_can_focus: false,
+            set_focusable: false,
+            set_tooltip_text: Some(&self.entry.name),
+            connect_clicked[sender, index] => move |_| {
+                let _ = sender.output(index.current_index());
             },
-            gtk::Button {
-                #[watch]
-                set_css_classes: if self.selected { &["flat", "rounded", "selected"] } else { &["flat", "rounded"] },
-                set_can_focus: false,
-                set_focusable: false,
-                set_hexpand: true,
-                set_halign: gtk::Align::Start,
+            gtk::Box {
+                set_orientation: gtk::Orientation::Vertical,
+                set_spacing: 4,
+                #[name = "icon_image"]
+                gtk::Image {},
+                #[name = "avatar"]
+                adw::Avatar {
+                    set_show_initials: true,
+                },
                 gtk::Label {
                     set_label: &self.entry.name,
-                    set_halign: gtk::Align::Start,
+                    set_max_width_chars: 10,
+                    set_ellipsize: gtk::pango::EllipsizeMode::End,
+                    add_css_class: "caption",
                 },
             },
         }
     }
 
-    fn init_model(entry: Self::Init, _index: &DynamicIndex, _sender: FactorySender<Self>) -> Self {
+    fn init_model(
+        (entry, icon_size): Self::Init,
+        _index: &DynamicIndex,
+        _sender: FactorySender<Self>,
+    ) -> Self {
         Self {
             entry,
             selected: false,
+            icon_size,
         }
     }
 
     fn init_widgets(
         &mut self,
-        _index: &DynamicIndex,
+        index: &DynamicIndex,
         root: Self::Root,
         _returned_widget: &<Self::ParentWidget as relm4::factory::FactoryView>::ReturnedWidget,
+        sender: FactorySender<Self>,
+    ) -> Self::Widgets {
+        let index = index.clone();
+        let widgets = view_output!();
+
+        set_entry_icon(
+            &widgets.icon_image,
+            &widgets.avatar,
+            &self.entry,
+            self.icon_size,
+        );
+
+        widgets
+    }
+
+    fn update(&mut self, msg: Self::Input, _sender: FactorySender<Self>) {
+        self.selected = msg;
+    }
+}
+
+const GAME_TILE_ICON_SIZE: i32 = 128;
+/// Fixed, so moving up and down in the grid is a fixed step through the results
+const GAME_GRID_COLUMNS: u32 = 4;
+
+/// A large tile in the games grid, mirroring a row of the hidden result list
+#[derive(Debug)]
+struct GameTileView {
+    entry: Entry,
+    selected: bool,
+    /// None hides the icon
+    icon_size: Option<i32>,
+    /// Whether it can be dropped onto another tile to make a folder
+    movable: bool,
+}
+
+#[derive(Debug)]
+enum TileOutput {
+    Clicked(usize),
+    /// The tile at the first index was dropped onto the one at the second
+    Dropped(usize, usize),
+}
+
+#[relm4::factory]
+impl FactoryComponent for GameTileView {
+    type ParentWidget = gtk::FlowBox;
+    type CommandOutput = ();
+    type Input = bool;
+    type Output = TileOutput;
+    type Init = (Entry, Option<i32>, bool);
+
+    view! {
+        #[root]
+        gtk::Button {
+            #[watch]
+            set_css_classes: if self.selected { &["flat", "game-tile", "selected"] } else { &["flat", "game-tile"] },
+            set_can_focus: false,
+            set_focusable: false,
+            connect_clicked[sender, index] => move |_| {
+                let _ = sender.output(TileOutput::Clicked(index.current_index()));
+            },
+            #[name = "tile_box"]
+            gtk::Box {
+                set_orientation: gtk::Orientation::Vertical,
+                set_spacing: 8,
+                #[name = "icon_overlay"]
+                gtk::Overlay {
+                    set_halign: gtk::Align::Center,
+                    #[wrap(Some)]
+                    set_child = &gtk::Box {
+                        #[name = "icon_image"]
+                        gtk::Image {},
+                        #[name = "avatar"]
+                        adw::Avatar {
+                            set_show_initials: true,
+                        },
+                    },
+                },
+                gtk::Label {
+                    set_label: &self.entry.name,
+                    set_max_width_chars: 16,
+                    set_ellipsize: gtk::pango::EllipsizeMode::End,
+                    add_css_class: "title-4",
+                },
+            },
+        }
+    }
+
+    fn init_model(
+        (entry, icon_size, movable): Self::Init,
+        _index: &DynamicIndex,
         _sender: FactorySender<Self>,
+    ) -> Self {
+        Self {
+            entry,
+            selected: false,
+            icon_size,
+            movable,
+        }
+    }
+
+    fn init_widgets(
+        &mut self,
+        index: &DynamicIndex,
+        root: Self::Root,
+        _returned_widget: &<Self::ParentWidget as relm4::factory::FactoryView>::ReturnedWidget,
+        sender: FactorySender<Self>,
     ) -> Self::Widgets {
+        let index = index.clone();
         let widgets = view_output!();
 
-        // Set icon based on whether it's a file path or icon name
-        if self.entry.icon.starts_with('/') {
-            widgets.icon_image.set_from_file(Some(&self.entry.icon));
-        } else {
-            widgets.icon_image.set_icon_name(Some(&self.entry.icon));
+        set_entry_icon(
+            &widgets.icon_image,
+            &widgets.avatar,
+            &self.entry,
+            self.icon_size,
+        );
+        add_origin_emblem(&widgets.icon_overlay, &self.entry, self.icon_size);
+
+        // Dropping an app onto another puts them in a folder, like in GNOME's app grid
+        if self.movable {
+            let source = gtk::DragSource::new();
+            source.set_actions(gtk::gdk::DragAction::MOVE);
+            source.set_content(Some(&gtk::gdk::ContentProvider::for_value(
+                &(index.current_index() as u32).to_value(),
+            )));
+            let tile = widgets.tile_box.clone();
+            source.connect_drag_begin(move |source, _| {
+                source.set_icon(Some(&gtk::WidgetPaintable::new(Some(&tile))), 0, 0);
+            });
+            widgets.tile_box.add_controller(source);
+
+            let target = gtk::DropTarget::new(u32::static_type(), gtk::gdk::DragAction::MOVE);
+            let sender = sender.clone();
+            let index = index.clone();
+            target.connect_drop(move |_, value, _, _| {
+                let Ok(dropped) = value.get::<u32>() else {
+                    return false;
+                };
+                let _ = sender.output(TileOutput::Dropped(dropped as usize, index.current_index()));
+                true
+            });
+            widgets.tile_box.add_controller(target);
         }
 
         widgets
@@ -88,16 +555,175 @@ impl FactoryComponent for EntryView {
     }
 }
 
+/// Show the entry's icon, or a letter avatar when it has none that can be displayed
+fn set_entry_icon(image: &gtk::Image, avatar: &adw::Avatar, entry: &Entry, size: Option<i32>) {
+    let Some(size) = size else {
+        image.set_visible(false);
+        avatar.set_visible(false);
+        return;
+    };
+
+    // dmenu lines are plain text, those naming a file get its thumbnail later
+    if entry.open_type == OpenType::Text {
+        image.set_visible(false);
+        avatar.set_visible(false);
+        return;
+    }
+
+    let has_icon = if entry.icon.is_empty() {
+        false
+    } else if entry.icon.starts_with('/') {
+        std::path::Path::new(&entry.icon).exists()
+    } else {
+        gtk::IconTheme::for_display(&image.display()).has_icon(&entry.icon)
+    };
+
+    image.set_visible(has_icon);
+    avatar.set_visible(!has_icon);
+
+    if has_icon {
+        image.set_pixel_size(size);
+        set_image_icon(image, &entry.icon);
+    } else {
+        avatar.set_size(size);
+        avatar.set_text(Some(&entry.name));
+    }
+}
+
+/// Mark the icon's corner with where the entry comes from, e.g. Flatpak or an open
+/// window, so entries with the same icon and name can be told apart
+fn add_origin_emblem(overlay: &gtk::Overlay, entry: &Entry, icon_size: Option<i32>) {
+    let (Some(size), Some(origin)) = (icon_size, origin::Origin::of(entry)) else {
+        return;
+    };
+    let emblem = gtk::Image::from_icon_name(origin.icon_name());
+    emblem.set_pixel_size((size / 3).max(10));
+    emblem.set_halign(gtk::Align::End);
+    emblem.set_valign(gtk::Align::End);
+    emblem.set_tooltip_text(Some(origin.label()));
+    emblem.add_css_class("origin-emblem");
+    overlay.add_overlay(&emblem);
+}
+
+/// Show an icon name or file path. Symbolic files go through the icon theme
+/// so they get recolored to the foreground color instead of staying black.
+fn set_image_icon(image: &gtk::Image, icon: &str) {
+    if !icon.starts_with('/') {
+        image.set_icon_name(Some(icon));
+    } else if icon::is_symbolic(icon) {
+        let file = gtk::gio::File::for_path(icon);
+        image.set_from_gicon(&gtk::gio::FileIcon::new(&file));
+    } else {
+        image.set_from_file(Some(icon));
+    }
+}
+
+/// Shortest time the "Launching…" row stays up, so it doesn't just flicker
+const LAUNCH_MIN_FEEDBACK: Duration = Duration::from_millis(300);
+/// Longest time to wait for a launched app with startup notification to open a window
+const LAUNCH_TIMEOUT: Duration = Duration::from_secs(10);
+/// Activations this soon after the previous one are treated as key repeat
+const ACTIVATE_DEBOUNCE: Duration = Duration::from_millis(500);
+/// How long the "Undo" toast stays up after a launch
+const UNDO_TIMEOUT_SECS: u32 = 4;
+/// Results shown at once, more are paged through to keep the widget count bounded
+const PAGE_SIZE: usize = 200;
+const PREVIEW_HEIGHT: i32 = 240;
+const COMMAND_BAR_HEIGHT: i32 = 56;
+/// The overview shows a fixed number of tiles per page, like GNOME's app grid
+const OVERVIEW_COLUMNS: u32 = 6;
+const OVERVIEW_ROWS: usize = 4;
+const OVERVIEW_TILE_ICON_SIZE: i32 = 96;
+
+/// What picking an entry of a picker does
+#[derive(Debug)]
+enum PickerKind {
+    /// Make the picked app the default for the MIME type
+    DefaultApp(String),
+    /// Open the file with the picked app
+    OpenWith(PathBuf),
+    /// Launch the picked entry, e.g. a browser profile
+    Launch,
+    /// Launch the picked app of a folder, which may still ask for a profile
+    Folder,
+}
+
+/// A list opened from an entry, e.g. the apps for a MIME type. Pickers stack,
+/// so a picked entry can open the next one, and Escape goes back one level.
+#[derive(Debug)]
+struct Picker {
+    /// Its part of the breadcrumb
+    title: String,
+    /// Shown in the empty search field
+    prompt: String,
+    kind: PickerKind,
+    entries: Vec<Entry>,
+}
+
 struct App {
     selected_name: String,
     selected_index: usize,
     entries: FactoryVecDeque<EntryView>,
+    top_apps: FactoryVecDeque<TopAppView>,
+    top_app_index: Option<usize>,
+    /// The current page as a grid of tiles, filled instead of the list in games mode
+    game_tiles: FactoryVecDeque<GameTileView>,
+    /// Whether the games grid is shown instead of the list
+    grid: bool,
     all_entries: Vec<Entry>,
+    /// The latest entries of each provider, merged into `all_entries` without a mode
+    provider_entries: BTreeMap<Provider, Vec<Entry>>,
+    /// Matches of the last query, to narrow down while typing
+    rank_cache: RankCache,
+    /// Every match for the query with its section header, in display order
+    results: Vec<(Entry, Option<&'static str>)>,
+    /// Index of the page of `results` in the list
+    page: usize,
     search_query: String,
     scrolled_window: gtk::ScrolledWindow,
     search_entry: gtk::SearchEntry,
+    toast_overlay: adw::ToastOverlay,
+    /// Whether the preview pane is shown, it follows the selection while it is
+    preview_open: bool,
+    /// The file in the preview pane, None when the selection isn't a file
+    previewed: Option<PathBuf>,
+    preview_picture: gtk::Picture,
+    preview_text: gtk::Label,
+    /// Name of the app being launched while waiting for its window
+    launching: Option<String>,
+    /// Counts launches, so answers about an earlier one are dropped
+    launch_generation: u64,
+    /// Entry ID and time of the last activation, to drop accidental repeats
+    last_activation: Option<(String, Instant)>,
+    /// The "Undo" toast of the last launch while it is up
+    undo_toast: Option<adw::Toast>,
+    /// Cookie of the idle inhibitor taken while the window is shown
+    idle_inhibit: Option<u32>,
+    /// Open pickers, the last one is shown
+    pickers: Vec<Picker>,
+    /// Category sections showing their apps, the others are collapsed
+    expanded_categories: HashSet<String>,
+    /// The copy launched last of apps installed twice, as remembered in the state
+    preferred_copies: BTreeMap<String, String>,
+    /// Origin badges for the other copies among the results, by entry ID
+    origin_badges: HashMap<String, &'static str>,
+    /// Desktop IDs a time rule hides right now, left out of every section
+    hidden: HashSet<String>,
+    /// Mode from the show-mode action or a typed prefix, until the window hides
+    shown_mode: Option<Mode>,
+    /// The last mode asked for with --mode, read from the state when the window shows
+    remembered_mode: Option<Mode>,
+    /// Counts reloads, so entries from an older one are dropped
+    entries_generation: u64,
+    /// Sources of the current reload that haven't answered yet
+    loading_sources: usize,
+    /// How long ranking the results for the query took
+    search_time: Duration,
     window: adw::ApplicationWindow,
     usage_tracker: UsageTracker,
+    config: Config,
+    onboarding: Option<Controller<Onboarding>>,
+    options: RunOptions,
 }
 
 impl std::fmt::Debug for App {
@@ -113,110 +739,534 @@ impl std::fmt::Debug for App {
 enum Msg {
     NavigateUp,
     NavigateDown,
+    NavigateLeft,
+    NavigateRight,
+    LaunchTopApp(usize),
+    /// A tile of the games grid was clicked
+    LaunchTile(usize),
+    /// A tile was dragged onto another, to put them in a folder
+    TileDropped(usize, usize),
     SelectEntry,
     CloseWindow,
     SearchChanged(String),
+    /// Backspace on an empty query, going back to the global search
+    LeaveScope,
     WindowShown,
+    WindowHidden,
+    /// The session is about to lock or suspend
+    SessionLocked,
+    OnboardingFinished(Box<Config>),
+    CloseSelectedWindow,
+    KillSelectedProcess,
+    UninstallSelected,
+    NextPage,
+    PreviousPage,
+    PerformAction(Box<DestructiveAction>),
+    RowAction(usize, RowAction),
+    UndoLaunch(String),
+    UndoDismissed,
+    /// Show the window from the show and show-mode actions
+    Show(Option<Mode>),
+    RefreshCache,
+    /// Type the selected text result instead of printing it
+    TypeSelected,
+    /// Launch or focus the selected entry and stay open for the next one
+    PeekSelected,
+    /// Show or hide the preview of the selected file
+    TogglePreview,
+    /// List the apps that can open the selected file
+    OpenWithSelected,
+    /// Switch between all windows and those on the current workspace or monitor
+    CycleWindowScope,
+    /// A game controller's button, which only the games grid listens to
+    Gamepad(gamepad::Button),
+}
+
+/// Results of work done off the main loop, so niri's socket, D-Bus and slow
+/// directories never stall the window
+#[derive(Debug)]
+enum CmdOut {
+    /// Entries from a reload, tagged with the reload they answer
+    EntriesLoaded(u64, error::Result<Vec<Entry>>),
+    /// One provider's entries from a reload without a mode, tagged like `EntriesLoaded`
+    ProviderLoaded(u64, Provider, error::Result<Vec<Entry>>),
+    /// The launch request went through, or why it failed, tagged with the launch it answers
+    Launched(u64, Entry, error::Result<()>),
+    /// Carries the launched app's window when one appeared, tagged like `Launched`
+    LaunchFinished(u64, Option<String>),
+    /// Like `Launched`, for a launch that keeps the window open
+    Peeked(Entry, error::Result<()>),
+    /// A file's preview, dropped when the selection moved on
+    PreviewLoaded(PathBuf, error::Result<Preview>),
+    ActionPerformed(DestructiveAction, error::Result<()>),
+    /// Carries the number of apps found, or why the rebuild failed
+    CacheRefreshed(error::Result<usize>),
+    /// A picker whose apps had to be found first, tagged with the entries it was opened from
+    PickerLoaded(u64, Option<Box<Picker>>),
 }
 
 #[relm4::component]
-impl SimpleComponent for App {
+impl Component for App {
     type Input = Msg;
     type Output = ();
-    type Init = ();
+    type Init = RunOptions;
+    type CommandOutput = CmdOut;
 
     view! {
         #[name = "window"]
         adw::ApplicationWindow {
-            set_default_size: (800, 600),
-            gtk::Box {
-                set_orientation: gtk::Orientation::Vertical,
-                #[name = "headerbar"]
-                adw::HeaderBar {
-                    set_css_classes: &["flat"],
-                    #[wrap(Some)]
-                    #[name = "search_entry"]
-                    set_title_widget = &gtk::SearchEntry {
-                        set_hexpand: true,
-                        set_placeholder_text: Some("Search..."),
-                        connect_search_changed[sender] => move |entry| {
-                            sender.input(Msg::SearchChanged(entry.text().to_string()));
+            #[watch]
+            set_class_active: ("big-picture", model.grid),
+            #[watch]
+            set_class_active: ("touch", model.config.touch),
+            #[watch]
+            set_class_active: ("command-bar", model.is_bar()),
+            set_class_active: ("overview", model.is_overview()),
+            #[name = "toast_overlay"]
+            adw::ToastOverlay {
+                gtk::Box {
+                    #[watch]
+                    set_orientation: if model.is_bar() {
+                        gtk::Orientation::Horizontal
+                    } else {
+                        gtk::Orientation::Vertical
+                    },
+                    #[name = "headerbar"]
+                    adw::HeaderBar {
+                        // The results take the rest of the bar
+                        #[watch]
+                        set_hexpand: !model.is_bar(),
+                        set_css_classes: &["flat"],
+                        // There's no Escape key on a touchscreen
+                        pack_end = &gtk::Button {
+                            #[watch]
+                            set_visible: model.config.touch,
+                            set_icon_name: "window-close-symbolic",
+                            set_tooltip_text: Some("Close"),
+                            set_css_classes: &["circular"],
+                            set_focusable: false,
+                            connect_clicked => Msg::CloseWindow,
                         },
-                        connect_activate[sender] => move |_| {
-                            sender.input(Msg::SelectEntry);
+                        #[wrap(Some)]
+                        set_title_widget = &gtk::Box {
+                            set_spacing: 6,
+                            // The overview keeps the search centered at its own width
+                            set_hexpand: !model.is_overview(),
+                            #[name = "prompt_icon"]
+                            gtk::Image {
+                                set_visible: false,
+                            },
+                            #[name = "prompt_label"]
+                            gtk::Label {
+                                set_visible: false,
+                                add_css_class: "heading",
+                            },
+                            gtk::Label {
+                                #[watch]
+                                set_visible: model.shown_mode.is_some(),
+                                #[watch]
+                                set_label: model.shown_mode.map(|mode| mode.label()).unwrap_or_default(),
+                                add_css_class: "mode-chip",
+                            },
+                            gtk::Label {
+                                #[watch]
+                                set_visible: !model.pickers.is_empty(),
+                                #[watch]
+                                set_label: &model.breadcrumb(),
+                                set_ellipsize: gtk::pango::EllipsizeMode::Start,
+                                add_css_class: "dim-label",
+                            },
+                            #[name = "search_entry"]
+                            gtk::SearchEntry {
+                                set_hexpand: true,
+                                set_placeholder_text: Some("Search..."),
+                                connect_search_changed[sender] => move |entry| {
+                                    sender.input(Msg::SearchChanged(entry.text().to_string()));
+                                },
+                                connect_activate[sender] => move |_| {
+                                    sender.input(Msg::SelectEntry);
+                                },
+                            },
                         },
                     },
-                },
-                #[name = "scrolled_window"]
-                gtk::ScrolledWindow {
-                    set_vexpand: true,
-                    set_hexpand: true,
+                    gtk::Label {
+                        #[watch]
+                        set_visible: model.config.result_stats && !model.is_bar(),
+                        #[watch]
+                        set_label: &model.result_stats(),
+                        set_halign: gtk::Align::End,
+                        set_margin_end: 18,
+                        set_css_classes: &["caption", "dim-label", "numeric"],
+                    },
                     #[local_ref]
-                    entries_box -> gtk::Box {
+                    top_apps_box -> gtk::Box {
+                        set_orientation: gtk::Orientation::Horizontal,
+                        set_spacing: 6,
+                        set_margin_start: 12,
+                        set_margin_end: 12,
+                        set_halign: gtk::Align::Center,
+                    },
+                    #[name = "scrolled_window"]
+                    gtk::ScrolledWindow {
+                        set_vexpand: true,
+                        set_hexpand: true,
+                        set_kinetic_scrolling: true,
+                        // Overlay scrollbars only show up after scrolling, which a finger can't hover
+                        #[watch]
+                        set_overlay_scrolling: !model.config.touch,
+                        #[watch]
+                        set_vscrollbar_policy: if model.is_bar() {
+                            gtk::PolicyType::Never
+                        } else {
+                            gtk::PolicyType::Automatic
+                        },
+                        gtk::Box {
+                            set_orientation: gtk::Orientation::Vertical,
+                            #[local_ref]
+                            entries_box -> gtk::Box {
+                                #[watch]
+                                set_orientation: if model.is_bar() {
+                                    gtk::Orientation::Horizontal
+                                } else {
+                                    gtk::Orientation::Vertical
+                                },
+                                set_spacing: 6,
+                                set_margin_all: 12,
+                                #[watch]
+                                set_visible: !model.grid,
+                            },
+                            #[local_ref]
+                            game_tiles_box -> gtk::FlowBox {
+                                #[watch]
+                                set_visible: model.grid,
+                                set_selection_mode: gtk::SelectionMode::None,
+                                set_min_children_per_line: model.grid_columns(),
+                                set_max_children_per_line: model.grid_columns(),
+                                set_homogeneous: true,
+                                set_row_spacing: 12,
+                                set_column_spacing: 12,
+                                set_margin_all: 12,
+                                set_valign: gtk::Align::Start,
+                                // Keep keyboard focus in the search entry
+                                set_can_focus: false,
+                            },
+                        },
+                    },
+                    gtk::Box {
+                        #[watch]
+                        set_visible: model.preview_open && !model.is_bar(),
                         set_orientation: gtk::Orientation::Vertical,
+                        set_height_request: PREVIEW_HEIGHT,
+                        set_margin_start: 12,
+                        set_margin_end: 12,
+                        set_margin_bottom: 12,
+                        set_overflow: gtk::Overflow::Hidden,
+                        add_css_class: "card",
+                        #[name = "preview_picture"]
+                        gtk::Picture {
+                            set_vexpand: true,
+                            set_can_shrink: true,
+                            set_content_fit: gtk::ContentFit::Contain,
+                            set_visible: false,
+                        },
+                        #[name = "preview_text"]
+                        gtk::Label {
+                            set_vexpand: true,
+                            set_halign: gtk::Align::Start,
+                            set_valign: gtk::Align::Start,
+                            set_xalign: 0.0,
+                            set_margin_all: 12,
+                            add_css_class: "monospace",
+                        },
+                    },
+                    gtk::Box {
+                        #[watch]
+                        set_visible: model.results.len() > model.page_size(),
+                        set_halign: gtk::Align::Center,
                         set_spacing: 6,
-                        set_margin_all: 12,
-                    }
+                        set_margin_bottom: 6,
+                        // Pages of tiles are turned with the mouse too
+                        gtk::Button {
+                            set_visible: model.is_overview(),
+                            #[watch]
+                            set_sensitive: model.page > 0,
+                            set_icon_name: "go-previous-symbolic",
+                            set_tooltip_text: Some("Previous page"),
+                            set_css_classes: &["flat", "circular"],
+                            set_focusable: false,
+                            connect_clicked => Msg::PreviousPage,
+                        },
+                        gtk::Label {
+                            #[watch]
+                            set_label: &model.page_label(),
+                            add_css_class: "caption",
+                            add_css_class: "dim-label",
+                        },
+                        gtk::Button {
+                            set_visible: model.is_overview(),
+                            #[watch]
+                            set_sensitive: (model.page + 1) * model.page_size() < model.results.len(),
+                            set_icon_name: "go-next-symbolic",
+                            set_tooltip_text: Some("Next page"),
+                            set_css_classes: &["flat", "circular"],
+                            set_focusable: false,
+                            connect_clicked => Msg::NextPage,
+                        },
+                    },
+                    gtk::Revealer {
+                        #[watch]
+                        set_reveal_child: model.launching.is_some(),
+                        #[wrap(Some)]
+                        set_child = &gtk::Box {
+                            set_spacing: 6,
+                            set_margin_all: 12,
+                            set_halign: gtk::Align::Center,
+                            adw::Spinner {},
+                            gtk::Label {
+                                #[watch]
+                                set_label: &model
+                                    .launching
+                                    .as_ref()
+                                    .map(|name| format!("Launching {}…", name))
+                                    .unwrap_or_default(),
+                            },
+                        },
+                    },
                 }
             }
         }
     }
 
     fn init(
-        _: Self::Init,
+        options: Self::Init,
         root: Self::Root,
         sender: ComponentSender<Self>,
     ) -> ComponentParts<Self> {
         let entries = FactoryVecDeque::builder()
             .launch(gtk::Box::default())
-            .detach();
+            .forward(sender.input_sender(), |(index, action)| {
+                Msg::RowAction(index, action)
+            });
+
+        let top_apps = FactoryVecDeque::builder()
+            .launch(gtk::Box::default())
+            .forward(sender.input_sender(), Msg::LaunchTopApp);
+
+        let game_tiles = FactoryVecDeque::builder()
+            .launch(gtk::FlowBox::default())
+            .forward(sender.input_sender(), |output| match output {
+                TileOutput::Clicked(index) => Msg::LaunchTile(index),
+                TileOutput::Dropped(dropped, onto) => Msg::TileDropped(dropped, onto),
+            });
 
-        let app_entries = get_entries().unwrap_or_else(|e| {
-            eprintln!("Failed to load entries: {}", e);
-            vec![]
+        let config = Config::load().unwrap_or_else(|e| {
+            eprintln!("Failed to load config: {:#}", e);
+            Config::default()
         });
+        notify::set_enabled(config.notifications);
+
+        // The rest load in the background once the window shows, so a stuck
+        // compositor or directory can't hold up the start
+        let app_entries: Vec<Entry> = options
+            .dmenu_lines
+            .iter()
+            .flatten()
+            .map(|line| Entry::text(line))
+            .collect();
 
         let first_name = app_entries
             .first()
             .map(|e| e.name.clone())
             .unwrap_or_default();
 
-        let usage_tracker = UsageTracker::load().unwrap_or_else(|e| {
-            eprintln!("Failed to load usage tracker: {}", e);
+        // dmenu choices are arbitrary text, so app usage must not reorder them
+        let usage_tracker = if options.dmenu_lines.is_some() {
             UsageTracker::new()
-        });
+        } else {
+            UsageTracker::load().unwrap_or_else(|e| {
+                eprintln!("Failed to load usage tracker: {:#}", e);
+                UsageTracker::new()
+            })
+        };
+
+        // No config and no usage data means this is the first start,
+        // but scripted invocations shouldn't be interrupted by onboarding
+        let first_run = !options.is_standalone() && !Config::exists() && !UsageTracker::has_data();
 
         let mut model = App {
             selected_name: first_name,
             selected_index: 0,
             entries,
+            top_apps,
+            top_app_index: None,
+            grid: false,
+            game_tiles,
             all_entries: app_entries.clone(),
+            provider_entries: BTreeMap::new(),
+            rank_cache: RankCache::default(),
+            results: vec![],
+            page: 0,
             search_query: String::new(),
             scrolled_window: gtk::ScrolledWindow::new(),
             search_entry: gtk::SearchEntry::new(),
+            toast_overlay: adw::ToastOverlay::new(),
+            preview_open: false,
+            previewed: None,
+            preview_picture: gtk::Picture::new(),
+            preview_text: gtk::Label::new(None),
+            launching: None,
+            launch_generation: 0,
+            undo_toast: None,
+            idle_inhibit: None,
+            pickers: vec![],
+            expanded_categories: HashSet::new(),
+            preferred_copies: State::load().preferred_copies,
+            origin_badges: HashMap::new(),
+            hidden: HashSet::new(),
+            shown_mode: None,
+            remembered_mode: State::load().mode,
+            entries_generation: 0,
+            loading_sources: 0,
+            search_time: Duration::ZERO,
+            last_activation: None,
             window: root.clone(),
             usage_tracker,
+            config,
+            onboarding: None,
+            options,
         };
 
-        // Add all desktop entries to the factory
-        for entry in app_entries {
-            model.entries.guard().push_back(entry);
-        }
+        model.grid = model.shows_grid(effective_mode(&model.options, None, model.remembered_mode));
+
+        // Show the first page of all entries until something is typed
+        model.hidden = rules::now()
+            .map(|now| rules::hidden(&model.config.rules, now))
+            .unwrap_or_default();
+        model.results = app_entries
+            .into_iter()
+            .filter(|entry| !model.hidden.contains(&entry.id))
+            .map(|entry| (entry, None))
+            .collect();
+        model.show_page();
+
+        model.update_top_apps();
+        model
+            .top_apps
+            .widget()
+            .set_visible(!model.top_apps.is_empty() && !model.is_bar());
 
         let entries_box = model.entries.widget();
+        let top_apps_box = model.top_apps.widget();
+        let game_tiles_box = model.game_tiles.widget();
         let widgets = view_output!();
 
         // Update with the actual widgets from the view
         model.scrolled_window = widgets.scrolled_window.clone();
         model.search_entry = widgets.search_entry.clone();
+        model.toast_overlay = widgets.toast_overlay.clone();
+        model.preview_picture = widgets.preview_picture.clone();
+        model.preview_text = widgets.preview_text.clone();
+
+        // Show the prompt (and its icon) in front of the search entry
+        if let Some(prompt) = model
+            .options
+            .prompt
+            .as_ref()
+            .or(model.config.prompt.as_ref())
+        {
+            widgets.prompt_label.set_label(prompt);
+            widgets.prompt_label.set_visible(true);
+        }
+
+        if let Some(icon) = model
+            .options
+            .prompt_icon
+            .as_ref()
+            .or(model.config.prompt_icon.as_ref())
+        {
+            set_image_icon(&widgets.prompt_icon, icon);
+            widgets.prompt_icon.set_visible(true);
+        }
+
+        // Password prompts mask the input and only ever print what was typed
+        if model.options.password {
+            if let Some(text) = widgets.search_entry.delegate().and_downcast::<gtk::Text>() {
+                text.set_visibility(false);
+                text.set_input_purpose(gtk::InputPurpose::Password);
+            }
+            widgets.search_entry.set_placeholder_text(Some("Password"));
+            widgets.scrolled_window.set_visible(false);
+        }
 
-        // Add keyboard event controller to search entry for Escape key
+        let key_map = KeyMap::new(&model.config.keybinds);
+
+        // Add keyboard event controller to search entry for the cancel and entry action
+        // bindings. It runs in the capture phase since the text field would otherwise
+        // consume shortcuts like <Control>Delete itself.
         let search_key_controller = gtk::EventControllerKey::new();
+        search_key_controller.set_propagation_phase(gtk::PropagationPhase::Capture);
         let sender_clone = sender.clone();
-        search_key_controller.connect_key_pressed(move |_controller, key, _code, _modifier| {
-            match key {
-                gtk::gdk::Key::Escape => {
-                    sender_clone.input(Msg::CloseWindow);
+        let search_key_map = key_map.clone();
+        let search_entry = widgets.search_entry.clone();
+        let bar = model.is_bar();
+        search_key_controller.connect_key_pressed(move |_controller, key, _code, modifier| {
+            // Go through the field's own activation like Enter, so a search still
+            // waiting to run does before the selection is launched
+            if is_soft_keyboard_action(key) {
+                search_entry.emit_by_name::<()>("activate", &[]);
+                return gtk::glib::Propagation::Stop;
+            }
+
+            // The command bar's chips are side by side, so the arrows always move through them
+            if bar {
+                match key {
+                    gtk::gdk::Key::Left => {
+                        sender_clone.input(Msg::NavigateLeft);
+                        return gtk::glib::Propagation::Stop;
+                    }
+                    gtk::gdk::Key::Right => {
+                        sender_clone.input(Msg::NavigateRight);
+                        return gtk::glib::Propagation::Stop;
+                    }
+                    _ => {}
+                }
+            }
+
+            // With nothing typed the arrow keys move through the top apps instead
+            if search_entry.text().is_empty() {
+                match key {
+                    gtk::gdk::Key::BackSpace => {
+                        sender_clone.input(Msg::LeaveScope);
+                        return gtk::glib::Propagation::Stop;
+                    }
+                    gtk::gdk::Key::Left => {
+                        sender_clone.input(Msg::NavigateLeft);
+                        return gtk::glib::Propagation::Stop;
+                    }
+                    gtk::gdk::Key::Right => {
+                        sender_clone.input(Msg::NavigateRight);
+                        return gtk::glib::Propagation::Stop;
+                    }
+                    _ => {}
+                }
+            }
+
+            match search_key_map.action(key, modifier) {
+                Some(
+                    msg @ (Msg::CloseWindow
+                    | Msg::CloseSelectedWindow
+                    | Msg::KillSelectedProcess
+                    | Msg::UninstallSelected
+                    | Msg::NextPage
+                    | Msg::PreviousPage
+                    | Msg::RefreshCache
+                    | Msg::TypeSelected
+                    | Msg::PeekSelected
+                    | Msg::TogglePreview
+                    | Msg::OpenWithSelected
+                    | Msg::CycleWindowScope),
+                ) => {
+                    sender_clone.input(msg);
                     gtk::glib::Propagation::Stop
                 }
                 _ => gtk::glib::Propagation::Proceed,
@@ -224,14 +1274,27 @@ impl SimpleComponent for App {
         });
         widgets.search_entry.add_controller(search_key_controller);
 
+        // On-screen keyboards and input methods may commit their Enter, Go or Search
+        // action as a line break instead of a key press, which the one-line field would
+        // keep as text. Whatever came before it is typed, then the field is activated.
+        if let Some(text) = widgets.search_entry.delegate() {
+            let search_entry = widgets.search_entry.clone();
+            text.connect_insert_text(move |editable, inserted, position| {
+                if !inserted.ends_with(['\n', '\r']) {
+                    return;
+                }
+                editable.stop_signal_emission_by_name("insert-text");
+                let typed = inserted.trim_end_matches(['\n', '\r']);
+                if !typed.is_empty() {
+                    editable.insert_text(typed, position);
+                }
+                search_entry.emit_by_name::<()>("activate", &[]);
+            });
+        }
+
         // Focus search entry on startup
         widgets.search_entry.grab_focus();
 
-        // Select the first entry
-        if !model.entries.is_empty() {
-            model.entries.send(0, true);
-        }
-
         // Load CSS
         let css = CssProvider::new();
         css.load_from_string(include_str!("style.css"));
@@ -241,35 +1304,38 @@ impl SimpleComponent for App {
             gtk::STYLE_PROVIDER_PRIORITY_APPLICATION,
         );
 
-        // Setup layer shell
-        widgets.window.init_layer_shell();
-        widgets.window.set_layer(Layer::Overlay);
-        widgets.window.set_exclusive_zone(-1);
-        widgets
-            .window
-            .set_keyboard_mode(gtk4_layer_shell::KeyboardMode::Exclusive);
+        if gtk4_layer_shell::is_supported() {
+            // Setup layer shell
+            widgets.window.init_layer_shell();
+            widgets.window.set_layer(Layer::Overlay);
+            widgets.window.set_exclusive_zone(-1);
+            apply_keyboard_mode(&widgets.window, &model.config);
+            crash::set_window(&widgets.window);
+        } else {
+            // On X11 and compositors without layer shell this is a normal window.
+            // GTK 4 can neither keep it above others nor place it, window managers
+            // center it. Hiding once it loses focus makes it behave like the overlay.
+            widgets.window.set_title(Some("AdwLauncher"));
+            let sender_clone = sender.clone();
+            widgets.window.connect_is_active_notify(move |window| {
+                if !window.is_active() && window.is_visible() {
+                    sender_clone.input(Msg::CloseWindow);
+                }
+            });
+        }
+        apply_geometry(&widgets.window, &model.config, model.layout());
 
         // Add keyboard event controller
         let key_controller = gtk::EventControllerKey::new();
         let sender_clone = sender.clone();
-        key_controller.connect_key_pressed(move |_controller, key, _code, _modifier| match key {
-            gtk::gdk::Key::Up | gtk::gdk::Key::k => {
-                sender_clone.input(Msg::NavigateUp);
+        key_controller.connect_key_pressed(move |_controller, key, _code, modifier| match key_map
+            .action(key, modifier)
+        {
+            Some(msg) => {
+                sender_clone.input(msg);
                 gtk::glib::Propagation::Stop
             }
-            gtk::gdk::Key::Down | gtk::gdk::Key::j => {
-                sender_clone.input(Msg::NavigateDown);
-                gtk::glib::Propagation::Stop
-            }
-            gtk::gdk::Key::Return | gtk::gdk::Key::KP_Enter => {
-                sender_clone.input(Msg::SelectEntry);
-                gtk::glib::Propagation::Stop
-            }
-            gtk::gdk::Key::Escape => {
-                sender_clone.input(Msg::CloseWindow);
-                gtk::glib::Propagation::Stop
-            }
-            _ => gtk::glib::Propagation::Proceed,
+            None => gtk::glib::Propagation::Proceed,
         });
         widgets.window.add_controller(key_controller);
 
@@ -278,159 +1344,1445 @@ impl SimpleComponent for App {
         widgets.window.connect_show(move |_| {
             sender_clone.input(Msg::WindowShown);
         });
+        let sender_clone = sender.clone();
+        widgets.window.connect_hide(move |_| {
+            sender_clone.input(Msg::WindowHidden);
+        });
+
+        register_actions(&sender);
+
+        if model.config.hide_on_lock {
+            let lock_sender = sender.clone();
+            session::watch_lock(move || lock_sender.input(Msg::SessionLocked));
+        }
+
+        if model.config.gamepad {
+            let gamepad_sender = sender.input_sender().clone();
+            gamepad::listen(move |button| gamepad_sender.emit(Msg::Gamepad(button)));
+        }
+
+        // Ask for the basic settings before the first use
+        if first_run {
+            let onboarding = Onboarding::builder().launch(model.config.clone()).forward(
+                sender.input_sender(),
+                |output| match output {
+                    OnboardingOutput::Finished(config) => Msg::OnboardingFinished(Box::new(config)),
+                },
+            );
+            onboarding.widget().present(Some(&widgets.window));
+            model.onboarding = Some(onboarding);
+        }
 
         ComponentParts { model, widgets }
     }
 
-    fn update(&mut self, msg: Msg, sender: ComponentSender<Self>) {
+    fn update(&mut self, msg: Msg, sender: ComponentSender<Self>, _root: &Self::Root) {
         match msg {
             Msg::NavigateUp => {
-                let entries_len = self.entries.len();
-                if entries_len > 0 {
-                    // Deselect current
-                    self.entries.send(self.selected_index, false);
-
-                    // Move up (wrap around)
-                    if self.selected_index == 0 {
-                        self.selected_index = entries_len - 1;
-                    } else {
-                        self.selected_index -= 1;
-                    }
-
-                    // Select new
-                    self.entries.send(self.selected_index, true);
-
-                    // Update selected name
-                    if let Some(entry) = self.entries.get(self.selected_index) {
-                        self.selected_name = entry.entry.name.clone();
-                    }
-
-                    // Scroll to selected item
-                    self.scroll_to_index(self.selected_index);
-                }
+                self.move_selection(-self.row_step());
+                self.update_preview(&sender);
             }
             Msg::NavigateDown => {
-                let entries_len = self.entries.len();
-                if entries_len > 0 {
-                    // Deselect current
-                    self.entries.send(self.selected_index, false);
+                self.move_selection(self.row_step());
+                self.update_preview(&sender);
+            }
+            Msg::SelectEntry => {
+                // Like dmenu, hand back the typed text when nothing matches it
+                if self.entries.is_empty() && self.options.dmenu_lines.is_some() {
+                    println!("{}", self.search_query);
+                    sender.input(Msg::CloseWindow);
+                    return;
+                }
 
-                    // Move down (wrap around)
-                    self.selected_index = (self.selected_index + 1) % entries_len;
+                // A highlighted top app takes precedence over the list selection
+                let selected = match self.top_app_index {
+                    Some(index) => self.top_apps.get(index).map(|view| &view.entry),
+                    None => self
+                        .entries
+                        .get(self.selected_index)
+                        .map(|view| &view.entry),
+                };
 
-                    // Select new
-                    self.entries.send(self.selected_index, true);
+                if let Some(entry) = selected.cloned() {
+                    self.activate_entry(entry, &sender);
+                }
+            }
+            Msg::RowAction(index, action) => {
+                let Some(entry) = self.entries.get(index).map(|view| view.entry.clone()) else {
+                    return;
+                };
 
-                    // Update selected name
-                    if let Some(entry) = self.entries.get(self.selected_index) {
-                        self.selected_name = entry.entry.name.clone();
+                match action {
+                    RowAction::RunInTerminal => {
+                        let entry = Entry {
+                            open_type: OpenType::Terminal,
+                            ..entry
+                        };
+                        self.activate_entry(entry, &sender);
                     }
-
-                    // Scroll to selected item
-                    self.scroll_to_index(self.selected_index);
+                    RowAction::CloseWindow => {
+                        self.request_action(DestructiveAction::CloseWindow(entry), &sender)
+                    }
+                    RowAction::KillProcess => {
+                        self.request_action(DestructiveAction::KillProcess(entry), &sender)
+                    }
+                    RowAction::Uninstall => {
+                        self.request_action(DestructiveAction::Uninstall(entry), &sender)
+                    }
+                    RowAction::OpenWith => self.open_with_picker(entry, &sender),
                 }
             }
-            Msg::SelectEntry => {
-                if let Some(entry) = self.entries.get(self.selected_index) {
-                    if let Err(e) = launch_entry(&entry.entry) {
-                        eprintln!("Failed to launch entry: {}", e);
-                    } else {
-                        // Record usage for non-window entries
-                        if entry.entry.open_type != types::OpenType::Window {
-                            self.usage_tracker.record_launch(&entry.entry.name);
-                            if let Err(e) = self.usage_tracker.save() {
-                                eprintln!("Failed to save usage data: {}", e);
-                            }
-                        }
-                        // Close the window on successful launch
-                        sender.input(Msg::CloseWindow);
+            Msg::LaunchTile(index) => {
+                if let Some(view) = self.entries.get(index) {
+                    let entry = view.entry.clone();
+                    self.activate_entry(entry, &sender);
+                }
+            }
+            Msg::TileDropped(dropped, onto) => {
+                let (Some(dropped), Some(target)) =
+                    (self.entries.get(dropped), self.entries.get(onto))
+                else {
+                    return;
+                };
+                let (dropped, target) = (dropped.entry.clone(), target.entry.clone());
+
+                let mut config = self.config.clone();
+                let Some(folder) = folders::add(&mut config.folders, &dropped, &target) else {
+                    return;
+                };
+                match config.save() {
+                    Ok(()) => {
+                        self.config = config;
+                        self.show_toast(&format!("Moved {} to {}", dropped.name, folder));
+                        self.filter_entries();
                     }
+                    Err(e) => {
+                        eprintln!("Failed to save folders: {:#}", e);
+                        self.show_toast(&e.to_string());
+                    }
+                }
+            }
+            Msg::LaunchTopApp(index) => {
+                if let Some(view) = self.top_apps.get(index) {
+                    let entry = view.entry.clone();
+                    self.activate_entry(entry, &sender);
                 }
             }
+            Msg::NavigateLeft if self.grid || self.is_bar() => self.move_selection(-1),
+            Msg::NavigateRight if self.grid || self.is_bar() => self.move_selection(1),
+            Msg::NavigateLeft | Msg::NavigateRight => {
+                let len = self.top_apps.len();
+                if len > 0 && self.top_apps.widget().is_visible() {
+                    let index = match (self.top_app_index, msg) {
+                        (None, _) => 0,
+                        (Some(0), Msg::NavigateLeft) => len - 1,
+                        (Some(index), Msg::NavigateLeft) => index - 1,
+                        (Some(index), _) => (index + 1) % len,
+                    };
+                    self.select_top_app(Some(index));
+                }
+            }
+            Msg::UndoDismissed => {
+                // A toast left over from before the window was shown again must not hide it
+                if self.undo_toast.take().is_some() {
+                    sender.input(Msg::CloseWindow);
+                }
+            }
+            Msg::UndoLaunch(id) => {
+                sender.spawn_command(move |_| {
+                    if let Err(e) = app_discovery::close_window(&id) {
+                        eprintln!("Failed to close launched window: {:#}", e);
+                    }
+                });
+            }
             Msg::CloseWindow => {
-                self.window.set_visible(false);
+                // Backing out of a picker returns to the list it was opened from
+                if !self.pickers.is_empty() {
+                    self.leave_picker();
+                    return;
+                }
+
+                self.remember_geometry();
+                self.shown_mode = None;
+                if self.options.is_standalone() {
+                    // Nothing else will show this window again
+                    relm4::main_application().quit();
+                } else {
+                    self.window.set_visible(false);
+                }
             }
             Msg::SearchChanged(query) => {
-                self.search_query = query;
+                if let Some((mode, rest)) = self.scope_prefix(&query) {
+                    let rest = rest.to_string();
+                    self.shown_mode = Some(mode);
+                    self.reload_entries(&sender);
+                    // The entry reports the shortened text again later, which is harmless
+                    self.search_entry.set_text(&rest);
+                    self.search_entry.set_position(-1);
+                    self.search_query = rest;
+                } else {
+                    self.search_query = query;
+                }
                 self.filter_entries();
+                self.update_preview(&sender);
+            }
+            Msg::LeaveScope => {
+                if !self.pickers.is_empty() {
+                    self.leave_picker();
+                } else if self.shown_mode.is_some() && self.options.mode.is_none() {
+                    self.shown_mode = None;
+                    self.reload_entries(&sender);
+                }
             }
             Msg::WindowShown => {
-                // Reload all entries when window is shown
-                match get_entries() {
-                    Ok(entries) => self.all_entries = entries,
-                    Err(e) => eprintln!("Failed to reload entries: {}", e),
+                self.launching = None;
+                self.preview_open = false;
+                if !self.pickers.is_empty() {
+                    self.pickers.clear();
+                    self.search_entry.set_placeholder_text(Some("Search..."));
+                }
+                self.window.set_cursor_from_name(None);
+                if let Some(toast) = self.undo_toast.take() {
+                    toast.dismiss();
                 }
+                // Long deliberation on a media center shouldn't end at the lock screen
+                if self.config.inhibit_idle && self.idle_inhibit.is_none() {
+                    let cookie = relm4::main_application().inhibit(
+                        Some(&self.window),
+                        gtk::ApplicationInhibitFlags::IDLE,
+                        Some("Choosing what to launch"),
+                    );
+                    // 0 means the session doesn't support inhibiting
+                    self.idle_inhibit = (cookie != 0).then_some(cookie);
+                }
+                // A run with --mode may have changed it since, in a process of its own
+                self.remembered_mode = State::load().mode;
+                // Reload all entries when window is shown, the current ones stay until then
+                self.reload_entries(&sender);
                 self.search_query.clear();
                 self.search_entry.set_text("");
+                self.update_top_apps();
+                self.filter_entries();
+                self.search_entry.grab_focus();
+            }
+            Msg::WindowHidden => {
+                if let Some(cookie) = self.idle_inhibit.take() {
+                    relm4::main_application().uninhibit(cookie);
+                }
+            }
+            Msg::Gamepad(button) => {
+                if self.grid && self.window.is_visible() {
+                    sender.input(match button {
+                        gamepad::Button::Up => Msg::NavigateUp,
+                        gamepad::Button::Down => Msg::NavigateDown,
+                        gamepad::Button::Left => Msg::NavigateLeft,
+                        gamepad::Button::Right => Msg::NavigateRight,
+                        gamepad::Button::Activate => Msg::SelectEntry,
+                        gamepad::Button::Back => Msg::CloseWindow,
+                    });
+                }
+            }
+            Msg::SessionLocked => {
+                self.purge_results();
+                // Hidden right away, not back in the list a picker was opened from
+                self.pickers.clear();
+                if self.window.is_visible() {
+                    sender.input(Msg::CloseWindow);
+                }
+            }
+            Msg::OnboardingFinished(config) => {
+                if let Some(onboarding) = self.onboarding.take() {
+                    onboarding.widget().force_close();
+                }
+                apply_geometry(
+                    &self.window,
+                    &config,
+                    self.options.layout.unwrap_or(config.layout),
+                );
+                if gtk4_layer_shell::is_supported() {
+                    apply_keyboard_mode(&self.window, &config);
+                }
+                notify::set_enabled(config.notifications);
+                self.config = *config;
+                self.search_entry.grab_focus();
+            }
+            Msg::CloseSelectedWindow => {
+                if let Some(entry) = self.selected_entry(&[OpenType::Window]) {
+                    self.request_action(DestructiveAction::CloseWindow(entry), &sender);
+                }
+            }
+            Msg::KillSelectedProcess => {
+                if let Some(entry) = self.selected_entry(&[OpenType::Window]) {
+                    self.request_action(DestructiveAction::KillProcess(entry), &sender);
+                }
+            }
+            Msg::UninstallSelected => {
+                if let Some(entry) = self.selected_entry(&[OpenType::Graphical, OpenType::Terminal])
+                {
+                    self.request_action(DestructiveAction::Uninstall(entry), &sender);
+                }
+            }
+            Msg::Show(mode) => {
+                self.shown_mode = mode;
+                if self.window.is_visible() {
+                    // Already up, so only the entries need reloading
+                    sender.input(Msg::WindowShown);
+                } else {
+                    // The focused output may have changed since the last time
+                    if self.config.size != SizePreset::Custom {
+                        apply_geometry(&self.window, &self.config, self.layout());
+                    }
+                    self.window.present();
+                }
+            }
+            Msg::TypeSelected => {
+                // Only text results are typed. On anything else the binding is the
+                // plain activation it would be without it, so the default Shift+Return
+                // still launches a just launched app again.
+                let Some(entry) = self.selected_entry(&[OpenType::Text]) else {
+                    sender.input(Msg::SelectEntry);
+                    return;
+                };
+                // wtype outlives the launcher and types once the window is gone
+                match app_discovery::type_text(&entry.exec) {
+                    Ok(()) => sender.input(Msg::CloseWindow),
+                    Err(e) => {
+                        eprintln!("Failed to type text: {:#}", e);
+                        self.show_toast(&e.to_string());
+                    }
+                }
+            }
+            Msg::PeekSelected => {
+                // Picking only ever does what the picker is for
+                if !self.pickers.is_empty() {
+                    return;
+                }
+                let Some(entry) = self.selected_entry(&[
+                    OpenType::Graphical,
+                    OpenType::Terminal,
+                    OpenType::Window,
+                ]) else {
+                    return;
+                };
+
+                let config = self.config.clone();
+                sender.spawn_oneshot_command(move || {
+                    let result = launch_entry(&entry, &config);
+                    CmdOut::Peeked(entry, result)
+                });
+            }
+            Msg::TogglePreview => {
+                // Only file results open it
+                if !self.preview_open && self.selected_file().is_none() {
+                    return;
+                }
+                self.preview_open = !self.preview_open;
+                self.previewed = None;
+                self.update_preview(&sender);
+            }
+            Msg::OpenWithSelected => {
+                // Only text results naming a file have something to open
+                if let Some(entry) = self.selected_entry(&[OpenType::Text]) {
+                    self.open_with_picker(entry, &sender);
+                }
+            }
+            Msg::CycleWindowScope => {
+                // Until the launcher restarts, the config sets where it starts from
+                self.config.window_scope = self.config.window_scope.next();
+                self.show_toast(self.config.window_scope.label());
+                self.reload_entries(&sender);
+            }
+            Msg::RefreshCache => {
+                self.show_toast("Rescanning applications…");
+                index::clear();
+
+                let config = self.config.clone();
+                sender.spawn_oneshot_command(move || {
+                    CmdOut::CacheRefreshed(
+                        app_discovery::refresh_desktop_entries(&config)
+                            .map(|entries| entries.len()),
+                    )
+                });
+            }
+            Msg::NextPage => {
+                if (self.page + 1) * self.page_size() < self.results.len() {
+                    self.page += 1;
+                    self.show_page();
+                    self.update_preview(&sender);
+                }
+            }
+            Msg::PreviousPage => {
+                if self.page > 0 {
+                    self.page -= 1;
+                    self.show_page();
+                    self.update_preview(&sender);
+                }
+            }
+            Msg::PerformAction(action) => {
+                let config = self.config.clone();
+                sender.spawn_oneshot_command(move || {
+                    let result = action.perform(&config);
+                    CmdOut::ActionPerformed(*action, result)
+                });
+            }
+        }
+    }
+
+    fn update_cmd(&mut self, output: CmdOut, sender: ComponentSender<Self>, _root: &Self::Root) {
+        match output {
+            CmdOut::EntriesLoaded(generation, result) => {
+                // A newer reload started meanwhile, e.g. by switching modes again
+                if generation != self.entries_generation {
+                    return;
+                }
+                self.loading_sources = 0;
+
+                match result {
+                    Ok(entries) => {
+                        self.all_entries = entries;
+                        self.rank_cache.clear();
+                    }
+                    Err(e) => {
+                        eprintln!("Failed to reload entries: {:#}", e);
+                        if e.is_compositor_timeout() {
+                            self.show_toast(
+                                "The compositor isn't responding, open windows are left out",
+                            );
+                        }
+                        return;
+                    }
+                }
+                self.update_top_apps();
+                self.filter_entries();
+            }
+            CmdOut::ProviderLoaded(generation, provider, result) => {
+                if generation != self.entries_generation {
+                    return;
+                }
+                self.loading_sources = self.loading_sources.saturating_sub(1);
+
+                match result {
+                    Ok(entries) => {
+                        self.provider_entries.insert(provider, entries);
+                    }
+                    Err(e) => {
+                        eprintln!("Failed to load {:?} entries: {:#}", provider, e);
+                        // The other sources still show, but say why windows are missing
+                        if e.is_compositor_timeout() {
+                            self.show_toast(
+                                "The compositor isn't responding, open windows are left out",
+                            );
+                        }
+                        return;
+                    }
+                }
+                // Providers that haven't answered yet keep their previous entries
+                self.all_entries = self.provider_entries.values().flatten().cloned().collect();
+                self.rank_cache.clear();
+                self.update_top_apps();
+
+                // A late answer mustn't move the selection away from what's about to be activated
+                let selected = self
+                    .entries
+                    .get(self.selected_index)
+                    .map(|view| view.entry.clone());
                 self.filter_entries();
+                let index = selected.and_then(|selected| {
+                    self.entries.iter().position(|view| {
+                        view.entry.open_type == selected.open_type
+                            && view.entry.id == selected.id
+                            && view.entry.exec == selected.exec
+                    })
+                });
+                if let Some(index) = index
+                    && index > 0
+                {
+                    self.move_selection(index as isize);
+                }
+            }
+            CmdOut::Launched(generation, entry, Err(e)) => {
+                eprintln!("Failed to launch entry: {:#}", e);
+                // A later launch keeps its own feedback
+                if generation == self.launch_generation {
+                    self.launching = None;
+                    self.window.set_cursor_from_name(None);
+                }
+                self.show_toast(&format!("Failed to launch {}: {}", entry.name, e));
+            }
+            CmdOut::Launched(generation, entry, Ok(())) => {
+                if !matches!(entry.open_type, OpenType::Graphical | OpenType::Terminal) {
+                    if generation != self.launch_generation {
+                        return;
+                    }
+                    // Close the window on successful launch
+                    self.launching = None;
+                    sender.input(Msg::CloseWindow);
+                    return;
+                }
+
+                // Record usage for application entries
+                self.record_usage(&entry);
+            }
+            CmdOut::Peeked(entry, Err(e)) => {
+                eprintln!("Failed to launch entry: {:#}", e);
+                self.show_toast(&format!("Failed to launch {}: {}", entry.name, e));
+            }
+            CmdOut::Peeked(entry, Ok(())) => {
+                if entry.open_type != OpenType::Window {
+                    self.record_usage(&entry);
+                }
+                self.show_toast(&format!("Opened {}", entry.name));
+                // Focusing a window can take the keyboard when the grab isn't exclusive
+                self.window.present();
                 self.search_entry.grab_focus();
             }
+            CmdOut::PickerLoaded(generation, picker) => {
+                // The window was hidden or reloaded meanwhile
+                if generation != self.entries_generation {
+                    return;
+                }
+                if let Some(picker) = picker {
+                    self.push_picker(*picker);
+                }
+            }
+            CmdOut::PreviewLoaded(path, result) => {
+                if !self.preview_open || self.previewed.as_ref() != Some(&path) {
+                    return;
+                }
+                match result {
+                    Ok(Preview::Image(image)) => {
+                        self.preview_picture.set_filename(Some(&image));
+                        self.preview_picture.set_visible(true);
+                        self.preview_text.set_visible(false);
+                    }
+                    Ok(Preview::Text(text)) => self.preview_text.set_label(&text),
+                    Ok(Preview::Unsupported) => {
+                        self.preview_text.set_label("No preview for this file")
+                    }
+                    Err(e) => {
+                        eprintln!("Failed to preview file: {:#}", e);
+                        self.preview_text.set_label(&e.to_string());
+                    }
+                }
+            }
+            CmdOut::LaunchFinished(generation, window) => {
+                // Both the window watcher and the timeout report in, only act once,
+                // and never for a launch that has since been replaced by another
+                if generation != self.launch_generation {
+                    return;
+                }
+                let Some(name) = self.launching.take() else {
+                    return;
+                };
+                self.window.set_cursor_from_name(None);
+
+                match window {
+                    // Stay up for a moment so a wrong launch can be taken back
+                    Some(id) if self.config.undo_launch => {
+                        let toast = adw::Toast::builder()
+                            .title(format!("Opened {}", name))
+                            .button_label("Undo")
+                            .timeout(UNDO_TIMEOUT_SECS)
+                            .build();
+                        let undo_sender = sender.clone();
+                        toast.connect_button_clicked(move |_| {
+                            undo_sender.input(Msg::UndoLaunch(id.clone()));
+                        });
+                        // Also emitted after Undo, so this is the single place that hides
+                        let dismiss_sender = sender.clone();
+                        toast.connect_dismissed(move |_| {
+                            dismiss_sender.input(Msg::UndoDismissed);
+                        });
+                        self.toast_overlay.add_toast(toast.clone());
+                        self.undo_toast = Some(toast);
+                    }
+                    _ => sender.input(Msg::CloseWindow),
+                }
+            }
+            CmdOut::ActionPerformed(_, Err(e)) => {
+                eprintln!("Failed to perform action: {:#}", e);
+                self.show_toast(&e.to_string());
+            }
+            CmdOut::ActionPerformed(DestructiveAction::Power(_), Ok(())) => {
+                sender.input(Msg::CloseWindow)
+            }
+            CmdOut::ActionPerformed(
+                DestructiveAction::CloseWindow(entry)
+                | DestructiveAction::KillProcess(entry)
+                | DestructiveAction::Uninstall(entry),
+                Ok(()),
+            ) => {
+                // The compositor and flatpak finish asynchronously, so drop
+                // the entry right away instead of reloading
+                self.all_entries.retain(|e| {
+                    e.open_type != entry.open_type || e.id != entry.id || e.exec != entry.exec
+                });
+                self.rank_cache.clear();
+                self.filter_entries();
+            }
+            CmdOut::CacheRefreshed(result) => {
+                match result {
+                    Ok(count) => self.show_toast(&format!("Found {} applications", count)),
+                    Err(e) => {
+                        eprintln!("Failed to refresh cache: {:#}", e);
+                        self.show_toast(&format!("Failed to rescan applications: {}", e));
+                    }
+                }
+                self.reload_entries(&sender);
+            }
         }
     }
 }
 
 impl App {
-    fn filter_entries(&mut self) {
-        // Deselect current entry before clearing
-        if !self.entries.is_empty() && self.selected_index < self.entries.len() {
-            self.entries.send(self.selected_index, false);
+    /// Launch an entry (or ask first for power actions) and close on success
+    fn activate_entry(&mut self, entry: Entry, sender: &ComponentSender<Self>) {
+        // Impatient repeated activations would start the app several times
+        if self.launching.is_some() {
+            return;
         }
 
-        // Clear existing entries
+        // Sections open and close as quickly as they are clicked
+        if entry.open_type == OpenType::Category {
+            if !self.expanded_categories.remove(&entry.exec) {
+                self.expanded_categories.insert(entry.exec.clone());
+            }
+            self.filter_entries();
+            let index = self
+                .entries
+                .iter()
+                .position(|view| view.entry.id == entry.id);
+            if let Some(index) = index {
+                self.move_selection(index as isize);
+            }
+            return;
+        }
+        if let Some((_, at)) = &self.last_activation
+            && at.elapsed() < ACTIVATE_DEBOUNCE
+        {
+            return;
+        }
+
+        // Picking a default app doesn't launch it
+        if self.pickers.is_empty() && self.is_recent_relaunch(&entry) {
+            self.show_toast(&format!(
+                "{} was just launched, hold Shift to launch it again",
+                entry.name
+            ));
+            return;
+        }
+        self.last_activation = Some((entry.id.clone(), Instant::now()));
+
+        // Help lines for modes type their prefix, which switches to the mode
+        if entry.open_type == OpenType::Help {
+            if !entry.exec.is_empty() {
+                self.search_entry.set_text(&entry.exec);
+                self.search_entry.set_position(-1);
+            }
+            return;
+        }
+
+        // Copying stays in the list, so several values can be looked up in one go
+        if entry.open_type == OpenType::Variable {
+            WidgetExt::display(&self.window)
+                .clipboard()
+                .set_text(&entry.exec);
+            self.show_toast(&format!(
+                "Copied the value of {}",
+                environment::variable_name(&entry)
+            ));
+            return;
+        }
+
+        if entry.open_type == OpenType::Folder {
+            self.push_picker(Picker {
+                title: entry.name.clone(),
+                prompt: format!("Search in {}", entry.name),
+                kind: PickerKind::Folder,
+                entries: folders::members(&entry, &self.config.folders, &self.all_entries),
+            });
+            return;
+        }
+
+        if entry.open_type == OpenType::Power {
+            self.request_action(DestructiveAction::Power(entry), sender);
+            return;
+        }
+
+        if entry.open_type == OpenType::MimeType {
+            let config = self.config.clone();
+            let generation = self.entries_generation;
+            sender.spawn_oneshot_command(move || {
+                let picker = Picker {
+                    title: entry.exec.clone(),
+                    prompt: format!("Default app for {}", entry.exec),
+                    kind: PickerKind::DefaultApp(entry.exec.clone()),
+                    entries: mime::handlers(&installed_apps(&config), &entry.exec),
+                };
+                CmdOut::PickerLoaded(generation, Some(Box::new(picker)))
+            });
+            return;
+        }
+
+        // Toggling stays in the list, so several entries can be changed in one go
+        if entry.open_type == OpenType::Autostart {
+            match autostart::toggle(&entry.id) {
+                Ok(enabled) => {
+                    let state = if enabled { "enabled" } else { "disabled" };
+                    self.show_toast(&format!("Autostart {}", state));
+                }
+                Err(e) => {
+                    eprintln!("Failed to toggle autostart entry: {:#}", e);
+                    self.show_toast(&e.to_string());
+                }
+            }
+            self.reload_entries(sender);
+            return;
+        }
+
+        let entry = match self.pickers.last().map(|picker| &picker.kind) {
+            Some(PickerKind::DefaultApp(mime)) => {
+                let mime = mime.clone();
+                match mime::set_default(&entry.id, &mime) {
+                    Ok(()) => {
+                        self.show_toast(&format!("{} now opens {}", entry.name, mime));
+                        // The MIME type list names the current defaults
+                        self.reload_entries(sender);
+                    }
+                    Err(e) => {
+                        eprintln!("Failed to set default app: {:#}", e);
+                        self.show_toast(&e.to_string());
+                    }
+                }
+                self.leave_picker();
+                return;
+            }
+            Some(PickerKind::OpenWith(path)) => mime::open_with(&entry, path),
+            Some(PickerKind::Launch | PickerKind::Folder) | None => entry,
+        };
+
+        // Browsers with several profiles ask which one first
+        if matches!(
+            self.pickers.last().map(|picker| &picker.kind),
+            None | Some(PickerKind::Folder)
+        ) && entry.open_type == OpenType::Graphical
+        {
+            let profiles = browsers::profile_entries(&entry, self.config.network);
+            if profiles.len() > 1 {
+                self.push_picker(Picker {
+                    title: entry.name.clone(),
+                    prompt: format!("{} profile", entry.name),
+                    kind: PickerKind::Launch,
+                    entries: profiles,
+                });
+                return;
+            }
+        }
+
+        // Apps with StartupNotify promise a window, and so do terminals
+        let is_app = matches!(entry.open_type, OpenType::Graphical | OpenType::Terminal);
+        let wait_for_window =
+            is_app && (entry.startup_notify || entry.open_type == OpenType::Terminal);
+        let window_rules = is_app
+            .then(|| app_discovery::window_rules(&entry, &self.config).cloned())
+            .flatten();
+
+        // Keep the window up with a busy cursor until the app maps its window,
+        // so slow apps still show that something is happening. Apps without
+        // startup notification only get the short feedback. The timeout covers
+        // apps that never open a window and a stuck event stream.
+        self.launching = Some(entry.name.clone());
+        self.launch_generation += 1;
+        let generation = self.launch_generation;
+        if wait_for_window {
+            self.window.set_cursor_from_name(Some("progress"));
+        }
+
+        // Talking to the compositor happens off the main loop, a busy one mustn't freeze the window
+        let config = self.config.clone();
+        sender.spawn_command(move |out| {
+            // The watcher has to see the window list before the launch
+            let watcher = (wait_for_window || window_rules.is_some())
+                .then(|| {
+                    WindowWatcher::start().unwrap_or_else(|e| {
+                        eprintln!("Failed to watch for new windows: {:#}", e);
+                        None
+                    })
+                })
+                .flatten();
+
+            let started = Instant::now();
+            let result = launch_entry(&entry, &config);
+            let launched = result.is_ok();
+            out.emit(CmdOut::Launched(generation, entry.clone(), result));
+            if !launched || !is_app {
+                return;
+            }
+
+            // Undo and the window rules must only ever reach the launched app's window
+            let window = watcher.and_then(|watcher| {
+                watcher
+                    .wait(LAUNCH_TIMEOUT, |window| {
+                        app_discovery::opened_by(&entry, window, &config)
+                    })
+                    .unwrap_or_else(|e| {
+                        eprintln!("Failed to watch for new windows: {:#}", e);
+                        None
+                    })
+            });
+            if let (Some(id), Some(rules)) = (&window, &window_rules)
+                && let Err(e) = app_discovery::apply_window_rules(id, rules)
+            {
+                eprintln!("Failed to apply window rules: {:#}", e);
+            }
+            std::thread::sleep(LAUNCH_MIN_FEEDBACK.saturating_sub(started.elapsed()));
+            out.emit(CmdOut::LaunchFinished(generation, window));
+        });
+
+        if is_app {
+            let out = sender.command_sender().clone();
+            gtk::glib::timeout_add_local_once(LAUNCH_TIMEOUT, move || {
+                out.emit(CmdOut::LaunchFinished(generation, None));
+            });
+        }
+    }
+
+    /// Whether the app was launched within the configured guard time,
+    /// unless Shift is held to launch it anyway
+    fn is_recent_relaunch(&self, entry: &Entry) -> bool {
+        let guard = Duration::from_secs(self.config.relaunch_guard_secs);
+        let recent = matches!(entry.open_type, OpenType::Graphical | OpenType::Terminal)
+            && self
+                .last_activation
+                .as_ref()
+                .is_some_and(|(id, at)| *id == entry.id && at.elapsed() < guard);
+
+        if !recent {
+            return false;
+        }
+
+        let shift_held = WidgetExt::display(&self.window)
+            .default_seat()
+            .and_then(|seat| seat.keyboard())
+            .is_some_and(|keyboard| {
+                keyboard
+                    .modifier_state()
+                    .contains(gtk::gdk::ModifierType::SHIFT_MASK)
+            });
+
+        !shift_held
+    }
+
+    /// The mode a query's prefix switches to and the rest of the query,
+    /// unless the search is already scoped
+    fn scope_prefix<'a>(&self, query: &'a str) -> Option<(Mode, &'a str)> {
+        // Kiosk mode lists the allowed apps only, "run " would reach shell history
+        if !self.accepts_prefixes() || self.config.kiosk.enabled {
+            return None;
+        }
+
+        Mode::value_variants().iter().find_map(|mode| {
+            let prefix = mode.prefix()?;
+            let typed = query.get(..prefix.len() + 1)?;
+            (typed.eq_ignore_ascii_case(&format!("{} ", prefix)))
+                .then(|| (*mode, &query[prefix.len() + 1..]))
+        })
+    }
+
+    /// Whether typed prefixes can still switch the mode
+    fn accepts_prefixes(&self) -> bool {
+        self.shown_mode.is_none()
+            && self.options.mode.is_none()
+            && self.options.dmenu_lines.is_none()
+            && self.pickers.is_empty()
+    }
+
+    /// Load the entries in the background, the list is updated once they arrive
+    fn reload_entries(&mut self, sender: &ComponentSender<Self>) {
+        self.entries_generation += 1;
+        let generation = self.entries_generation;
+        let options = self.options.clone();
+        let config = self.config.clone();
+        let mode = effective_mode(&options, self.shown_mode, self.remembered_mode);
+        self.grid = self.shows_grid(mode);
+
+        // Without a mode each provider's entries are shown as soon as they arrive
+        if options.dmenu_lines.is_none() && matches!(mode, None | Some(Mode::All)) {
+            // The entries shown so far stand in for the providers until they answer
+            if self.provider_entries.is_empty() {
+                for entry in &self.all_entries {
+                    self.provider_entries
+                        .entry(Provider::of(entry))
+                        .or_default()
+                        .push(entry.clone());
+                }
+            }
+            self.loading_sources = Provider::ALL.len();
+            sender.spawn_command(move |out| {
+                query_providers(&config, |provider, result| {
+                    out.emit(CmdOut::ProviderLoaded(generation, provider, result));
+                });
+            });
+            return;
+        }
+        // Entries of another mode replaced them, so they are no longer current
+        self.provider_entries.clear();
+        self.loading_sources = 1;
+
+        sender.spawn_oneshot_command(move || {
+            let result = load_entries(&options, mode, &config);
+            CmdOut::EntriesLoaded(generation, result)
+        });
+    }
+
+    fn push_picker(&mut self, picker: Picker) {
+        self.search_entry.set_placeholder_text(Some(&picker.prompt));
+        self.pickers.push(picker);
+        self.rank_cache.clear();
+        self.search_entry.set_text("");
+        self.filter_entries();
+    }
+
+    /// Forget the query and every result, e.g. history or variable values, so none
+    /// show behind the lock screen or stay in memory. They load again when shown.
+    fn purge_results(&mut self) {
+        // Answers still on their way are dropped
+        self.entries_generation += 1;
+        self.loading_sources = 0;
+        self.all_entries.clear();
+        self.provider_entries.clear();
+        self.rank_cache.clear();
+        self.results.clear();
         self.entries.guard().clear();
+        self.game_tiles.guard().clear();
+        self.search_query.clear();
+        self.search_entry.set_text("");
+        self.preview_open = false;
+        self.previewed = None;
+        self.preview_text.set_label("");
+        self.preview_picture
+            .set_paintable(None::<&gtk::gdk::Paintable>);
+    }
 
-        if self.search_query.is_empty() {
-            // When no search query, sort by recent usage
-            let mut sorted_entries: Vec<(f64, Entry)> = self
-                .all_entries
-                .iter()
-                .map(|entry| {
-                    let boost = self.usage_tracker.calculate_boost(&entry.name);
-                    (boost, entry.clone())
+    /// Go back one level, to the previous picker or the search
+    fn leave_picker(&mut self) {
+        self.pickers.pop();
+        let prompt = self
+            .pickers
+            .last()
+            .map_or("Search...", |picker| picker.prompt.as_str());
+        self.search_entry.set_placeholder_text(Some(prompt));
+        self.rank_cache.clear();
+        self.search_entry.set_text("");
+        self.filter_entries();
+    }
+
+    /// The apps that can open a file result, to open it with one of them
+    fn open_with_picker(&self, entry: Entry, sender: &ComponentSender<Self>) {
+        let config = self.config.clone();
+        let generation = self.entries_generation;
+        sender.spawn_oneshot_command(move || {
+            let picker = app_discovery::entry_file(&entry).map(|path| {
+                let handlers = mime::file_type(&path)
+                    .map(|mime| mime::handlers(&installed_apps(&config), &mime))
+                    .unwrap_or_default();
+                let name = path
+                    .file_name()
+                    .map(|name| name.to_string_lossy().to_string())
+                    .unwrap_or_else(|| entry.name.clone());
+
+                Box::new(Picker {
+                    title: format!("Open {}", name),
+                    prompt: format!("Open {} with", name),
+                    kind: PickerKind::OpenWith(path),
+                    entries: handlers,
                 })
-                .collect();
+            });
+            CmdOut::PickerLoaded(generation, picker)
+        });
+    }
+
+    /// Where the shown picker was opened from, e.g. "Default Apps › text/plain"
+    fn breadcrumb(&self) -> String {
+        let root = effective_mode(&self.options, self.shown_mode, self.remembered_mode)
+            .map_or("Search", |mode| mode.label());
+        std::iter::once(root)
+            .chain(self.pickers.iter().map(|picker| picker.title.as_str()))
+            .collect::<Vec<&str>>()
+            .join(" › ")
+    }
+
+    /// Save the window size for the next start, if it can be resized at all
+    fn remember_geometry(&self) {
+        if gtk4_layer_shell::is_supported() {
+            return;
+        }
 
-            // Sort by usage boost (highest first)
-            sorted_entries
-                .sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or(std::cmp::Ordering::Equal));
+        let (width, height) = (self.window.width(), self.window.height());
+        if width > 0 && height > 0 {
+            State::update(|state| {
+                state.width = Some(width);
+                state.height = Some(height);
+            });
+        }
+    }
 
-            for (_boost, entry) in sorted_entries {
-                self.entries.guard().push_back(entry);
+    fn show_toast(&self, message: &str) {
+        self.toast_overlay.add_toast(adw::Toast::new(message));
+    }
+
+    /// Rebuild the strip of most used apps from the usage data
+    fn update_top_apps(&mut self) {
+        self.select_top_app(None);
+
+        // The strip uses bigger icons than the list but follows the same toggle
+        let icon_size = self.config.show_icons.then_some(TOP_APP_ICON_SIZE);
+        let mut top_apps = self.top_apps.guard();
+        top_apps.clear();
+        for name in self.usage_tracker.most_used(self.config.top_apps) {
+            if let Some(entry) = self.all_entries.iter().find(|entry| {
+                entry.name == name
+                    && matches!(entry.open_type, OpenType::Graphical | OpenType::Terminal)
+                    && !self.hidden.contains(&entry.id)
+            }) {
+                top_apps.push_back((entry.clone(), icon_size));
             }
+        }
+    }
+
+    fn select_top_app(&mut self, index: Option<usize>) {
+        if let Some(previous) = self.top_app_index {
+            self.top_apps.send(previous, false);
+        }
+        if let Some(index) = index {
+            self.top_apps.send(index, true);
+        }
+        self.top_app_index = index;
+    }
+
+    /// The latest launches, of apps, files and commands alike, for the empty query
+    fn recent_entries(&self) -> Vec<Entry> {
+        if !self.search_query.trim().is_empty()
+            || self.options.dmenu_lines.is_some()
+            || !self.pickers.is_empty()
+        {
+            return vec![];
+        }
+        let everything = matches!(
+            effective_mode(&self.options, self.shown_mode, self.remembered_mode),
+            None | Some(Mode::All)
+        );
+
+        self.usage_tracker
+            .recent()
+            .iter()
+            .filter_map(|launch| {
+                let listed = self.all_entries.iter().find(|entry| {
+                    entry.id == launch.id
+                        && entry.exec == launch.exec
+                        && entry.open_type == launch.open_type
+                });
+                match listed {
+                    Some(entry) => Some(entry.clone()),
+                    // Files opened with an app and commands aren't listed anywhere,
+                    // apps that aren't listed were removed or belong to another mode
+                    None if everything && !launch.exec.is_empty() => {
+                        let is_app = self.all_entries.iter().any(|entry| entry.id == launch.id);
+                        (is_app || !launch.id.ends_with(".desktop")).then(|| launch.entry())
+                    }
+                    None => None,
+                }
+            })
+            .filter(|entry| !self.hidden.contains(&entry.id))
+            .take(self.config.recent_apps)
+            .collect()
+    }
+
+    /// Apps whose windows were closed while the service runs, for the empty query
+    fn closed_entries(&self) -> Vec<Entry> {
+        if !self.search_query.trim().is_empty()
+            || self.options.dmenu_lines.is_some()
+            || !self.pickers.is_empty()
+            || self.grid
+        {
+            return vec![];
+        }
+
+        closed::entries(
+            &self.visible(&self.all_entries),
+            self.config.recently_closed,
+        )
+    }
+
+    /// The entries no time rule hides right now
+    fn visible<'a>(&self, entries: &'a [Entry]) -> Cow<'a, [Entry]> {
+        if self.hidden.is_empty() {
+            return Cow::Borrowed(entries);
+        }
+        entries
+            .iter()
+            .filter(|entry| !self.hidden.contains(&entry.id))
+            .cloned()
+            .collect()
+    }
+
+    /// Count a launch, unless tracking is off for everything or for the shown mode
+    fn record_usage(&mut self, entry: &Entry) {
+        // dmenu runs start from empty usage data, saving it would replace the real one
+        if self.options.dmenu_lines.is_some() {
+            return;
+        }
+        let untracked = effective_mode(&self.options, self.shown_mode, self.remembered_mode)
+            .is_some_and(|mode| self.config.untracked_modes.contains(&mode));
+        if !self.config.usage_tracking || untracked {
+            return;
+        }
+
+        self.usage_tracker.record_launch(&entry.name);
+        self.usage_tracker.record_recent(entry);
+        if let Err(e) = self.usage_tracker.save() {
+            eprintln!("Failed to save usage data: {:#}", e);
+        }
+        if duplicates::record_launch(entry, &self.all_entries) {
+            self.preferred_copies
+                .insert(entry.name.clone(), entry.id.clone());
+        }
+    }
+
+    /// The layout from the command line, or else the config
+    fn layout(&self) -> Layout {
+        self.options.layout.unwrap_or(self.config.layout)
+    }
+
+    fn is_bar(&self) -> bool {
+        self.layout() == Layout::Bar
+    }
+
+    fn is_overview(&self) -> bool {
+        self.layout() == Layout::Overview
+    }
+
+    /// Whether results are tiles, for games and in the overview
+    fn shows_grid(&self, mode: Option<Mode>) -> bool {
+        self.is_overview() || (mode == Some(Mode::Games) && !self.is_bar())
+    }
+
+    fn grid_columns(&self) -> u32 {
+        if self.is_overview() {
+            OVERVIEW_COLUMNS
         } else {
-            // Use fuzzy matching to filter entries
-            let matcher = SkimMatcherV2::default();
-            let mut scored_entries: Vec<(f64, Entry)> = self
-                .all_entries
-                .iter()
-                .filter_map(|entry| {
-                    matcher
-                        .fuzzy_match(&entry.name, &self.search_query)
-                        .map(|fuzzy_score| {
-                            // Calculate combined score with usage boost
-                            let usage_boost = self.usage_tracker.calculate_boost(&entry.name);
-                            // Fuzzy score is the primary factor, usage provides a boost
-                            // Usage boost can add up to 50% to the fuzzy score
-                            let combined_score = fuzzy_score as f64 * (1.0 + usage_boost * 0.5);
-                            (combined_score, entry.clone())
-                        })
-                })
+            GAME_GRID_COLUMNS
+        }
+    }
+
+    /// Results shown at once, a screenful of tiles in the overview
+    fn page_size(&self) -> usize {
+        if self.is_overview() {
+            OVERVIEW_COLUMNS as usize * OVERVIEW_ROWS
+        } else {
+            PAGE_SIZE
+        }
+    }
+
+    fn row_style(&self) -> RowStyle {
+        RowStyle {
+            icon_size: self.config.list_icon_size(),
+            actions: self.config.row_actions && !self.config.kiosk.enabled,
+            drag: !self.config.kiosk.enabled,
+            chips: self.is_bar(),
+            usage: self.config.usage_indicators,
+            network: self.config.network,
+        }
+    }
+
+    /// The file the selected text result names, e.g. a line from `fd | adwlauncher --dmenu`
+    fn selected_file(&self) -> Option<PathBuf> {
+        self.selected_entry(&[OpenType::Text])
+            .and_then(|entry| app_discovery::entry_file(&entry))
+    }
+
+    /// Point the open preview pane at the selection, loading the file off the main loop
+    fn update_preview(&mut self, sender: &ComponentSender<Self>) {
+        if !self.preview_open {
+            return;
+        }
+        let path = self.selected_file();
+        if path == self.previewed {
+            return;
+        }
+        self.previewed = path.clone();
+
+        self.preview_picture.set_visible(false);
+        self.preview_text.set_visible(true);
+        match path {
+            Some(path) if !self.config.network && network::is_remote(&path) => self
+                .preview_text
+                .set_label("On a network filesystem, previews need network = true"),
+            Some(path) => {
+                self.preview_text.set_label("");
+                sender.spawn_oneshot_command(move || {
+                    let result = preview::load(&path);
+                    CmdOut::PreviewLoaded(path, result)
+                });
+            }
+            None => self.preview_text.set_label("Not a file"),
+        }
+    }
+
+    /// The selected entry, if it is one of the given kinds
+    fn selected_entry(&self, kinds: &[OpenType]) -> Option<Entry> {
+        self.entries
+            .get(self.selected_index)
+            .map(|view| &view.entry)
+            .filter(|entry| kinds.contains(&entry.open_type))
+            .cloned()
+    }
+
+    fn request_action(&self, action: DestructiveAction, sender: &ComponentSender<Self>) {
+        if self.config.kiosk.enabled {
+            return;
+        }
+
+        if !action.needs_confirmation(&self.config.confirm) {
+            sender.input(Msg::PerformAction(Box::new(action)));
+            return;
+        }
+
+        let sender = sender.clone();
+        let confirmed = action.clone();
+        actions::confirm(&self.window, &action, move || {
+            sender.input(Msg::PerformAction(Box::new(confirmed.clone())));
+        });
+    }
+
+    /// Rank the entries for the query and show the first page, timing the search
+    fn filter_entries(&mut self) {
+        let started = Instant::now();
+
+        // Time rules leave entries out of every section, so they apply before any is built
+        let now = rules::now();
+        let hidden = now
+            .map(|now| rules::hidden(&self.config.rules, now))
+            .unwrap_or_default();
+        if hidden != self.hidden {
+            self.hidden = hidden;
+            self.update_top_apps();
+        }
+
+        self.rank_results(now);
+        self.search_time = started.elapsed();
+    }
+
+    /// "12 results · 3 ms", saying so while sources are still loading
+    fn result_stats(&self) -> String {
+        let count = match self.results.len() {
+            1 => "1 result".to_string(),
+            count => format!("{} results", count),
+        };
+        let mut stats = format!("{} · {} ms", count, self.search_time.as_millis());
+        if self.loading_sources > 0 {
+            stats.push_str(" · loading…");
+        }
+        stats
+    }
+
+    /// Fill `results` for the query, then show their first page
+    fn rank_results(&mut self, now: Option<rules::Time>) {
+        // The top apps strip only makes sense before anything is typed
+        if !self.search_query.is_empty() {
+            self.select_top_app(None);
+        }
+        self.top_apps.widget().set_visible(
+            self.search_query.is_empty()
+                && self.pickers.is_empty()
+                && !self.grid
+                && !self.is_bar()
+                && !self.top_apps.is_empty(),
+        );
+
+        // "?" lists the modes and keys instead of searching
+        if let Some(filter) = self.search_query.strip_prefix(help::PREFIX)
+            && self.accepts_prefixes()
+            && !self.config.kiosk.enabled
+        {
+            self.results = help::entries(filter, &self.config.keybinds);
+            self.page = 0;
+            self.show_page();
+            return;
+        }
+
+        let mut search_options = SearchOptions::from_config(&self.config);
+        // -i forces case-insensitive matching regardless of the config
+        search_options.ignore_case |= self.options.ignore_case;
+
+        // A picker only offers its own entries, e.g. the apps that can open a MIME type
+        let candidates = match self.pickers.last() {
+            Some(picker) => &picker.entries,
+            None => &self.all_entries,
+        };
+        let mut ranked = search::rank_entries_cached(
+            candidates,
+            &self.search_query,
+            &self.usage_tracker,
+            search_options,
+            &mut self.rank_cache,
+        );
+        // Without a mode the entries come from several providers
+        if !self.provider_entries.is_empty()
+            && self.pickers.is_empty()
+            && !self.search_query.is_empty()
+        {
+            app_discovery::merge_ranked(&mut ranked, &self.search_query, search_options);
+        }
+        if let Some(now) = now {
+            rules::apply(&self.config.rules, now, &mut ranked);
+        }
+        self.origin_badges = duplicates::prefer_launched(&mut ranked, &self.preferred_copies);
+
+        // Recent launches lead the empty query, apart from the usage ranking below
+        let recent = self.recent_entries();
+        if !recent.is_empty() {
+            ranked.retain(|(_score, entry)| {
+                !recent
+                    .iter()
+                    .any(|r| r.name == entry.name && r.open_type == entry.open_type)
+            });
+        }
+        // Then the apps whose windows were just closed, to open them again
+        let mut reopen = self.closed_entries();
+        reopen.retain(|entry| !recent.iter().any(|r| r.id == entry.id));
+        ranked.retain(|(_score, entry)| !reopen.iter().any(|r| r.id == entry.id));
+
+        // A typo shouldn't leave the list empty when a launched app is close to it
+        if ranked.is_empty() && self.options.dmenu_lines.is_none() && self.pickers.is_empty() {
+            let suggestions = search::suggestions(
+                &self.visible(candidates),
+                &self.search_query,
+                &self.usage_tracker,
+                search_options,
+            );
+            self.results = suggestions
+                .into_iter()
+                .enumerate()
+                .map(|(index, entry)| (entry, (index == 0).then_some("Did you mean")))
                 .collect();
+            self.page = 0;
+            self.show_page();
+            return;
+        }
 
-            // Sort by combined score (highest first)
-            scored_entries
-                .sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or(std::cmp::Ordering::Equal));
+        // Like GNOME's overview, the tiles are apps until something is typed
+        if self.is_overview() && self.search_query.is_empty() && self.accepts_prefixes() {
+            ranked.retain(|(_score, entry)| {
+                matches!(entry.open_type, OpenType::Graphical | OpenType::Terminal)
+            });
+        }
 
-            // Add filtered and sorted entries
-            for (_score, entry) in scored_entries {
-                self.entries.guard().push_back(entry);
+        // Apps in folders are reached through their folder until something is typed
+        if self.search_query.is_empty()
+            && self.accepts_prefixes()
+            && !self.config.folders.is_empty()
+        {
+            ranked = folders::group(ranked, &self.config.folders);
+        }
+
+        // Browsing by type, the tiles and the bar have no room for sections
+        if self.config.group_by_category
+            && self.search_query.is_empty()
+            && self.accepts_prefixes()
+            && !self.grid
+            && !self.is_bar()
+        {
+            ranked = categories::group(ranked, &self.expanded_categories);
+        }
+
+        let header = if recent.is_empty() && reopen.is_empty() {
+            None
+        } else {
+            Some("All")
+        };
+        self.results = recent
+            .into_iter()
+            .enumerate()
+            .map(|(index, entry)| (entry, (index == 0).then_some("Recent")))
+            .chain(
+                reopen
+                    .into_iter()
+                    .enumerate()
+                    .map(|(index, entry)| (entry, (index == 0).then_some("Recently closed"))),
+            )
+            .chain(
+                ranked
+                    .into_iter()
+                    .enumerate()
+                    .map(|(index, (_score, entry))| {
+                        (entry, if index == 0 { header } else { None })
+                    }),
+            )
+            .collect();
+        self.page = 0;
+        self.show_page();
+    }
+
+    /// Fill the list with the current page of results and select its first entry
+    fn show_page(&mut self) {
+        // Deselect current entry before clearing
+        if !self.entries.is_empty() && self.selected_index < self.entries.len() {
+            self.entries
+                .send(self.selected_index, EntryMsg::Select(false));
+        }
+
+        let style = self.row_style();
+        let tile_icon_size = self.config.show_icons.then_some(if self.is_overview() {
+            OVERVIEW_TILE_ICON_SIZE
+        } else {
+            GAME_TILE_ICON_SIZE
+        });
+        let page_size = self.page_size();
+        // Folders are made by dragging in the grid shown before anything is typed
+        let movable = self.grid
+            && self.search_query.is_empty()
+            && self.accepts_prefixes()
+            && !self.config.kiosk.enabled;
+        let mut entries = self.entries.guard();
+        let mut game_tiles = self.game_tiles.guard();
+        entries.clear();
+        game_tiles.clear();
+        for (entry, header) in self
+            .results
+            .iter()
+            .skip(self.page * page_size)
+            .take(page_size)
+        {
+            let badge = self.origin_badges.get(&entry.id).copied();
+            let usage = self.usage_tracker.get_stats(&entry.name).map(|stats| {
+                (
+                    self.usage_tracker.calculate_boost(&entry.name),
+                    stats.use_count,
+                )
+            });
+            // Only looked for when a row can use it, text results show its thumbnail
+            let thumbnail = style.icon_size.is_some() && entry.open_type == OpenType::Text;
+            let file = (style.drag || style.actions || thumbnail)
+                .then(|| app_discovery::entry_file(entry))
+                .flatten();
+            entries.push_back((entry.clone(), style, file, *header, badge, usage));
+            // The list stays filled in games mode, selection and activation go through it
+            if self.grid {
+                game_tiles.push_back((entry.clone(), tile_icon_size, movable));
             }
         }
+        drop(entries);
+        drop(game_tiles);
+        self.scrolled_window.vadjustment().set_value(0.0);
 
         // Reset selection to first entry
         self.selected_index = 0;
         if !self.entries.is_empty() {
-            self.entries.send(0, true);
+            self.entries.send(0, EntryMsg::Select(true));
+            if self.grid {
+                self.game_tiles.send(0, true);
+            }
             if let Some(entry) = self.entries.get(0) {
                 self.selected_name = entry.entry.name.clone();
             }
@@ -439,6 +2791,114 @@ impl App {
         }
     }
 
+    /// How far up and down move, a whole row of tiles in the grid
+    fn row_step(&self) -> isize {
+        if self.grid {
+            self.grid_columns() as isize
+        } else {
+            1
+        }
+    }
+
+    /// Move the selection by `step` results, wrapping around at either end
+    fn move_selection(&mut self, step: isize) {
+        self.select_top_app(None);
+        let entries_len = self.entries.len();
+        if entries_len == 0 {
+            return;
+        }
+
+        // Deselect current
+        self.entries
+            .send(self.selected_index, EntryMsg::Select(false));
+        if self.grid {
+            self.game_tiles.send(self.selected_index, false);
+        }
+
+        self.selected_index =
+            (self.selected_index as isize + step).rem_euclid(entries_len as isize) as usize;
+
+        // Select new
+        self.entries
+            .send(self.selected_index, EntryMsg::Select(true));
+        if self.grid {
+            self.game_tiles.send(self.selected_index, true);
+        }
+
+        // Update selected name
+        if let Some(entry) = self.entries.get(self.selected_index) {
+            self.selected_name = entry.entry.name.clone();
+        }
+
+        // Scroll to selected item
+        if self.grid {
+            self.scroll_to_tile(self.selected_index);
+        } else if self.is_bar() {
+            self.scroll_to_chip(self.selected_index);
+        } else {
+            self.scroll_to_index(self.selected_index);
+        }
+    }
+
+    /// Scroll the games grid just far enough to show a tile
+    fn scroll_to_tile(&self, index: usize) {
+        let grid = self.game_tiles.widget();
+        let Some(bounds) = grid
+            .child_at_index(index as i32)
+            .and_then(|child| child.compute_bounds(grid))
+        else {
+            return;
+        };
+
+        let adjustment = self.scrolled_window.vadjustment();
+        let margin = 20.0;
+        let top = bounds.y() as f64;
+        let bottom = top + bounds.height() as f64;
+
+        if top < adjustment.value() + margin {
+            adjustment.set_value((top - margin).max(0.0));
+        } else if bottom > adjustment.value() + adjustment.page_size() - margin {
+            // The adjustment clamps this to its upper bound
+            adjustment.set_value(bottom - adjustment.page_size() + margin);
+        }
+    }
+
+    /// Scroll the command bar just far enough to show a chip
+    fn scroll_to_chip(&self, index: usize) {
+        let entries_box = self.entries.widget();
+        let Some(bounds) =
+            std::iter::successors(entries_box.first_child(), |child| child.next_sibling())
+                .nth(index)
+                .and_then(|child| child.compute_bounds(entries_box))
+        else {
+            return;
+        };
+
+        let adjustment = self.scrolled_window.hadjustment();
+        let margin = 20.0;
+        let left = bounds.x() as f64;
+        let right = left + bounds.width() as f64;
+
+        if left < adjustment.value() + margin {
+            adjustment.set_value((left - margin).max(0.0));
+        } else if right > adjustment.value() + adjustment.page_size() - margin {
+            // The adjustment clamps this to its upper bound
+            adjustment.set_value(right - adjustment.page_size() + margin);
+        }
+    }
+
+    /// Where the list is in the results, e.g. "201–400 of 1234"
+    fn page_label(&self) -> String {
+        let start = self.page * self.page_size();
+        let end = (start + self.page_size()).min(self.results.len());
+        let label = format!("{}–{} of {} results", start + 1, end, self.results.len());
+        if end < self.results.len() {
+            format!("{}, Ctrl+Page Down for more", label)
+        } else {
+            label
+        }
+    }
+
     fn scroll_to_index(&self, index: usize) {
         let entries_box = self.entries.widget();
         let adjustment = self.scrolled_window.vadjustment();
@@ -506,15 +2966,372 @@ impl App {
     }
 }
 
+/// Actions for scripts and compositor binds, e.g.
+/// `gapplication action me.bofusland.adwlauncher show-mode '"windows"'`
+fn register_actions(sender: &ComponentSender<App>) {
+    let application = relm4::main_application();
+
+    let show = gtk::gio::SimpleAction::new("show", None);
+    let show_sender = sender.clone();
+    show.connect_activate(move |_, _| show_sender.input(Msg::Show(None)));
+    application.add_action(&show);
+
+    let show_mode = gtk::gio::SimpleAction::new("show-mode", Some(gtk::glib::VariantTy::STRING));
+    let show_mode_sender = sender.clone();
+    show_mode.connect_activate(move |_, parameter| {
+        let name = parameter.and_then(|p| p.str()).unwrap_or_default();
+        match <Mode as ValueEnum>::from_str(name, true) {
+            Ok(mode) => show_mode_sender.input(Msg::Show(Some(mode))),
+            Err(_) => eprintln!("Unknown mode for show-mode: {}", name),
+        }
+    });
+    application.add_action(&show_mode);
+
+    let quit = gtk::gio::SimpleAction::new("quit", None);
+    quit.connect_activate(|_, _| relm4::main_application().quit());
+    application.add_action(&quit);
+
+    let refresh_cache = gtk::gio::SimpleAction::new("refresh-cache", None);
+    let refresh_sender = sender.clone();
+    refresh_cache.connect_activate(move |_, _| refresh_sender.input(Msg::RefreshCache));
+    application.add_action(&refresh_cache);
+}
+
+/// `shown_mode` comes from the show-mode action and only applies without --mode
+/// The installed apps, e.g. for the apps that open a MIME type. May rescan their
+/// directories, so off the main loop.
+fn installed_apps(config: &Config) -> Vec<Entry> {
+    get_desktop_entries_cached(config).unwrap_or_else(|e| {
+        eprintln!("Failed to load applications: {:#}", e);
+        vec![]
+    })
+}
+
+fn load_entries(
+    options: &RunOptions,
+    mode: Option<Mode>,
+    config: &Config,
+) -> error::Result<Vec<Entry>> {
+    match &options.dmenu_lines {
+        Some(lines) => Ok(lines.iter().map(|line| Entry::text(line)).collect()),
+        None => get_entries_for_mode(mode, config),
+    }
+}
+
+/// The mode from the show-mode action or a typed prefix, then --mode, so the
+/// action can switch a service started with one. Without any of them the last
+/// mode asked for is shown again.
+fn effective_mode(
+    options: &RunOptions,
+    shown_mode: Option<Mode>,
+    remembered_mode: Option<Mode>,
+) -> Option<Mode> {
+    if options.dmenu_lines.is_some() {
+        return None;
+    }
+    shown_mode.or(options.mode).or(remembered_mode)
+}
+
+type Accelerator = (gtk::gdk::Key, gtk::gdk::ModifierType);
+
+/// Key bindings from the config, parsed into GTK accelerators
+#[derive(Clone)]
+struct KeyMap {
+    up: Vec<Accelerator>,
+    down: Vec<Accelerator>,
+    accept: Vec<Accelerator>,
+    cancel: Vec<Accelerator>,
+    close_window: Vec<Accelerator>,
+    kill_process: Vec<Accelerator>,
+    uninstall: Vec<Accelerator>,
+    next_page: Vec<Accelerator>,
+    previous_page: Vec<Accelerator>,
+    refresh: Vec<Accelerator>,
+    type_text: Vec<Accelerator>,
+    peek: Vec<Accelerator>,
+    preview: Vec<Accelerator>,
+    open_with: Vec<Accelerator>,
+    window_scope: Vec<Accelerator>,
+}
+
+impl KeyMap {
+    fn new(keybinds: &Keybinds) -> Self {
+        let parse = |binds: &[String]| -> Vec<Accelerator> {
+            binds
+                .iter()
+                .filter_map(|bind| {
+                    let parsed = gtk::accelerator_parse(bind.as_str());
+                    if parsed.is_none() {
+                        eprintln!("Ignoring invalid key binding: {}", bind);
+                    }
+                    parsed.map(|(key, modifier)| (normalize_key(key), modifier))
+                })
+                .collect()
+        };
+
+        Self {
+            up: parse(&keybinds.up),
+            down: parse(&keybinds.down),
+            accept: parse(&keybinds.accept),
+            cancel: parse(&keybinds.cancel),
+            close_window: parse(&keybinds.close_window),
+            kill_process: parse(&keybinds.kill_process),
+            uninstall: parse(&keybinds.uninstall),
+            next_page: parse(&keybinds.next_page),
+            previous_page: parse(&keybinds.previous_page),
+            refresh: parse(&keybinds.refresh),
+            type_text: parse(&keybinds.type_text),
+            peek: parse(&keybinds.peek),
+            preview: parse(&keybinds.preview),
+            open_with: parse(&keybinds.open_with),
+            window_scope: parse(&keybinds.window_scope),
+        }
+    }
+
+    fn action(&self, key: gtk::gdk::Key, modifier: gtk::gdk::ModifierType) -> Option<Msg> {
+        let pressed = (
+            normalize_key(key),
+            modifier & gtk::accelerator_get_default_mod_mask(),
+        );
+
+        if self.up.contains(&pressed) {
+            Some(Msg::NavigateUp)
+        } else if self.down.contains(&pressed) {
+            Some(Msg::NavigateDown)
+        } else if self.accept.contains(&pressed) || is_soft_keyboard_action(key) {
+            Some(Msg::SelectEntry)
+        } else if self.cancel.contains(&pressed) {
+            Some(Msg::CloseWindow)
+        } else if self.close_window.contains(&pressed) {
+            Some(Msg::CloseSelectedWindow)
+        } else if self.kill_process.contains(&pressed) {
+            Some(Msg::KillSelectedProcess)
+        } else if self.uninstall.contains(&pressed) {
+            Some(Msg::UninstallSelected)
+        } else if self.next_page.contains(&pressed) {
+            Some(Msg::NextPage)
+        } else if self.previous_page.contains(&pressed) {
+            Some(Msg::PreviousPage)
+        } else if self.refresh.contains(&pressed) {
+            Some(Msg::RefreshCache)
+        } else if self.type_text.contains(&pressed) {
+            Some(Msg::TypeSelected)
+        } else if self.peek.contains(&pressed) {
+            Some(Msg::PeekSelected)
+        } else if self.preview.contains(&pressed) {
+            Some(Msg::TogglePreview)
+        } else if self.open_with.contains(&pressed) {
+            Some(Msg::OpenWithSelected)
+        } else if self.window_scope.contains(&pressed) {
+            Some(Msg::CycleWindowScope)
+        } else {
+            None
+        }
+    }
+}
+
+/// Keypads and ISO layouts have Enter keys of their own, which bindings for
+/// Return cover as well
+fn normalize_key(key: gtk::gdk::Key) -> gtk::gdk::Key {
+    match key {
+        gtk::gdk::Key::KP_Enter | gtk::gdk::Key::ISO_Enter => gtk::gdk::Key::Return,
+        key => key.to_lower(),
+    }
+}
+
+/// The Search and Go keys on-screen keyboards send for their action button
+fn is_soft_keyboard_action(key: gtk::gdk::Key) -> bool {
+    matches!(key, gtk::gdk::Key::Search | gtk::gdk::Key::Go)
+}
+
+/// An exclusive keyboard grab keeps on-screen keyboards like squeekboard
+/// and wvkbd from showing up, so touch mode only takes focus on demand
+fn apply_keyboard_mode(window: &adw::ApplicationWindow, config: &Config) {
+    window.set_keyboard_mode(if config.touch {
+        gtk4_layer_shell::KeyboardMode::OnDemand
+    } else {
+        gtk4_layer_shell::KeyboardMode::Exclusive
+    });
+}
+
+fn apply_geometry(window: &adw::ApplicationWindow, config: &Config, layout: Layout) {
+    let preset = preset_size(config);
+    let fullscreen =
+        layout == Layout::Overview || (preset.is_some() && config.size == SizePreset::Fullscreen);
+    let (width, height) = preset.unwrap_or((config.width, config.height));
+
+    // Without layer shell the window can be resized, so the last size is restored
+    if gtk4_layer_shell::is_supported() && layout == Layout::Bar {
+        // Stretched across the top edge, so only the height matters
+        window.set_default_size(-1, COMMAND_BAR_HEIGHT);
+        window.set_anchor(Edge::Top, true);
+        window.set_anchor(Edge::Bottom, false);
+        window.set_anchor(Edge::Left, true);
+        window.set_anchor(Edge::Right, true);
+    } else if gtk4_layer_shell::is_supported() && fullscreen {
+        // Anchoring every edge leaves out the space panels reserve
+        window.set_default_size(-1, -1);
+        for edge in [Edge::Top, Edge::Bottom, Edge::Left, Edge::Right] {
+            window.set_anchor(edge, true);
+        }
+    } else if gtk4_layer_shell::is_supported() {
+        window.set_default_size(width, height);
+        window.set_anchor(Edge::Top, config.position == Position::Top);
+        window.set_anchor(Edge::Bottom, config.position == Position::Bottom);
+        window.set_anchor(Edge::Left, false);
+        window.set_anchor(Edge::Right, false);
+    } else if layout == Layout::Bar {
+        window.set_default_size(width, COMMAND_BAR_HEIGHT);
+    } else if layout == Layout::Overview {
+        window.fullscreen();
+    } else if preset.is_some() {
+        window.set_default_size(width, height);
+    } else {
+        let state = State::load();
+        window.set_default_size(
+            state.width.unwrap_or(config.width),
+            state.height.unwrap_or(config.height),
+        );
+    }
+}
+
+/// The window size the size preset gives on the focused output. None for a custom
+/// size, and outside niri and Hyprland or when it can't tell, where width and height apply.
+fn preset_size(config: &Config) -> Option<(i32, i32)> {
+    let (width_share, height_share) = config.size.fraction()?;
+    let backend = compositor::backend()?;
+
+    match backend.focused_output_size() {
+        Ok((width, height)) => Some((
+            (width as f64 * width_share).round() as i32,
+            (height as f64 * height_share).round() as i32,
+        )),
+        Err(e) => {
+            eprintln!("Failed to size the window for the output: {:#}", e);
+            None
+        }
+    }
+}
+
+/// Report a failed headless command and exit with a non-zero status
+fn exit_on_error(result: error::Result<()>) {
+    if let Err(e) = result {
+        eprintln!("{:#}", e);
+        std::process::exit(1);
+    }
+}
+
 fn main() {
-    let app = RelmApp::new("me.bofusland.adwlauncher");
+    let cli = Cli::parse_from(cli::translate_rofi_args(std::env::args()));
+
+    if let Some(name) = &cli.profile {
+        exit_on_error(profile::set(name));
+    }
+
+    // The crash log goes into the profile's directory
+    let restart_on_crash =
+        cli.gapplication_service && Config::load().is_ok_and(|config| config.restart_on_crash);
+    crash::install(restart_on_crash);
 
-    // Check if we're running with --gapplication-service flag
-    let has_service_flag = std::env::args().nth(1) == Some("--gapplication-service".to_string());
+    match &cli.command {
+        Some(Command::Launch { desktop_id }) => {
+            exit_on_error(commands::launch(desktop_id));
+            return;
+        }
+        Some(Command::Doctor) => {
+            match doctor::run() {
+                Ok(true) => {}
+                Ok(false) => std::process::exit(1),
+                Err(e) => exit_on_error(Err(e)),
+            }
+            return;
+        }
+        Some(Command::Refresh) => {
+            exit_on_error(commands::refresh());
+            return;
+        }
+        Some(Command::DumpCache { json }) => {
+            exit_on_error(commands::dump_cache(*json));
+            return;
+        }
+        Some(Command::ExportState { file, usage }) => {
+            exit_on_error(commands::export_state(file.as_deref(), *usage));
+            return;
+        }
+        Some(Command::ImportState { file }) => {
+            exit_on_error(commands::import_state(file));
+            return;
+        }
+        Some(Command::Completions { shell }) => {
+            exit_on_error(commands::completions(*shell));
+            return;
+        }
+        None => {}
+    }
 
-    if !has_service_flag {
+    if cli.generate_man {
+        exit_on_error(commands::generate_man());
+        return;
+    }
+
+    if let Some(source) = cli.import_config {
+        exit_on_error(commands::import_config(source));
+        return;
+    }
+
+    if cli.list {
+        let query = cli.query.unwrap_or_default();
+        exit_on_error(commands::list(cli.mode, &query, cli.json));
+        return;
+    }
+
+    if let Some(mode) = cli.mode {
+        State::update(|state| state.mode = Some(mode));
+    }
+
+    let options = RunOptions {
+        mode: cli.mode,
+        // A password prompt has nothing to choose from, so stdin is left alone
+        dmenu_lines: match (cli.dmenu, cli.password) {
+            (true, false) => Some(
+                std::io::stdin()
+                    .lines()
+                    .map_while(|line| line.ok())
+                    .filter(|line| !line.is_empty())
+                    .collect(),
+            ),
+            (true, true) => Some(vec![]),
+            (false, _) => None,
+        },
+        password: cli.password,
+        prompt: cli.prompt,
+        prompt_icon: cli.prompt_icon,
+        ignore_case: cli.ignore_case,
+        layout: cli.layout,
+    };
+
+    index::set_enabled(cli.gapplication_service);
+    // Only a running service is around to see windows close
+    if cli.gapplication_service
+        && let Some(backend) = compositor::backend()
+    {
+        closed::watch(backend);
+    }
+
+    let app = RelmApp::new(&profile::application_id());
+
+    if options.is_standalone() {
+        // Don't hand these options over to an already running service
+        relm4::main_application().set_flags(gtk::gio::ApplicationFlags::NON_UNIQUE);
+    } else if !cli.gapplication_service {
         eprintln!("Please run with --gapplication-service");
     }
 
-    app.run::<App>(());
+    // Everything except the service flag is handled by clap, so GTK only sees that
+    let mut gtk_args: Vec<String> = std::env::args().take(1).collect();
+    if cli.gapplication_service {
+        gtk_args.push("--gapplication-service".to_string());
+    }
+
+    app.with_args(gtk_args).run::<App>(options);
 }
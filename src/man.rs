@@ -0,0 +1,78 @@
+use crate::config::{Config, ManViewer};
+use crate::error::{LauncherError, Result};
+use crate::index;
+use crate::types::{Entry, OpenType};
+use std::collections::HashSet;
+use std::process::{Command, Stdio};
+
+/// Changes when mandb rebuilds the whatis database, e.g. after installing packages
+pub fn fingerprint() -> String {
+    index::modified_key([
+        "/var/cache/man",
+        "/var/cache/man/index.db",
+        "/usr/share/man",
+    ])
+}
+
+/// Every installed manual page, from the whatis database behind `man -k`
+pub fn get_man_entries(config: &Config) -> Result<Vec<Entry>> {
+    let output = Command::new("man")
+        .args(["-k", "."])
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()
+        .map_err(|e| LauncherError::Launch(format!("Failed to run man -k: {}", e)))?;
+
+    // man -k also fails when nothing matches, e.g. before mandb ever ran
+    let listing = String::from_utf8_lossy(&output.stdout);
+    let mut seen = HashSet::new();
+    Ok(listing
+        .lines()
+        .flat_map(parse_line)
+        .filter(|(name, section, _)| seen.insert(format!("{}.{}", name, section)))
+        .map(|(name, section, description)| entry(name, section, description, config))
+        .collect())
+}
+
+/// "ls (1)  - list directory contents" from man-db, or "ls(1) - ..." from mandoc,
+/// which may also list several names, e.g. "cat, tac(1) - ..."
+fn parse_line(line: &str) -> Vec<(&str, &str, &str)> {
+    let Some((page, description)) = line.split_once(" - ") else {
+        return vec![];
+    };
+    let page = page.trim();
+    let (Some(open), Some(close)) = (page.rfind('('), page.rfind(')')) else {
+        return vec![];
+    };
+    if close < open {
+        return vec![];
+    }
+
+    let section = &page[open + 1..close];
+    page[..open]
+        .split(',')
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .map(|name| (name, section, description.trim()))
+        .collect()
+}
+
+fn entry(name: &str, section: &str, description: &str, config: &Config) -> Entry {
+    let (exec, open_type) = match config.man_viewer {
+        ManViewer::Terminal => (format!("man {} {}", section, name), OpenType::Terminal),
+        ManViewer::Yelp => (
+            format!("yelp man:{}({})", name, section),
+            OpenType::Graphical,
+        ),
+    };
+
+    Entry {
+        id: format!("man-{}.{}", name, section),
+        // The description is part of the name, so pages are found by topic too
+        name: format!("{}({}) — {}", name, section, description),
+        exec,
+        icon: "accessories-dictionary-symbolic".to_string(),
+        open_type,
+        ..Default::default()
+    }
+}
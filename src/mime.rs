@@ -0,0 +1,132 @@
+use crate::error::{LauncherError, Result};
+use crate::exec::quote_argument;
+use crate::types::{Entry, OpenType};
+use relm4::gtk::gio;
+use std::collections::{BTreeSet, HashMap};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// One entry per MIME type some installed app can open, named with its current default
+pub fn mime_entries(apps: &[Entry]) -> Vec<Entry> {
+    let defaults = default_apps();
+    let mime_types: BTreeSet<&str> = apps
+        .iter()
+        .flat_map(|app| app.mime_types.iter().map(String::as_str))
+        .collect();
+
+    mime_types
+        .into_iter()
+        .map(|mime| {
+            let default = defaults
+                .get(mime)
+                .and_then(|id| apps.iter().find(|app| app.id == *id))
+                .map(|app| app.name.as_str())
+                .unwrap_or("no default");
+
+            Entry {
+                id: mime.to_string(),
+                name: format!("{} ({})", mime, default),
+                exec: mime.to_string(),
+                // Icon themes name MIME type icons like "application-pdf"
+                icon: mime.replace('/', "-"),
+                open_type: OpenType::MimeType,
+                ..Default::default()
+            }
+        })
+        .collect()
+}
+
+/// The apps that can open a MIME type, to pick the new default from
+pub fn handlers(apps: &[Entry], mime: &str) -> Vec<Entry> {
+    apps.iter()
+        .filter(|app| app.mime_types.iter().any(|m| m == mime))
+        .cloned()
+        .collect()
+}
+
+/// A file's MIME type, guessed from its name and first bytes like file managers do
+pub fn file_type(path: &Path) -> Option<String> {
+    let mut head = vec![];
+    if let Ok(file) = std::fs::File::open(path) {
+        let _ = file.take(4096).read_to_end(&mut head);
+    }
+    let (content_type, _uncertain) = gio::content_type_guess(Some(path), head.as_slice());
+    gio::content_type_get_mime_type(&content_type).map(|mime| mime.to_string())
+}
+
+/// The app entry with the file added to its command line, named after both
+pub fn open_with(app: &Entry, path: &Path) -> Entry {
+    let file = path
+        .file_name()
+        .map_or_else(|| path.to_string_lossy(), |name| name.to_string_lossy());
+    Entry {
+        name: format!("{} with {}", file, app.name),
+        exec: format!("{} {}", app.exec, quote_argument(&path.to_string_lossy())),
+        ..app.clone()
+    }
+}
+
+/// Make an app the default for a MIME type through xdg-mime
+pub fn set_default(app_id: &str, mime: &str) -> Result<()> {
+    let status = Command::new("xdg-mime")
+        .args(["default", app_id, mime])
+        .status()
+        .map_err(|e| LauncherError::Launch(format!("Failed to run xdg-mime: {}", e)))?;
+
+    if !status.success() {
+        return Err(LauncherError::Launch(format!(
+            "xdg-mime failed with {}",
+            status
+        )));
+    }
+
+    Ok(())
+}
+
+/// mimeapps.list files, most important first
+fn mimeapps_lists() -> Vec<PathBuf> {
+    let mut paths = vec![];
+    if let Some(config_dir) = dirs::config_dir() {
+        paths.push(config_dir.join("mimeapps.list"));
+    }
+    if let Some(data_dir) = dirs::data_dir() {
+        paths.push(data_dir.join("applications/mimeapps.list"));
+    }
+    paths.push(PathBuf::from("/etc/xdg/mimeapps.list"));
+    paths.push(PathBuf::from("/usr/share/applications/mimeapps.list"));
+    paths
+}
+
+/// The current default desktop file ID for each MIME type that has one
+fn default_apps() -> HashMap<String, String> {
+    let mut defaults = HashMap::new();
+
+    for path in mimeapps_lists() {
+        let Ok(content) = std::fs::read_to_string(&path) else {
+            continue;
+        };
+
+        let mut in_defaults = false;
+        for line in content.lines().map(str::trim) {
+            if line.starts_with('[') {
+                in_defaults = line == "[Default Applications]";
+                continue;
+            }
+            if !in_defaults {
+                continue;
+            }
+
+            if let Some((mime, apps)) = line.split_once('=')
+                && let Some(app) = apps.split(';').map(str::trim).find(|app| !app.is_empty())
+            {
+                // Earlier files take precedence
+                defaults
+                    .entry(mime.trim().to_string())
+                    .or_insert_with(|| app.to_string());
+            }
+        }
+    }
+
+    defaults
+}
@@ -0,0 +1,125 @@
+//! What `network = false` keeps the launcher from reaching: files on network
+//! filesystems, which are told apart by their mount so asking doesn't touch them
+
+use crate::types::Entry;
+use std::fs;
+use std::path::Path;
+
+const MOUNTS: &str = "/proc/self/mounts";
+
+/// Filesystem types whose files are fetched over the network, as the mount table names them
+const REMOTE_FILESYSTEMS: &[&str] = &[
+    "nfs",
+    "nfs4",
+    "cifs",
+    "smb3",
+    "smbfs",
+    "ncpfs",
+    "9p",
+    "afs",
+    "ceph",
+    "glusterfs",
+    "lustre",
+    "davfs",
+    "fuse.sshfs",
+    "fuse.rclone",
+    "fuse.s3fs",
+    "fuse.gvfsd-fuse",
+];
+
+/// Whether reading the file would go over the network
+pub fn is_remote(path: &Path) -> bool {
+    fs::read_to_string(MOUNTS).is_ok_and(|mounts| is_remote_in(&mounts, path))
+}
+
+/// Clear the icons kept on network filesystems, the entries show a letter avatar instead
+pub fn drop_remote_icons(entries: &mut [Entry]) {
+    let Ok(mounts) = fs::read_to_string(MOUNTS) else {
+        return;
+    };
+    for entry in entries.iter_mut() {
+        if entry.icon.starts_with('/') && is_remote_in(&mounts, Path::new(&entry.icon)) {
+            entry.icon.clear();
+        }
+    }
+}
+
+/// Whether the path is on a remote mount in `mounts`, in the format of /proc/self/mounts.
+/// The deepest mount point holding the path counts, and the last one mounted there.
+fn is_remote_in(mounts: &str, path: &Path) -> bool {
+    mounts
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let (_device, mount_point, fs_type) = (fields.next()?, fields.next()?, fields.next()?);
+            Some((unescape(mount_point), fs_type))
+        })
+        .filter(|(mount_point, _)| path.starts_with(mount_point))
+        .max_by_key(|(mount_point, _)| mount_point.len())
+        .is_some_and(|(_, fs_type)| REMOTE_FILESYSTEMS.contains(&fs_type))
+}
+
+/// Undo the octal escapes of spaces, tabs, newlines and backslashes in mount points
+fn unescape(field: &str) -> String {
+    let mut unescaped = String::with_capacity(field.len());
+    let mut rest = field;
+    while let Some(at) = rest.find('\\') {
+        unescaped.push_str(&rest[..at]);
+        let byte = rest
+            .get(at + 1..at + 4)
+            .and_then(|octal| u8::from_str_radix(octal, 8).ok());
+        match byte {
+            Some(byte) => {
+                unescaped.push(char::from(byte));
+                rest = &rest[at + 4..];
+            }
+            None => {
+                unescaped.push('\\');
+                rest = &rest[at + 1..];
+            }
+        }
+    }
+    unescaped.push_str(rest);
+    unescaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TABLE: &str = "\
+/dev/nvme0n1p2 / btrfs rw,relatime 0 0
+nas:/export /mnt/nas nfs4 rw,relatime 0 0
+/dev/sdb1 /mnt/nas/usb ext4 rw,relatime 0 0
+//server/share /mnt/my\\040share cifs rw 0 0
+gvfsd-fuse /run/user/1000/gvfs fuse.gvfsd-fuse rw,nosuid,nodev 0 0
+";
+
+    #[test]
+    fn the_deepest_mount_decides() {
+        assert!(is_remote_in(TABLE, Path::new("/mnt/nas/icons/app.png")));
+        assert!(!is_remote_in(TABLE, Path::new("/mnt/nas/usb/app.png")));
+        assert!(!is_remote_in(TABLE, Path::new("/mnt/nasty/app.png")));
+        assert!(!is_remote_in(
+            TABLE,
+            Path::new("/usr/share/pixmaps/app.png")
+        ));
+        assert!(is_remote_in(
+            TABLE,
+            Path::new("/run/user/1000/gvfs/sftp:host=example/notes.txt")
+        ));
+    }
+
+    #[test]
+    fn escaped_mount_points_are_matched() {
+        assert_eq!(unescape(r"/mnt/my\040share"), "/mnt/my share");
+        assert_eq!(unescape(r"/odd\path"), r"/odd\path");
+        assert!(is_remote_in(TABLE, Path::new("/mnt/my share/report.pdf")));
+    }
+
+    #[test]
+    fn later_mounts_hide_earlier_ones() {
+        let remounted = format!("{}/dev/sdc1 /mnt/nas ext4 rw 0 0\n", TABLE);
+        assert!(!is_remote_in(&remounted, Path::new("/mnt/nas/app.png")));
+    }
+}
@@ -0,0 +1,469 @@
+use crate::compositor::{Backend, Events, Window, WindowEvent};
+use crate::config::{WindowRules, WindowScope};
+use crate::error::{LauncherError, Result};
+use niri_ipc::{Action, Event, Reply, Request, Response, SizeChange, WorkspaceReferenceArg};
+use std::collections::{HashMap, HashSet};
+use std::io::{BufRead, BufReader, ErrorKind, Write};
+use std::os::unix::net::UnixStream;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+#[cfg(test)]
+pub mod mock;
+
+/// niri answers right away, a request taking longer than this means it is stuck
+pub const TIMEOUT: Duration = Duration::from_millis(500);
+
+/// niri, through the socket NIRI_SOCKET names. niri answers one request per
+/// connection and closes it, so every request connects anew, like niri_ipc does.
+pub struct Niri {
+    path: PathBuf,
+}
+
+impl Niri {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    /// The niri instance the launcher runs in, None outside niri
+    pub fn from_env() -> Option<Self> {
+        std::env::var_os("NIRI_SOCKET").map(Self::new)
+    }
+
+    /// Send a request on a connection of its own. `description` completes
+    /// "Failed to …" in errors, e.g. "spawn application".
+    pub fn send(&self, request: Request, description: &str) -> Result<Response> {
+        Connection::open(&self.path)?
+            .send(&request)
+            .map_err(|e| request_error(e, description))?
+            .map_err(|e| LauncherError::NiriRequest(format!("Niri error: {}", e)))
+    }
+
+    fn action(&self, action: Action, description: &str) -> Result<()> {
+        self.send(Request::Action(action), description)?;
+        Ok(())
+    }
+
+    fn query_windows(&self) -> Result<Vec<niri_ipc::Window>> {
+        match self.send(Request::Windows, "query windows")? {
+            Response::Windows(windows) => Ok(windows),
+            _ => Err(unexpected_response()),
+        }
+    }
+
+    /// The workspaces whose windows are listed, None for all of them
+    fn scoped_workspaces(&self, scope: WindowScope) -> Result<Option<HashSet<u64>>> {
+        if scope == WindowScope::All {
+            return Ok(None);
+        }
+
+        let workspaces = match self.send(Request::Workspaces, "query workspaces")? {
+            Response::Workspaces(workspaces) => workspaces,
+            _ => return Err(unexpected_response()),
+        };
+        let Some(focused) = workspaces.iter().find(|workspace| workspace.is_focused) else {
+            return Ok(None);
+        };
+        Ok(Some(
+            workspaces
+                .iter()
+                .filter(|workspace| match scope {
+                    WindowScope::Output => workspace.output == focused.output,
+                    _ => workspace.id == focused.id,
+                })
+                .map(|workspace| workspace.id)
+                .collect(),
+        ))
+    }
+}
+
+impl Backend for Niri {
+    fn version(&self) -> Result<String> {
+        match self.send(Request::Version, "query the version")? {
+            Response::Version(version) => Ok(format!("niri {}", version)),
+            _ => Err(unexpected_response()),
+        }
+    }
+
+    fn windows(&self, scope: WindowScope) -> Result<Vec<Window>> {
+        let workspaces = self.scoped_workspaces(scope)?;
+        Ok(self
+            .query_windows()?
+            .into_iter()
+            .filter(|window| match &workspaces {
+                Some(workspaces) => window
+                    .workspace_id
+                    .is_some_and(|id| workspaces.contains(&id)),
+                None => true,
+            })
+            .map(window)
+            .collect())
+    }
+
+    fn focus_window(&self, id: &str) -> Result<()> {
+        self.action(Action::FocusWindow { id: id.parse()? }, "focus window")
+    }
+
+    fn close_window(&self, id: &str) -> Result<()> {
+        self.action(
+            Action::CloseWindow {
+                id: Some(id.parse()?),
+            },
+            "close window",
+        )
+    }
+
+    fn window_pid(&self, id: &str) -> Result<Option<u32>> {
+        let id = id.parse::<u64>()?;
+        Ok(self
+            .query_windows()?
+            .into_iter()
+            .find(|window| window.id == id)
+            .and_then(|window| window.pid)
+            .map(|pid| pid as u32))
+    }
+
+    fn spawn(&self, command: &[String], description: &str) -> Result<()> {
+        self.action(
+            Action::Spawn {
+                command: command.to_vec(),
+            },
+            description,
+        )
+    }
+
+    fn focus_workspace(&self, workspace: &str) -> Result<()> {
+        self.action(
+            Action::FocusWorkspace {
+                reference: workspace_reference(workspace),
+            },
+            "switch workspace",
+        )
+    }
+
+    fn apply_window_rules(&self, id: &str, rules: &WindowRules) -> Result<()> {
+        let id = id.parse::<u64>()?;
+        match rules.floating {
+            Some(true) => self.action(
+                Action::MoveWindowToFloating { id: Some(id) },
+                "float window",
+            )?,
+            Some(false) => {
+                self.action(Action::MoveWindowToTiling { id: Some(id) }, "tile window")?
+            }
+            None => {}
+        }
+
+        if let Some(workspace) = &rules.workspace {
+            self.action(
+                Action::MoveWindowToWorkspace {
+                    window_id: Some(id),
+                    reference: workspace_reference(workspace),
+                    focus: false,
+                },
+                "move window to workspace",
+            )?;
+        }
+
+        if let Some(width) = &rules.column_width {
+            let change = width.parse::<SizeChange>().map_err(|e| {
+                LauncherError::Config(format!("Invalid column width {}: {}", width, e))
+            })?;
+            self.action(
+                Action::SetWindowWidth {
+                    id: Some(id),
+                    change,
+                },
+                "set window width",
+            )?;
+        }
+
+        Ok(())
+    }
+
+    fn log_out(&self) -> Result<()> {
+        self.action(
+            Action::Quit {
+                skip_confirmation: true,
+            },
+            "log out",
+        )
+    }
+
+    /// niri derives the logical size from the mode's resolution, the scale and the rotation
+    fn focused_output_size(&self) -> Result<(i32, i32)> {
+        match self.send(Request::FocusedOutput, "query the focused output")? {
+            Response::FocusedOutput(Some(output)) => output
+                .logical
+                .map(|logical| (logical.width as i32, logical.height as i32))
+                .ok_or_else(|| {
+                    LauncherError::NiriRequest(format!("Output {} is disabled", output.name))
+                }),
+            Response::FocusedOutput(None) => Err(LauncherError::NiriRequest(
+                "No output is focused".to_string(),
+            )),
+            _ => Err(unexpected_response()),
+        }
+    }
+
+    fn events(&self) -> Result<Box<dyn Events>> {
+        Ok(Box::new(NiriEvents::subscribe(&self.path)?))
+    }
+}
+
+/// niri's event stream. Closing only names the window, so the open ones are kept.
+struct NiriEvents {
+    connection: Connection,
+    open: HashMap<u64, Window>,
+}
+
+impl NiriEvents {
+    fn subscribe(path: &Path) -> Result<Self> {
+        let mut connection = Connection::open(path)?;
+        connection
+            .send(&Request::EventStream)
+            .map_err(|e| request_error(e, "subscribe to events"))?
+            .map_err(|e| LauncherError::NiriRequest(format!("Niri error: {}", e)))?;
+
+        // The stream starts with the full state, including the window list
+        let deadline = Instant::now() + TIMEOUT;
+        loop {
+            let event = connection
+                .read_event(deadline.saturating_duration_since(Instant::now()))
+                .map_err(read_error)?
+                .ok_or_else(|| request_error(ErrorKind::TimedOut.into(), "list windows"))?;
+
+            if let Event::WindowsChanged { windows } = event {
+                return Ok(Self {
+                    connection,
+                    open: windows.into_iter().map(|w| (w.id, window(w))).collect(),
+                });
+            }
+        }
+    }
+}
+
+impl Events for NiriEvents {
+    fn next(&mut self, timeout: Option<Duration>) -> Result<Option<WindowEvent>> {
+        let deadline = timeout.map(|timeout| Instant::now() + timeout);
+        loop {
+            let event = match deadline {
+                Some(deadline) => self
+                    .connection
+                    .read_event(deadline.saturating_duration_since(Instant::now())),
+                None => self.connection.next_event().map(Some),
+            }
+            .map_err(read_error)?;
+
+            match event {
+                None => return Ok(None),
+                Some(Event::WindowsChanged { windows }) => {
+                    self.open = windows.into_iter().map(|w| (w.id, window(w))).collect();
+                }
+                Some(Event::WindowOpenedOrChanged { window: changed }) => {
+                    let id = changed.id;
+                    let changed = window(changed);
+                    if self.open.insert(id, changed.clone()).is_none() {
+                        return Ok(Some(WindowEvent::Opened(changed)));
+                    }
+                }
+                Some(Event::WindowClosed { id }) => {
+                    if let Some(closed) = self.open.remove(&id) {
+                        return Ok(Some(WindowEvent::Closed(closed)));
+                    }
+                }
+                Some(_) => {}
+            }
+        }
+    }
+}
+
+fn window(window: niri_ipc::Window) -> Window {
+    Window {
+        id: window.id.to_string(),
+        app_id: window.app_id.unwrap_or_default(),
+        title: window.title.unwrap_or_default(),
+    }
+}
+
+/// Workspaces are named in the config, or given by their index when the name is a number
+fn workspace_reference(workspace: &str) -> WorkspaceReferenceArg {
+    match workspace.parse::<u8>() {
+        Ok(index) => WorkspaceReferenceArg::Index(index),
+        Err(_) => WorkspaceReferenceArg::Name(workspace.to_string()),
+    }
+}
+
+fn unexpected_response() -> LauncherError {
+    LauncherError::NiriRequest("Unexpected response type".to_string())
+}
+
+fn read_error(error: std::io::Error) -> LauncherError {
+    LauncherError::NiriRequest(format!("Failed to read event: {}", error))
+}
+
+/// niri's socket speaks one JSON message per line. Unlike niri_ipc's socket,
+/// reads and writes give up after `TIMEOUT`, so a stuck compositor can't hang the launcher.
+pub struct Connection {
+    stream: BufReader<UnixStream>,
+}
+
+impl Connection {
+    pub fn open(path: &Path) -> Result<Self> {
+        let connect = || -> std::io::Result<UnixStream> {
+            let stream = UnixStream::connect(path)?;
+            stream.set_read_timeout(Some(TIMEOUT))?;
+            stream.set_write_timeout(Some(TIMEOUT))?;
+            Ok(stream)
+        };
+        let stream = connect()
+            .map_err(|e| LauncherError::NiriConnection(format!("Failed to connect: {}", e)))?;
+
+        Ok(Self {
+            stream: BufReader::new(stream),
+        })
+    }
+
+    pub fn send(&mut self, request: &Request) -> std::io::Result<Reply> {
+        let mut message = serde_json::to_string(request)?;
+        message.push('\n');
+        self.stream.get_mut().write_all(message.as_bytes())?;
+
+        let line = self.read_line()?;
+        Ok(serde_json::from_str(&line)?)
+    }
+
+    /// The next event after an `EventStream` request, waiting at most `timeout`.
+    /// None when it passed, the stream can't be read from after that.
+    pub fn read_event(&mut self, timeout: Duration) -> std::io::Result<Option<Event>> {
+        // A zero timeout would mean waiting forever
+        let timeout = timeout.max(Duration::from_millis(1));
+        self.stream.get_ref().set_read_timeout(Some(timeout))?;
+
+        match self.read_line() {
+            Ok(line) => Ok(Some(serde_json::from_str(&line)?)),
+            Err(e) if is_timeout(&e) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// The next event after an `EventStream` request, however long it takes,
+    /// for streams followed as long as the launcher runs
+    pub fn next_event(&mut self) -> std::io::Result<Event> {
+        self.stream.get_ref().set_read_timeout(None)?;
+        let line = self.read_line()?;
+        Ok(serde_json::from_str(&line)?)
+    }
+
+    fn read_line(&mut self) -> std::io::Result<String> {
+        let mut line = String::new();
+        if self.stream.read_line(&mut line)? == 0 {
+            return Err(ErrorKind::UnexpectedEof.into());
+        }
+        Ok(line)
+    }
+}
+
+/// A timeout becomes its own error, so callers can work around a stuck compositor
+pub fn request_error(error: std::io::Error, description: &str) -> LauncherError {
+    if is_timeout(&error) {
+        LauncherError::NiriTimeout(format!(
+            "No answer to {} within {} ms",
+            description,
+            TIMEOUT.as_millis()
+        ))
+    } else {
+        LauncherError::NiriRequest(format!("Failed to {}: {}", description, error))
+    }
+}
+
+fn is_timeout(error: &std::io::Error) -> bool {
+    matches!(error.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::mock::{self, MockNiri};
+    use super::*;
+
+    #[test]
+    fn window_scope_leaves_out_other_workspaces_and_outputs() {
+        let mut on_second = mock::window(2, "foot", "Terminal");
+        on_second["workspace_id"] = 2.into();
+        let mut on_other_output = mock::window(3, "mpv", "Video");
+        on_other_output["workspace_id"] = 3.into();
+        let niri = MockNiri::start(vec![
+            mock::window(1, "firefox", "Mozilla Firefox"),
+            on_second,
+            on_other_output,
+        ]);
+        niri.set_workspaces(vec![
+            mock::workspace(1, "DP-1", true),
+            mock::workspace(2, "DP-1", false),
+            mock::workspace(3, "HDMI-A-1", false),
+        ]);
+
+        let client = niri.client();
+        let ids = |scope| {
+            client
+                .windows(scope)
+                .unwrap()
+                .into_iter()
+                .map(|window| window.id)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(ids(WindowScope::All), ["1", "2", "3"]);
+        assert_eq!(ids(WindowScope::Workspace), ["1"]);
+        assert_eq!(ids(WindowScope::Output), ["1", "2"]);
+    }
+
+    #[test]
+    fn window_rules_become_actions_on_the_window() {
+        let niri = MockNiri::start(vec![]);
+        let rules = WindowRules {
+            floating: Some(true),
+            workspace: Some("mail".to_string()),
+            column_width: Some("50%".to_string()),
+        };
+
+        niri.client().apply_window_rules("7", &rules).unwrap();
+
+        assert!(matches!(
+            niri.actions().as_slice(),
+            [
+                Action::MoveWindowToFloating { id: Some(7) },
+                Action::MoveWindowToWorkspace {
+                    window_id: Some(7),
+                    reference: WorkspaceReferenceArg::Name(name),
+                    focus: false,
+                },
+                Action::SetWindowWidth {
+                    id: Some(7),
+                    change: SizeChange::SetProportion(_),
+                },
+            ] if name == "mail"
+        ));
+    }
+
+    #[test]
+    fn events_report_windows_opened_and_closed_after_subscribing() {
+        let niri = MockNiri::start(vec![mock::window(1, "foot", "Terminal")]);
+        let client = niri.client();
+
+        let mut events = client.events().unwrap();
+        client.spawn(&["mpv".to_string()], "spawn").unwrap();
+        client.close_window("1").unwrap();
+
+        let timeout = Some(Duration::from_secs(5));
+        let opened = mock::FIRST_SPAWNED_ID.to_string();
+        assert!(matches!(
+            events.next(timeout).unwrap(),
+            Some(WindowEvent::Opened(Window { id, app_id, .. })) if id == opened && app_id == "mpv"
+        ));
+        // Closing only names the window, the app ID is remembered from before
+        assert!(matches!(
+            events.next(timeout).unwrap(),
+            Some(WindowEvent::Closed(Window { id, app_id, .. })) if id == "1" && app_id == "foot"
+        ));
+    }
+}
@@ -0,0 +1,192 @@
+//! A stand-in for niri's IPC socket, so the niri backend can be tested
+//! without a running niri
+
+use super::Niri;
+use niri_ipc::{Action, Request};
+use serde_json::{Value, json};
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+
+static SOCKETS: AtomicUsize = AtomicUsize::new(0);
+/// The first window opened by a spawn request gets this ID, later ones count up from it
+pub const FIRST_SPAWNED_ID: u64 = 1000;
+
+#[derive(Default)]
+struct Compositor {
+    windows: Vec<Value>,
+    workspaces: Vec<Value>,
+    requests: Vec<Request>,
+    spawned: u64,
+    /// Connections that asked for the event stream
+    streams: Vec<UnixStream>,
+}
+
+pub struct MockNiri {
+    path: PathBuf,
+    compositor: Arc<Mutex<Compositor>>,
+}
+
+impl MockNiri {
+    /// Listen on a new socket with these windows open
+    pub fn start(windows: Vec<Value>) -> Self {
+        let path = std::env::temp_dir().join(format!(
+            "adwlauncher-mock-niri-{}-{}.sock",
+            std::process::id(),
+            SOCKETS.fetch_add(1, Ordering::Relaxed)
+        ));
+        let _ = std::fs::remove_file(&path);
+        let listener = UnixListener::bind(&path).expect("Failed to bind the mock niri socket");
+
+        let compositor = Arc::new(Mutex::new(Compositor {
+            windows,
+            ..Default::default()
+        }));
+        let shared = compositor.clone();
+        std::thread::spawn(move || {
+            for stream in listener.incoming().map_while(Result::ok) {
+                let compositor = shared.clone();
+                std::thread::spawn(move || serve(stream, &compositor));
+            }
+        });
+
+        Self { path, compositor }
+    }
+
+    /// A backend talking to this socket
+    pub fn client(&self) -> Niri {
+        Niri::new(&self.path)
+    }
+
+    /// Report these workspaces, the windows say which one they are on
+    pub fn set_workspaces(&self, workspaces: Vec<Value>) {
+        lock(&self.compositor).workspaces = workspaces;
+    }
+
+    /// Every request received so far, in order
+    pub fn requests(&self) -> Vec<Request> {
+        lock(&self.compositor).requests.clone()
+    }
+
+    /// Only the actions among the requests
+    pub fn actions(&self) -> Vec<Action> {
+        self.requests()
+            .into_iter()
+            .filter_map(|request| match request {
+                Request::Action(action) => Some(action),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+impl Drop for MockNiri {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// A window as niri reports it, in its IPC JSON form
+pub fn window(id: u64, app_id: &str, title: &str) -> Value {
+    json!({
+        "id": id,
+        "title": title,
+        "app_id": app_id,
+        "pid": null,
+        "workspace_id": 1,
+        "is_focused": false,
+        "is_floating": false,
+        "is_urgent": false,
+        "layout": {
+            "pos_in_scrolling_layout": [1, 1],
+            "tile_size": [800.0, 600.0],
+            "window_size": [800, 600],
+            "tile_pos_in_workspace_view": null,
+            "window_offset_in_tile": [0.0, 0.0],
+        },
+        "focus_timestamp": null,
+    })
+}
+
+/// A workspace as niri reports it, in its IPC JSON form
+pub fn workspace(id: u64, output: &str, focused: bool) -> Value {
+    json!({
+        "id": id,
+        "idx": id,
+        "name": null,
+        "output": output,
+        "is_urgent": false,
+        "is_active": focused,
+        "is_focused": focused,
+        "active_window_id": null,
+    })
+}
+
+fn lock(compositor: &Mutex<Compositor>) -> MutexGuard<'_, Compositor> {
+    compositor.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Answer a connection's request with one JSON line and close it like niri does,
+/// unless it asked for the event stream
+fn serve(stream: UnixStream, compositor: &Mutex<Compositor>) {
+    let Ok(mut writer) = stream.try_clone() else {
+        return;
+    };
+
+    for line in BufReader::new(stream).lines().map_while(Result::ok) {
+        let Ok(request) = serde_json::from_str::<Request>(&line) else {
+            let _ = writeln!(writer, "{}", json!({ "Err": "Unknown request" }));
+            continue;
+        };
+
+        let mut compositor = lock(compositor);
+        compositor.requests.push(request.clone());
+        let reply = match &request {
+            Request::Version => json!({ "Ok": { "Version": "mock" } }),
+            Request::Windows => json!({ "Ok": { "Windows": compositor.windows } }),
+            Request::Workspaces => json!({ "Ok": { "Workspaces": compositor.workspaces } }),
+            Request::EventStream => {
+                let _ = writeln!(writer, "{}", json!({ "Ok": "Handled" }));
+                let _ = writeln!(
+                    writer,
+                    "{}",
+                    json!({ "WindowsChanged": { "windows": compositor.windows } })
+                );
+                if let Ok(stream) = writer.try_clone() {
+                    compositor.streams.push(stream);
+                }
+                continue;
+            }
+            Request::Action(Action::Spawn { command }) => {
+                // The spawned app opens a window right away
+                let id = FIRST_SPAWNED_ID + compositor.spawned;
+                compositor.spawned += 1;
+                let app_id = command.last().cloned().unwrap_or_default();
+                let opened = window(id, &app_id, &app_id);
+                let event = json!({ "WindowOpenedOrChanged": { "window": opened } });
+                for stream in &mut compositor.streams {
+                    let _ = writeln!(stream, "{}", event);
+                }
+                compositor.windows.push(opened);
+                json!({ "Ok": "Handled" })
+            }
+            Request::Action(Action::CloseWindow { id: Some(id) }) => {
+                let id = *id;
+                compositor.windows.retain(|window| window["id"] != id);
+                let event = json!({ "WindowClosed": { "id": id } });
+                for stream in &mut compositor.streams {
+                    let _ = writeln!(stream, "{}", event);
+                }
+                json!({ "Ok": "Handled" })
+            }
+            Request::Action(_) => json!({ "Ok": "Handled" }),
+            _ => json!({ "Err": "Not mocked" }),
+        };
+        drop(compositor);
+
+        let _ = writeln!(writer, "{}", reply);
+        return;
+    }
+}
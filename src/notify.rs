@@ -0,0 +1,56 @@
+use relm4::gtk::gio;
+use relm4::gtk::glib::Variant;
+use relm4::gtk::glib::variant::ToVariant;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// Turn desktop notifications on or off, normally from the `notifications` config option
+pub fn set_enabled(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+/// Report a problem through org.freedesktop.Notifications when notifications are enabled.
+/// Failures to notify are only logged, there's nowhere else to report them.
+pub fn notify(summary: &str, body: &str) {
+    if !ENABLED.load(Ordering::Relaxed) {
+        return;
+    }
+
+    if let Err(e) = send(summary, body) {
+        eprintln!("Failed to send notification: {}", e);
+    }
+}
+
+fn send(summary: &str, body: &str) -> Result<(), gio::glib::Error> {
+    let connection = gio::bus_get_sync(gio::BusType::Session, None::<&gio::Cancellable>)?;
+
+    let actions: Vec<String> = vec![];
+    let hints: HashMap<String, Variant> = HashMap::new();
+    let parameters = (
+        "adwlauncher",
+        0u32,
+        "system-search",
+        summary,
+        body,
+        actions,
+        hints,
+        -1i32,
+    )
+        .to_variant();
+
+    connection.call_sync(
+        Some("org.freedesktop.Notifications"),
+        "/org/freedesktop/Notifications",
+        "org.freedesktop.Notifications",
+        "Notify",
+        Some(&parameters),
+        None,
+        gio::DBusCallFlags::NONE,
+        -1,
+        None::<&gio::Cancellable>,
+    )?;
+
+    Ok(())
+}
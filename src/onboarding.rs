@@ -0,0 +1,150 @@
+use crate::config::{Config, Position};
+use relm4::adw::prelude::*;
+use relm4::prelude::*;
+
+/// First-run dialog that collects the basic settings and writes the initial config
+#[derive(Debug)]
+pub struct Onboarding {
+    config: Config,
+}
+
+#[derive(Debug)]
+pub enum OnboardingMsg {
+    TerminalChanged(String),
+    PositionChanged(u32),
+    WidthChanged(f64),
+    HeightChanged(f64),
+    UsageTrackingChanged(bool),
+    Finish,
+}
+
+#[derive(Debug)]
+pub enum OnboardingOutput {
+    Finished(Config),
+}
+
+#[relm4::component(pub)]
+impl SimpleComponent for Onboarding {
+    type Input = OnboardingMsg;
+    type Output = OnboardingOutput;
+    type Init = Config;
+
+    view! {
+        #[root]
+        adw::Dialog {
+            set_title: "Welcome to AdwLauncher",
+            set_content_width: 420,
+            set_can_close: false,
+
+            #[wrap(Some)]
+            set_child = &adw::ToolbarView {
+                add_top_bar = &adw::HeaderBar {
+                    set_show_end_title_buttons: false,
+                },
+
+                #[wrap(Some)]
+                set_content = &adw::PreferencesPage {
+                    adw::PreferencesGroup {
+                        set_title: "Launching",
+                        set_description: Some("Used for applications that need a terminal"),
+
+                        adw::EntryRow {
+                            set_title: "Terminal command",
+                            set_text: &model.config.terminal,
+                            connect_changed[sender] => move |row| {
+                                sender.input(OnboardingMsg::TerminalChanged(row.text().to_string()));
+                            },
+                        },
+                    },
+
+                    adw::PreferencesGroup {
+                        set_title: "Window",
+
+                        adw::ComboRow {
+                            set_title: "Position",
+                            set_model: Some(&gtk::StringList::new(
+                                &Position::ALL.iter().map(|p| p.label()).collect::<Vec<_>>(),
+                            )),
+                            set_selected: Position::ALL
+                                .iter()
+                                .position(|p| *p == model.config.position)
+                                .unwrap_or(0) as u32,
+                            connect_selected_notify[sender] => move |row| {
+                                sender.input(OnboardingMsg::PositionChanged(row.selected()));
+                            },
+                        },
+
+                        adw::SpinRow::with_range(320.0, 3840.0, 10.0) {
+                            set_title: "Width",
+                            set_value: model.config.width as f64,
+                            connect_value_notify[sender] => move |row| {
+                                sender.input(OnboardingMsg::WidthChanged(row.value()));
+                            },
+                        },
+
+                        adw::SpinRow::with_range(200.0, 2160.0, 10.0) {
+                            set_title: "Height",
+                            set_value: model.config.height as f64,
+                            connect_value_notify[sender] => move |row| {
+                                sender.input(OnboardingMsg::HeightChanged(row.value()));
+                            },
+                        },
+                    },
+
+                    adw::PreferencesGroup {
+                        set_title: "Privacy",
+
+                        adw::SwitchRow {
+                            set_title: "Track usage",
+                            set_subtitle: "Rank frequently and recently used apps higher",
+                            set_active: model.config.usage_tracking,
+                            connect_active_notify[sender] => move |row| {
+                                sender.input(OnboardingMsg::UsageTrackingChanged(row.is_active()));
+                            },
+                        },
+                    },
+
+                    adw::PreferencesGroup {
+                        gtk::Button {
+                            set_label: "Get Started",
+                            set_css_classes: &["pill", "suggested-action"],
+                            set_halign: gtk::Align::Center,
+                            connect_clicked => OnboardingMsg::Finish,
+                        },
+                    },
+                },
+            },
+        }
+    }
+
+    fn init(
+        config: Self::Init,
+        root: Self::Root,
+        sender: ComponentSender<Self>,
+    ) -> ComponentParts<Self> {
+        let model = Onboarding { config };
+        let widgets = view_output!();
+
+        ComponentParts { model, widgets }
+    }
+
+    fn update(&mut self, msg: OnboardingMsg, sender: ComponentSender<Self>) {
+        match msg {
+            OnboardingMsg::TerminalChanged(terminal) => self.config.terminal = terminal,
+            OnboardingMsg::PositionChanged(index) => {
+                if let Some(position) = Position::ALL.get(index as usize) {
+                    self.config.position = *position;
+                }
+            }
+            OnboardingMsg::WidthChanged(width) => self.config.width = width as i32,
+            OnboardingMsg::HeightChanged(height) => self.config.height = height as i32,
+            OnboardingMsg::UsageTrackingChanged(enabled) => self.config.usage_tracking = enabled,
+            OnboardingMsg::Finish => {
+                if let Err(e) = self.config.save() {
+                    eprintln!("Failed to save config: {:#}", e);
+                }
+                let _ = sender.output(OnboardingOutput::Finished(self.config.clone()));
+            }
+        }
+    }
+}
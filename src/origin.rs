@@ -0,0 +1,69 @@
+use crate::app_discovery;
+use crate::types::{Entry, OpenType};
+
+/// Where an entry comes from, when that isn't an ordinary installed app
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Origin {
+    Flatpak,
+    Snap,
+    /// A Windows program run through Wine or Bottles
+    Wine,
+    /// An app exported from a Distrobox or Toolbx container
+    Container,
+    /// An open window rather than the app itself
+    Window,
+}
+
+impl Origin {
+    pub fn of(entry: &Entry) -> Option<Self> {
+        match entry.open_type {
+            OpenType::Window => Some(Origin::Window),
+            OpenType::Graphical | OpenType::Terminal => {
+                if is_wine(entry) {
+                    Some(Origin::Wine)
+                } else if app_discovery::flatpak_app_id(entry).is_some() {
+                    Some(Origin::Flatpak)
+                } else if entry.exec.contains("/snap/bin/") {
+                    Some(Origin::Snap)
+                } else if entry.exec.contains("distrobox-enter")
+                    || entry.exec.contains("toolbox run")
+                {
+                    Some(Origin::Container)
+                } else {
+                    None
+                }
+            }
+            _ => None,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Origin::Flatpak => "Flatpak",
+            Origin::Snap => "Snap",
+            Origin::Wine => "Windows program",
+            Origin::Container => "Container",
+            Origin::Window => "Open window",
+        }
+    }
+
+    pub fn icon_name(&self) -> &'static str {
+        match self {
+            Origin::Flatpak => "package-x-generic-symbolic",
+            Origin::Snap => "application-x-addon-symbolic",
+            Origin::Wine => "computer-symbolic",
+            Origin::Container => "network-server-symbolic",
+            Origin::Window => "focus-windows-symbolic",
+        }
+    }
+}
+
+/// Programs found in Wine prefixes and bottles, or desktop files Wine wrote itself
+fn is_wine(entry: &Entry) -> bool {
+    entry.id.starts_with("wine-")
+        || entry.id.starts_with("bottles-")
+        || entry
+            .exec
+            .split_whitespace()
+            .any(|word| matches!(word.rsplit('/').next(), Some("wine" | "wine64")))
+}
@@ -0,0 +1,100 @@
+use crate::error::{LauncherError, Result};
+use crate::profile;
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+/// Only the start of a text file is shown
+const TEXT_BYTES: usize = 4096;
+const TEXT_LINES: usize = 40;
+/// Longest side of a rendered PDF page, in pixels
+const PDF_PAGE_SIZE: u32 = 512;
+/// Formats GTK's image loaders read without extra plugins
+const IMAGE_EXTENSIONS: &[&str] = &[
+    "png", "jpg", "jpeg", "gif", "webp", "svg", "bmp", "tif", "tiff", "ico",
+];
+
+/// What the preview pane shows for a file
+#[derive(Debug)]
+pub enum Preview {
+    /// An image to draw, for PDFs a rendering of the first page
+    Image(PathBuf),
+    /// The first lines of a text file
+    Text(String),
+    /// Binary files, directories, and PDFs without poppler's pdftoppm
+    Unsupported,
+}
+
+/// Look at a file without opening it in an app. PDFs are rendered with
+/// pdftoppm from poppler-utils when it is installed.
+pub fn load(path: &Path) -> Result<Preview> {
+    if path.is_dir() {
+        return Ok(Preview::Unsupported);
+    }
+
+    let extension = path
+        .extension()
+        .map(|extension| extension.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    if IMAGE_EXTENSIONS.contains(&extension.as_str()) {
+        return Ok(Preview::Image(path.to_path_buf()));
+    }
+    if extension == "pdf" {
+        return render_pdf(path);
+    }
+
+    let mut head = Vec::with_capacity(TEXT_BYTES);
+    File::open(path)?
+        .take(TEXT_BYTES as u64)
+        .read_to_end(&mut head)?;
+    Ok(text_head(&head).map_or(Preview::Unsupported, Preview::Text))
+}
+
+/// The first lines, or None for binary data. The cut at `TEXT_BYTES` may
+/// split a character, which is dropped rather than taken for binary.
+fn text_head(head: &[u8]) -> Option<String> {
+    if head.contains(&0) {
+        return None;
+    }
+    let text = match std::str::from_utf8(head) {
+        Ok(text) => text,
+        Err(e) if e.error_len().is_none() => std::str::from_utf8(&head[..e.valid_up_to()]).ok()?,
+        Err(_) => return None,
+    };
+    Some(text.lines().take(TEXT_LINES).collect::<Vec<_>>().join("\n"))
+}
+
+fn render_pdf(path: &Path) -> Result<Preview> {
+    let cache_dir = dirs::cache_dir().ok_or_else(|| {
+        LauncherError::Io(std::io::Error::new(
+            std::io::ErrorKind::NotFound,
+            "Could not find cache directory",
+        ))
+    })?;
+    let dir = cache_dir.join(profile::directory());
+    std::fs::create_dir_all(&dir)?;
+    // pdftoppm adds the extension itself
+    let output = dir.join("preview");
+
+    let status = Command::new("pdftoppm")
+        .args(["-png", "-singlefile", "-f", "1", "-l", "1", "-scale-to"])
+        .arg(PDF_PAGE_SIZE.to_string())
+        .arg(path)
+        .arg(&output)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status();
+
+    match status {
+        Ok(status) if status.success() => Ok(Preview::Image(output.with_extension("png"))),
+        Ok(status) => Err(LauncherError::Io(std::io::Error::other(format!(
+            "pdftoppm failed for {}: {}",
+            path.display(),
+            status
+        )))),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Preview::Unsupported),
+        Err(e) => Err(e.into()),
+    }
+}
@@ -0,0 +1,45 @@
+use crate::error::{LauncherError, Result};
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+const APPLICATION_ID: &str = "me.bofusland.adwlauncher";
+
+static PROFILE: OnceLock<String> = OnceLock::new();
+
+/// Use a profile's own config, usage data and state for the rest of the run,
+/// normally from --profile. Must be called before any of them is read.
+pub fn set(name: &str) -> Result<()> {
+    let valid = !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if !valid {
+        return Err(LauncherError::Config(format!(
+            "Invalid profile name {:?}, use letters, digits, - and _",
+            name
+        )));
+    }
+
+    PROFILE
+        .set(name.to_string())
+        .map_err(|_| LauncherError::Config("The profile was already chosen".to_string()))
+}
+
+/// Directory below the config, cache and state directories holding the
+/// profile's files, the default profile uses the top level one
+pub fn directory() -> PathBuf {
+    let base = PathBuf::from("adwlauncher");
+    match PROFILE.get() {
+        Some(name) => base.join("profiles").join(name),
+        None => base,
+    }
+}
+
+/// Each profile runs its own service, so they can be shown independently
+pub fn application_id() -> String {
+    match PROFILE.get() {
+        // D-Bus name elements can't start with a digit
+        Some(name) => format!("{}.profile_{}", APPLICATION_ID, name.replace('-', "_")),
+        None => APPLICATION_ID.to_string(),
+    }
+}
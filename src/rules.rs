@@ -0,0 +1,192 @@
+use crate::config::{EntryRule, RuleAction};
+use crate::types::Entry;
+use relm4::gtk::glib;
+use std::collections::HashSet;
+
+const DAYS: [&str; 7] = ["mon", "tue", "wed", "thu", "fri", "sat", "sun"];
+
+/// A local weekday (0 for Monday) and minute of the day
+pub type Time = (usize, u32);
+
+/// The local time right now
+pub fn now() -> Option<Time> {
+    let now = glib::DateTime::now_local().ok()?;
+    let day = usize::try_from(now.day_of_week() - 1).ok()?;
+    let minute = u32::try_from(now.hour() * 60 + now.minute()).ok()?;
+    Some((day, minute))
+}
+
+/// Minutes since midnight for "HH:MM", "24:00" being the end of the day
+fn parse_time(time: &str) -> Option<u32> {
+    let (hours, minutes) = time.trim().split_once(':')?;
+    let (hours, minutes): (u32, u32) = (hours.parse().ok()?, minutes.parse().ok()?);
+    let valid = (hours < 24 && minutes < 60) || (hours == 24 && minutes == 0);
+    valid.then_some(hours * 60 + minutes)
+}
+
+impl EntryRule {
+    fn is_active(&self, (day, minute): Time) -> bool {
+        // Without times the rule lasts the whole day
+        let from = if self.from.is_empty() {
+            Some(0)
+        } else {
+            parse_time(&self.from)
+        };
+        let to = if self.to.is_empty() {
+            Some(24 * 60)
+        } else {
+            parse_time(&self.to)
+        };
+        let (Some(from), Some(to)) = (from, to) else {
+            eprintln!(
+                "Ignoring rule with invalid times {:?}–{:?}",
+                self.from, self.to
+            );
+            return false;
+        };
+
+        if from <= to {
+            self.is_on(day) && (from..to).contains(&minute)
+        } else if minute >= from {
+            // e.g. 22:00 to 06:00 spans midnight
+            self.is_on(day)
+        } else {
+            // After midnight the rule still belongs to the day it started on
+            minute < to && self.is_on((day + DAYS.len() - 1) % DAYS.len())
+        }
+    }
+
+    fn is_on(&self, day: usize) -> bool {
+        self.days.is_empty()
+            || self.days.iter().any(|d| {
+                d.get(..3)
+                    .is_some_and(|d| d.eq_ignore_ascii_case(DAYS[day]))
+            })
+    }
+
+    fn applies_to(&self, entry: &Entry) -> bool {
+        self.apps.contains(&entry.id)
+    }
+}
+
+/// Desktop IDs that a rule active at `now` hides
+pub fn hidden(rules: &[EntryRule], now: Time) -> HashSet<String> {
+    rules
+        .iter()
+        .filter(|rule| rule.action == RuleAction::Hide && rule.is_active(now))
+        .flat_map(|rule| rule.apps.iter().cloned())
+        .collect()
+}
+
+/// Hide or move to the end the ranked entries that a rule active at `now` matches
+pub fn apply(rules: &[EntryRule], now: Time, ranked: &mut Vec<(f64, Entry)>) {
+    if rules.is_empty() {
+        return;
+    }
+
+    let active: Vec<&EntryRule> = rules.iter().filter(|rule| rule.is_active(now)).collect();
+    let action = |entry: &Entry| {
+        active
+            .iter()
+            .filter(|rule| rule.applies_to(entry))
+            .map(|rule| rule.action)
+            // Hiding wins over demoting
+            .min_by_key(|action| *action != RuleAction::Hide)
+    };
+
+    ranked.retain(|(_, entry)| action(entry) != Some(RuleAction::Hide));
+    // Stable, so demoted entries keep their order among themselves
+    ranked.sort_by_key(|(_, entry)| action(entry) == Some(RuleAction::Demote));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MON: usize = 0;
+    const FRI: usize = 4;
+    const SAT: usize = 5;
+    const SUN: usize = 6;
+
+    fn rule(days: &[&str], from: &str, to: &str, action: RuleAction) -> EntryRule {
+        EntryRule {
+            apps: vec!["steam.desktop".to_string()],
+            days: days.iter().map(|day| day.to_string()).collect(),
+            from: from.to_string(),
+            to: to.to_string(),
+            action,
+        }
+    }
+
+    fn at(hours: u32, minutes: u32) -> u32 {
+        hours * 60 + minutes
+    }
+
+    #[test]
+    fn daytime_rules_end_before_their_end_time() {
+        let work = rule(&["mon", "Tuesday"], "09:00", "17:00", RuleAction::Hide);
+        assert!(work.is_active((MON, at(9, 0))));
+        assert!(work.is_active((1, at(16, 59))));
+        assert!(!work.is_active((MON, at(17, 0))));
+        assert!(!work.is_active((MON, at(8, 59))));
+        assert!(!work.is_active((2, at(12, 0))));
+    }
+
+    #[test]
+    fn overnight_rules_cover_the_morning_after() {
+        let night = rule(&["fri"], "22:00", "06:00", RuleAction::Hide);
+        assert!(night.is_active((FRI, at(23, 0))));
+        assert!(night.is_active((SAT, at(2, 0))));
+        assert!(!night.is_active((SAT, at(6, 0))));
+        assert!(!night.is_active((FRI, at(2, 0))));
+        assert!(!night.is_active((SAT, at(23, 0))));
+
+        // Sunday night ends on Monday morning
+        let sunday = rule(&["sun"], "22:00", "06:00", RuleAction::Hide);
+        assert!(sunday.is_active((MON, at(5, 59))));
+        assert!(!sunday.is_active((SUN, at(5, 59))));
+    }
+
+    #[test]
+    fn rules_without_times_last_all_day() {
+        let weekend = rule(&["sat", "sun"], "", "", RuleAction::Hide);
+        assert!(weekend.is_active((SAT, 0)));
+        assert!(weekend.is_active((SUN, at(23, 59))));
+        assert!(!weekend.is_active((MON, at(12, 0))));
+    }
+
+    #[test]
+    fn times_are_checked() {
+        assert_eq!(parse_time("07:30"), Some(at(7, 30)));
+        assert_eq!(parse_time(" 24:00 "), Some(at(24, 0)));
+        assert_eq!(parse_time("24:59"), None);
+        assert_eq!(parse_time("12:60"), None);
+        assert_eq!(parse_time("noon"), None);
+        assert!(!rule(&[], "08:00", "25:00", RuleAction::Hide).is_active((MON, at(9, 0))));
+    }
+
+    #[test]
+    fn hiding_wins_over_demoting() {
+        let entry = |id: &str| Entry {
+            id: id.to_string(),
+            name: id.to_string(),
+            ..Default::default()
+        };
+        let mut ranked = vec![
+            (3.0, entry("steam.desktop")),
+            (2.0, entry("lutris.desktop")),
+            (1.0, entry("firefox.desktop")),
+        ];
+        let mut demote = rule(&[], "", "", RuleAction::Demote);
+        demote.apps.push("lutris.desktop".to_string());
+        let rules = [demote, rule(&[], "", "", RuleAction::Hide)];
+
+        apply(&rules, (MON, at(12, 0)), &mut ranked);
+        let ids: Vec<&str> = ranked.iter().map(|(_, entry)| entry.id.as_str()).collect();
+        assert_eq!(ids, ["firefox.desktop", "lutris.desktop"]);
+        assert_eq!(
+            hidden(&rules, (MON, at(12, 0))),
+            HashSet::from(["steam.desktop".to_string()])
+        );
+    }
+}
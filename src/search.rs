@@ -0,0 +1,562 @@
+use crate::config::{Config, FieldWeights};
+use crate::types::Entry;
+use crate::usage::UsageTracker;
+use fuzzy_matcher::FuzzyMatcher;
+use fuzzy_matcher::skim::SkimMatcherV2;
+use std::borrow::Cow;
+use unicode_normalization::UnicodeNormalization;
+use unicode_normalization::char::is_combining_mark;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct SearchOptions {
+    /// Ignore case even when the query contains capitals (smart case otherwise)
+    pub ignore_case: bool,
+    /// Match accented letters by their base letter, so "uber" finds "Über"
+    pub fold_diacritics: bool,
+    /// How much matching the generic name, keywords and comment counts next to the name
+    pub weights: FieldWeights,
+}
+
+impl SearchOptions {
+    pub fn from_config(config: &Config) -> Self {
+        Self {
+            ignore_case: !config.smart_case,
+            fold_diacritics: config.fold_diacritics,
+            weights: config.search_weights,
+        }
+    }
+
+    fn normalize<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        if self.fold_diacritics && !text.is_ascii() {
+            Cow::Owned(text.nfd().filter(|c| !is_combining_mark(*c)).collect())
+        } else {
+            Cow::Borrowed(text)
+        }
+    }
+}
+
+/// A query split into words that must all match, and `-words` whose entries are dropped
+struct ParsedQuery {
+    include: Vec<String>,
+    /// Lowercased, matched as plain substrings so exclusions stay predictable
+    exclude: Vec<String>,
+}
+
+impl ParsedQuery {
+    fn parse(query: &str) -> Self {
+        let mut include = vec![];
+        let mut exclude = vec![];
+
+        for word in query.split_whitespace() {
+            match word.strip_prefix('-') {
+                Some(term) if !term.is_empty() => exclude.push(term.to_lowercase()),
+                // A lone "-" is most likely part of a name being typed
+                _ => include.push(word.to_string()),
+            }
+        }
+
+        Self { include, exclude }
+    }
+
+    fn is_excluded(&self, name: &str) -> bool {
+        let name = name.to_lowercase();
+        self.exclude.iter().any(|term| name.contains(term.as_str()))
+    }
+}
+
+/// The entries the previous query matched, so a query extending it only
+/// scores those again instead of every entry, like fzf does
+#[derive(Debug, Default)]
+pub struct RankCache {
+    query: String,
+    options: SearchOptions,
+    /// Length of the entries the indices point into
+    entries_len: usize,
+    matched: Option<Vec<usize>>,
+}
+
+impl RankCache {
+    /// Forget the matches, needed whenever the ranked entries change
+    pub fn clear(&mut self) {
+        self.matched = None;
+    }
+
+    /// The previous matches when every entry matching `query` is among them
+    fn pool(&self, entries: &[Entry], query: &str, options: SearchOptions) -> Option<&[usize]> {
+        let matched = self.matched.as_deref()?;
+        if self.options != options
+            || self.entries_len != entries.len()
+            || !query.starts_with(&self.query)
+        {
+            return None;
+        }
+
+        // Extending a `-word` excludes less, so it can match more entries than before.
+        // Extending any other word or adding words only ever narrows the matches.
+        let extends_exclusion = !self.query.ends_with(char::is_whitespace)
+            && self
+                .query
+                .split_whitespace()
+                .next_back()
+                .is_some_and(|word| word.starts_with('-'));
+        (!extends_exclusion).then_some(matched)
+    }
+}
+
+/// How much the usage boost, between 0 and 1, can add to the fuzzy score
+const USAGE_WEIGHT: f64 = 0.5;
+
+/// Filter and rank entries for a query, highest score first.
+/// Every word of the query has to match and `-word` excludes entries containing it.
+/// A query without words to match keeps every entry and sorts by usage alone.
+pub fn rank_entries(
+    entries: &[Entry],
+    query: &str,
+    usage: &UsageTracker,
+    options: SearchOptions,
+) -> Vec<(f64, Entry)> {
+    score_entries(entries, 0..entries.len(), query, usage, options)
+        .into_iter()
+        .map(|(score, index)| (score, entries[index].clone()))
+        .collect()
+}
+
+/// Like `rank_entries`, reusing the matches of the previous query when this one extends it
+pub fn rank_entries_cached(
+    entries: &[Entry],
+    query: &str,
+    usage: &UsageTracker,
+    options: SearchOptions,
+    cache: &mut RankCache,
+) -> Vec<(f64, Entry)> {
+    let scored = match cache.pool(entries, query, options) {
+        Some(pool) => score_entries(entries, pool.iter().copied(), query, usage, options),
+        None => score_entries(entries, 0..entries.len(), query, usage, options),
+    };
+
+    // In the original order, so ties are ordered like a full scan orders them
+    let mut matched: Vec<usize> = scored.iter().map(|(_, index)| *index).collect();
+    matched.sort_unstable();
+    *cache = RankCache {
+        query: query.to_string(),
+        options,
+        entries_len: entries.len(),
+        matched: Some(matched),
+    };
+
+    scored
+        .into_iter()
+        .map(|(score, index)| (score, entries[index].clone()))
+        .collect()
+}
+
+/// Scores of the entries at `indices` that match the query, highest first
+fn score_entries(
+    entries: &[Entry],
+    indices: impl Iterator<Item = usize>,
+    query: &str,
+    usage: &UsageTracker,
+    options: SearchOptions,
+) -> Vec<(f64, usize)> {
+    let query = ParsedQuery::parse(&options.normalize(query));
+    let entries = indices
+        .filter_map(|index| Some((index, entries.get(index)?)))
+        .filter(|(_, entry)| !query.is_excluded(&options.normalize(&entry.name)));
+
+    let mut scored_entries: Vec<(f64, usize)> = if query.include.is_empty() {
+        // When no search query, sort by recent usage
+        entries
+            .map(|(index, entry)| (usage.calculate_boost(&entry.name), index))
+            .collect()
+    } else {
+        // Use fuzzy matching to filter entries
+        let matcher = matcher(options);
+        entries
+            .filter_map(|(index, entry)| {
+                let fields = weighted_fields(entry, options);
+                // All words must match, in any order, each in whichever field it matches best
+                let fuzzy_score: f64 = query
+                    .include
+                    .iter()
+                    .map(|word| {
+                        fields
+                            .iter()
+                            .filter_map(|(text, weight)| {
+                                Some(matcher.fuzzy_match(text, word)? as f64 * weight)
+                            })
+                            .max_by(f64::total_cmp)
+                    })
+                    .sum::<Option<f64>>()?;
+
+                // Calculate combined score with usage boost
+                let usage_boost = usage.calculate_boost(&entry.name);
+                // Fuzzy score is the primary factor, usage provides a boost
+                let combined_score = fuzzy_score * (1.0 + usage_boost * USAGE_WEIGHT);
+                Some((combined_score, index))
+            })
+            .collect()
+    };
+
+    // Sort by score (highest first)
+    scored_entries.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or(std::cmp::Ordering::Equal));
+
+    scored_entries
+}
+
+/// The highest score a query can reach: every word is the whole of an entry's most
+/// weighted field, and the entry is the most used one. Scores divided by it
+/// compare across entry lists, however well the best match of each matched.
+pub fn best_score(query: &str, options: SearchOptions) -> f64 {
+    let query = ParsedQuery::parse(&options.normalize(query));
+    let matcher = matcher(options);
+    let weights = options.weights;
+    let weight = [
+        weights.name,
+        weights.generic_name,
+        weights.keywords,
+        weights.comment,
+    ]
+    .into_iter()
+    .fold(0.0, f64::max);
+
+    let fuzzy_score: f64 = query
+        .include
+        .iter()
+        .filter_map(|word| matcher.fuzzy_match(word, word))
+        .map(|score| score as f64 * weight)
+        .sum();
+    fuzzy_score * (1.0 + USAGE_WEIGHT)
+}
+
+fn matcher(options: SearchOptions) -> SkimMatcherV2 {
+    if options.ignore_case {
+        SkimMatcherV2::default().ignore_case()
+    } else {
+        SkimMatcherV2::default()
+    }
+}
+
+/// The texts an entry is found by with their weights, leaving out empty and
+/// disabled fields. Only apps have fields besides the name.
+fn weighted_fields(entry: &Entry, options: SearchOptions) -> Vec<(Cow<'_, str>, f64)> {
+    let weights = options.weights;
+    std::iter::once((entry.name.as_str(), weights.name))
+        .chain(std::iter::once((
+            entry.generic_name.as_str(),
+            weights.generic_name,
+        )))
+        .chain(
+            entry
+                .keywords
+                .iter()
+                .map(|keyword| (keyword.as_str(), weights.keywords)),
+        )
+        .chain(std::iter::once((entry.comment.as_str(), weights.comment)))
+        .filter(|(text, weight)| !text.is_empty() && *weight > 0.0)
+        .map(|(text, weight)| (options.normalize(text), weight))
+        .collect()
+}
+
+/// Most suggestions offered when a query matches nothing
+const MAX_SUGGESTIONS: usize = 3;
+
+/// Previously launched entries whose name is a few typos away from a query
+/// that matches nothing, closest first
+pub fn suggestions(
+    entries: &[Entry],
+    query: &str,
+    usage: &UsageTracker,
+    options: SearchOptions,
+) -> Vec<Entry> {
+    let query = options.normalize(query.trim()).to_lowercase();
+    if query.is_empty() {
+        return vec![];
+    }
+    // Roughly one typo for every three letters typed
+    let max_distance = (query.chars().count() / 3).max(1);
+
+    let mut candidates: Vec<(usize, f64, &Entry)> = entries
+        .iter()
+        .filter(|entry| usage.get_stats(&entry.name).is_some())
+        .filter_map(|entry| {
+            let name = options.normalize(&entry.name).to_lowercase();
+            // Words count on their own, so "blendr" still finds "Blender 4.2"
+            let distance = std::iter::once(name.as_str())
+                .chain(name.split_whitespace())
+                .map(|candidate| edit_distance(&query, candidate))
+                .min()?;
+            (distance <= max_distance)
+                .then(|| (distance, usage.calculate_boost(&entry.name), entry))
+        })
+        .collect();
+
+    // Closest first, more used first among equally close ones
+    candidates.sort_by(|a, b| {
+        a.0.cmp(&b.0)
+            .then(b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal))
+    });
+
+    candidates
+        .into_iter()
+        .take(MAX_SUGGESTIONS)
+        .map(|(_, _, entry)| entry.clone())
+        .collect()
+}
+
+/// Levenshtein distance, counted in characters
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+
+    for (i, a_char) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a_char != *b_char);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+
+    previous[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::OpenType;
+    use std::fmt::Write;
+    use std::path::Path;
+
+    const HOUR: u64 = 60 * 60;
+    const DAY: u64 = 24 * HOUR;
+
+    const NAMES: &[&str] = &[
+        "Firefox",
+        "Firefox Developer Edition",
+        "Files",
+        "File Roller",
+        "Fractal",
+        "Terminal",
+        "Text Editor",
+        "Thunderbird",
+        "Settings",
+        "System Monitor",
+        "Steam",
+        "Spotify",
+        "Über Writer",
+        "GNU Image Manipulation Program",
+        "LibreOffice Writer",
+        "LibreOffice Calc",
+    ];
+
+    const QUERIES: &[&str] = &[
+        "",
+        "f",
+        "fi",
+        "fire",
+        "firefox dev",
+        "firefox -dev",
+        "te",
+        "s",
+        "st",
+        "sys mon",
+        "uber",
+        "writer",
+        "office",
+        "gimp",
+        "Te",
+        "xyz",
+    ];
+
+    fn entries() -> Vec<Entry> {
+        NAMES
+            .iter()
+            .map(|name| Entry {
+                id: format!("{}.desktop", name.to_lowercase().replace(' ', "-")),
+                name: name.to_string(),
+                exec: name.to_lowercase(),
+                open_type: OpenType::Graphical,
+                ..Default::default()
+            })
+            .collect()
+    }
+
+    /// Launched often and recently, rarely, or long ago
+    fn usage() -> UsageTracker {
+        let mut usage = UsageTracker::new();
+        usage.insert("Firefox", 120, HOUR / 2);
+        usage.insert("Terminal", 300, 2 * HOUR);
+        usage.insert("Files", 15, 3 * DAY);
+        usage.insert("Steam", 40, 20 * DAY);
+        usage.insert("Text Editor", 2, 90 * DAY);
+        usage
+    }
+
+    fn names(ranked: &[(f64, Entry)]) -> Vec<&str> {
+        ranked
+            .iter()
+            .map(|(_, entry)| entry.name.as_str())
+            .collect()
+    }
+
+    fn render(options: SearchOptions) -> String {
+        let entries = entries();
+        let usage = usage();
+        let mut rendered = String::new();
+
+        for query in QUERIES {
+            let ranked = rank_entries(&entries, query, &usage, options);
+            writeln!(rendered, "> {}", query).unwrap();
+            for name in names(&ranked) {
+                writeln!(rendered, "{}", name).unwrap();
+            }
+            writeln!(rendered).unwrap();
+        }
+
+        rendered
+    }
+
+    /// Compare with the file in src/snapshots, which is only written when
+    /// UPDATE_SNAPSHOTS is set, e.g. after deliberately changing the weights
+    fn assert_snapshot(name: &str, actual: &str) {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("src/snapshots")
+            .join(name);
+
+        if std::env::var_os("UPDATE_SNAPSHOTS").is_some() {
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(&path, actual).unwrap();
+            return;
+        }
+
+        let expected = std::fs::read_to_string(&path).unwrap_or_else(|e| {
+            panic!(
+                "Can't read {}: {}, run with UPDATE_SNAPSHOTS=1 to create it",
+                path.display(),
+                e
+            )
+        });
+        assert!(
+            expected == actual,
+            "Ranking differs from {}, rerun with UPDATE_SNAPSHOTS=1 if that's intended\n\
+             --- expected\n{}\n--- actual\n{}",
+            path.display(),
+            expected,
+            actual
+        );
+    }
+
+    #[test]
+    fn ranking_matches_snapshot() {
+        let options = SearchOptions {
+            ignore_case: false,
+            fold_diacritics: true,
+            ..Default::default()
+        };
+        assert_snapshot("ranking.txt", &render(options));
+    }
+
+    #[test]
+    fn ranking_ignoring_case_matches_snapshot() {
+        let options = SearchOptions {
+            ignore_case: true,
+            fold_diacritics: false,
+            ..Default::default()
+        };
+        assert_snapshot("ranking_ignore_case.txt", &render(options));
+    }
+
+    #[test]
+    fn empty_query_orders_by_usage() {
+        let ranked = rank_entries(&entries(), "", &usage(), SearchOptions::default());
+        assert_eq!(
+            names(&ranked)[..5],
+            ["Terminal", "Firefox", "Files", "Steam", "Text Editor"]
+        );
+        assert_eq!(ranked.len(), NAMES.len());
+    }
+
+    #[test]
+    fn every_word_has_to_match() {
+        let ranked = rank_entries(&entries(), "libre calc", &usage(), SearchOptions::default());
+        assert_eq!(names(&ranked), ["LibreOffice Calc"]);
+    }
+
+    #[test]
+    fn excluded_words_drop_entries() {
+        let ranked = rank_entries(
+            &entries(),
+            "firefox -developer",
+            &usage(),
+            SearchOptions::default(),
+        );
+        assert_eq!(names(&ranked), ["Firefox"]);
+    }
+
+    #[test]
+    fn diacritics_are_folded_when_enabled() {
+        let entries = entries();
+        let folded = SearchOptions {
+            fold_diacritics: true,
+            ..Default::default()
+        };
+        let ranked = rank_entries(&entries, "uber", &usage(), folded);
+        assert_eq!(names(&ranked), ["Über Writer"]);
+
+        let ranked = rank_entries(&entries, "uber", &usage(), SearchOptions::default());
+        assert!(ranked.is_empty());
+    }
+
+    #[test]
+    fn generic_names_keywords_and_comments_match_too() {
+        let mut entries = entries();
+        for entry in &mut entries {
+            match entry.name.as_str() {
+                "Firefox" => entry.generic_name = "Web Browser".to_string(),
+                "Files" => entry.keywords = vec!["folder".to_string(), "manager".to_string()],
+                "File Roller" => {
+                    entry.comment = "Create and modify an archive of files".to_string()
+                }
+                _ => {}
+            }
+        }
+
+        let ranked = rank_entries(&entries, "browser", &usage(), SearchOptions::default());
+        assert_eq!(names(&ranked), ["Firefox"]);
+        let ranked = rank_entries(&entries, "folder", &usage(), SearchOptions::default());
+        assert_eq!(names(&ranked), ["Files"]);
+
+        // A match in the name outweighs the same match in a comment
+        let ranked = rank_entries(&entries, "files", &usage(), SearchOptions::default());
+        assert_eq!(names(&ranked), ["Files", "File Roller"]);
+
+        let name_only = SearchOptions {
+            weights: FieldWeights {
+                generic_name: 0.0,
+                keywords: 0.0,
+                comment: 0.0,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        assert!(rank_entries(&entries, "browser", &usage(), name_only).is_empty());
+    }
+
+    #[test]
+    fn cached_ranking_matches_a_full_scan_while_typing() {
+        let entries = entries();
+        let usage = usage();
+        let options = SearchOptions::default();
+        let mut cache = RankCache::default();
+
+        // Extending, deleting, excluding and extending an exclusion
+        for query in [
+            "f", "fi", "fir", "fi", "fil", "file", "file ", "file -", "file -r", "file -ro", "t",
+            "te", "ter", "",
+        ] {
+            let cached = rank_entries_cached(&entries, query, &usage, options, &mut cache);
+            let full = rank_entries(&entries, query, &usage, options);
+            assert_eq!(names(&cached), names(&full), "query {:?}", query);
+        }
+    }
+}
@@ -0,0 +1,89 @@
+use relm4::gtk::gio;
+use relm4::gtk::glib::Variant;
+use relm4::gtk::glib::variant::ToVariant;
+
+const LOGIND: &str = "org.freedesktop.login1";
+const MANAGER_PATH: &str = "/org/freedesktop/login1";
+const MANAGER: &str = "org.freedesktop.login1.Manager";
+const SESSION: &str = "org.freedesktop.login1.Session";
+
+/// Call `on_lock` whenever logind asks to lock this session, e.g. through
+/// `loginctl lock-session` or an idle daemon, and before the system suspends.
+/// The bus is reached without waiting for it, failures only show on stderr.
+pub fn watch_lock(on_lock: impl Fn() + Clone + 'static) {
+    gio::bus_get(
+        gio::BusType::System,
+        None::<&gio::Cancellable>,
+        move |connection| {
+            let connection = match connection {
+                Ok(connection) => connection,
+                Err(e) => {
+                    eprintln!("Failed to watch for the session locking: {}", e);
+                    return;
+                }
+            };
+            watch_sleep(&connection, on_lock.clone());
+
+            let lock_connection = connection.clone();
+            own_session(&connection, move |session| {
+                // Without a session of its own, e.g. when started over SSH, any session locking counts
+                let session = session
+                    .inspect_err(|e| eprintln!("Failed to find the login session: {}", e))
+                    .ok();
+
+                #[allow(deprecated)]
+                lock_connection.signal_subscribe(
+                    Some(LOGIND),
+                    Some(SESSION),
+                    Some("Lock"),
+                    session.as_deref(),
+                    None,
+                    gio::DBusSignalFlags::NONE,
+                    move |_, _, _, _, _, _| on_lock(),
+                );
+            });
+        },
+    );
+}
+
+fn watch_sleep(connection: &gio::DBusConnection, on_lock: impl Fn() + 'static) {
+    #[allow(deprecated)]
+    connection.signal_subscribe(
+        Some(LOGIND),
+        Some(MANAGER),
+        Some("PrepareForSleep"),
+        Some(MANAGER_PATH),
+        None,
+        gio::DBusSignalFlags::NONE,
+        move |_, _, _, _, _, parameters: &Variant| {
+            // Sent again with false on resume
+            if parameters
+                .get::<(bool,)>()
+                .is_some_and(|(sleeping,)| sleeping)
+            {
+                on_lock();
+            }
+        },
+    );
+}
+
+/// Look up the object path of the session the launcher runs in
+fn own_session(
+    connection: &gio::DBusConnection,
+    on_found: impl FnOnce(Result<String, gio::glib::Error>) + 'static,
+) {
+    connection.call(
+        Some(LOGIND),
+        MANAGER_PATH,
+        MANAGER,
+        "GetSessionByPID",
+        Some(&(std::process::id(),).to_variant()),
+        None,
+        gio::DBusCallFlags::NONE,
+        -1,
+        None::<&gio::Cancellable>,
+        move |reply| {
+            on_found(reply.map(|reply| reply.child_value(0).str().unwrap_or_default().to_string()))
+        },
+    );
+}
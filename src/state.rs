@@ -0,0 +1,74 @@
+use crate::cli::Mode;
+use crate::error::{LauncherError, Result};
+use crate::profile;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+
+/// What the launcher remembers between runs, kept apart from the user's config
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(default)]
+pub struct State {
+    /// The mode last asked for with --mode, used when none is given
+    pub mode: Option<Mode>,
+    /// Last window size, only used when the window isn't a fixed size layer surface
+    pub width: Option<i32>,
+    pub height: Option<i32>,
+    /// For apps installed both natively and as a Flatpak, the desktop file ID
+    /// of the copy launched last, by app name
+    pub preferred_copies: BTreeMap<String, String>,
+}
+
+impl State {
+    /// The saved state, or the default one when there is none or it can't be read
+    pub fn load() -> Self {
+        let data = match Self::get_storage_path().and_then(|path| Ok(fs::read_to_string(path)?)) {
+            Ok(data) => data,
+            Err(_) => return Self::default(),
+        };
+
+        toml::from_str(&data).unwrap_or_else(|e| {
+            eprintln!("Ignoring unreadable state file: {}", e);
+            Self::default()
+        })
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let path = Self::get_storage_path()?;
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let data = toml::to_string_pretty(self)
+            .map_err(|e| LauncherError::Config(format!("Failed to serialize state: {}", e)))?;
+
+        fs::write(&path, data)?;
+        Ok(())
+    }
+
+    /// Load, change and save the state, unless nothing changed
+    pub fn update(change: impl FnOnce(&mut Self)) {
+        let mut state = Self::load();
+        let previous = state.clone();
+        change(&mut state);
+
+        if state != previous
+            && let Err(e) = state.save()
+        {
+            eprintln!("Failed to save state: {:#}", e);
+        }
+    }
+
+    fn get_storage_path() -> Result<PathBuf> {
+        let state_dir = dirs::state_dir().or_else(dirs::cache_dir).ok_or_else(|| {
+            LauncherError::Io(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                "Could not find state directory",
+            ))
+        })?;
+
+        Ok(state_dir.join(profile::directory()).join("state.toml"))
+    }
+}
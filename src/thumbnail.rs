@@ -0,0 +1,209 @@
+//! Thumbnails of file results, shared with file managers through the XDG
+//! thumbnail cache in ~/.cache/thumbnails
+
+use crate::mime;
+use relm4::gtk::gdk_pixbuf::Pixbuf;
+use relm4::gtk::gio::prelude::*;
+use relm4::gtk::{gio, glib};
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::UNIX_EPOCH;
+
+/// Longest side of the thumbnails made here, the spec's "normal" size
+const SIZE: i32 = 128;
+/// Sizes looked in for a thumbnail made before, a larger one does just as well
+const FLAVORS: &[&str] = &["normal", "large", "x-large", "xx-large"];
+const THUMBNAILER_GROUP: &str = "Thumbnailer Entry";
+
+/// A picture of the file to show in its place: the thumbnail a file manager or an
+/// earlier search made, if the file hasn't changed since, or a new one. Images are
+/// scaled here, other files like videos and PDFs go to the thumbnailers installed
+/// for their type. Small images are their own thumbnail. None when nothing can make one.
+pub fn get(path: &Path) -> Option<PathBuf> {
+    let cache_dir = glib::user_cache_dir().join("thumbnails");
+    // Thumbnails of thumbnails would pile up
+    if path.starts_with(&cache_dir) || !path.is_file() {
+        return None;
+    }
+
+    let uri = gio::File::for_path(path).uri().to_string();
+    let mtime = fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()?
+        .duration_since(UNIX_EPOCH)
+        .ok()?
+        .as_secs()
+        .to_string();
+    let name = format!(
+        "{}.png",
+        glib::compute_checksum_for_string(glib::ChecksumType::Md5, &uri)?
+    );
+
+    if let Some(thumbnail) = FLAVORS
+        .iter()
+        .map(|flavor| cache_dir.join(flavor).join(&name))
+        .find(|thumbnail| is_current(thumbnail, &uri, &mtime))
+    {
+        return Some(thumbnail);
+    }
+
+    let mime = mime::file_type(path)?;
+    let pixbuf = if mime.starts_with("image/") {
+        let (_, width, height) = Pixbuf::file_info(path)?;
+        if width <= SIZE && height <= SIZE {
+            return Some(path.to_path_buf());
+        }
+        Pixbuf::from_file_at_scale(path, SIZE, SIZE, true).ok()?
+    } else {
+        run_thumbnailer(path, &uri, &mime, &name)?
+    };
+
+    let thumbnail = cache_dir.join(FLAVORS[0]).join(&name);
+    match save(&pixbuf, &thumbnail, &uri, &mtime) {
+        Ok(()) => Some(thumbnail),
+        Err(e) => {
+            eprintln!("Failed to save thumbnail of {}: {}", path.display(), e);
+            None
+        }
+    }
+}
+
+/// Whether the thumbnail is of this file as it is now
+fn is_current(thumbnail: &Path, uri: &str, mtime: &str) -> bool {
+    let Ok(pixbuf) = Pixbuf::from_file(thumbnail) else {
+        return false;
+    };
+    pixbuf.option("tEXt::Thumb::URI").as_deref() == Some(uri)
+        && pixbuf.option("tEXt::Thumb::MTime").as_deref() == Some(mtime)
+}
+
+/// Write the thumbnail where other apps look for it, only readable by the user as the
+/// spec asks. It is renamed into place, so no app sees it half written.
+fn save(pixbuf: &Pixbuf, thumbnail: &Path, uri: &str, mtime: &str) -> Result<(), String> {
+    let dir = thumbnail.parent().ok_or("no thumbnail directory")?;
+    fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    fs::set_permissions(dir, fs::Permissions::from_mode(0o700)).map_err(|e| e.to_string())?;
+
+    let partial = thumbnail.with_extension(format!("png.{}", std::process::id()));
+    pixbuf
+        .savev(
+            &partial,
+            "png",
+            &[("tEXt::Thumb::URI", uri), ("tEXt::Thumb::MTime", mtime)],
+        )
+        .map_err(|e| e.to_string())?;
+    fs::set_permissions(&partial, fs::Permissions::from_mode(0o600))
+        .and_then(|()| fs::rename(&partial, thumbnail))
+        .map_err(|e| {
+            let _ = fs::remove_file(&partial);
+            e.to_string()
+        })
+}
+
+/// A picture from the first thumbnailer, as described in share/thumbnailers,
+/// that handles the MIME type and succeeds. `name` keeps files thumbnailed
+/// side by side apart.
+fn run_thumbnailer(path: &Path, uri: &str, mime: &str, name: &str) -> Option<Pixbuf> {
+    let output = std::env::temp_dir().join(format!("adwlauncher-{}-{}", std::process::id(), name));
+
+    let pixbuf = thumbnailers(mime).into_iter().find_map(|exec| {
+        let arguments: Vec<String> = glib::shell_parse_argv(&exec)
+            .ok()?
+            .iter()
+            .map(|argument| expand(&argument.to_string_lossy(), path, uri, &output))
+            .collect();
+        let (program, arguments) = arguments.split_first()?;
+        let status = Command::new(program)
+            .args(arguments)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .ok()?;
+        if !status.success() {
+            return None;
+        }
+        Pixbuf::from_file_at_scale(&output, SIZE, SIZE, true).ok()
+    });
+
+    let _ = fs::remove_file(&output);
+    pixbuf
+}
+
+/// Exec lines of the thumbnailers for a MIME type, the user's own first
+fn thumbnailers(mime: &str) -> Vec<String> {
+    let dirs = std::iter::once(glib::user_data_dir()).chain(glib::system_data_dirs());
+    dirs.filter_map(|dir| fs::read_dir(dir.join("thumbnailers")).ok())
+        .flatten()
+        .flatten()
+        .filter(|file| {
+            file.path()
+                .extension()
+                .is_some_and(|ext| ext == "thumbnailer")
+        })
+        .filter_map(|file| {
+            let key_file = glib::KeyFile::new();
+            key_file
+                .load_from_file(file.path(), glib::KeyFileFlags::NONE)
+                .ok()?;
+            let handles = key_file
+                .string_list(THUMBNAILER_GROUP, "MimeType")
+                .ok()?
+                .iter()
+                .any(|handled| handled.as_str() == mime);
+            let exec = key_file.string(THUMBNAILER_GROUP, "Exec").ok()?;
+            handles.then(|| exec.to_string())
+        })
+        .collect()
+}
+
+/// Fill in a thumbnailer argument: %i is the file, %u its URI, %o where the
+/// thumbnail goes, %s its size and %% a percent sign
+fn expand(argument: &str, input: &Path, uri: &str, output: &Path) -> String {
+    let mut expanded = String::with_capacity(argument.len());
+    let mut chars = argument.chars();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            expanded.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('i') => expanded.push_str(&input.to_string_lossy()),
+            Some('u') => expanded.push_str(uri),
+            Some('o') => expanded.push_str(&output.to_string_lossy()),
+            Some('s') => expanded.push_str(&SIZE.to_string()),
+            Some('%') => expanded.push('%'),
+            Some(other) => {
+                expanded.push('%');
+                expanded.push(other);
+            }
+            None => expanded.push('%'),
+        }
+    }
+    expanded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn thumbnailer_codes_are_filled_in() {
+        let expand = |argument| {
+            expand(
+                argument,
+                Path::new("/home/me/My Video.mkv"),
+                "file:///home/me/My%20Video.mkv",
+                Path::new("/tmp/thumb.png"),
+            )
+        };
+        assert_eq!(expand("%i"), "/home/me/My Video.mkv");
+        assert_eq!(expand("--uri=%u"), "--uri=file:///home/me/My%20Video.mkv");
+        assert_eq!(expand("-o%o"), "-o/tmp/thumb.png");
+        assert_eq!(expand("-s%s"), "-s128");
+        assert_eq!(expand("100%%"), "100%");
+        assert_eq!(expand("%x%"), "%x%");
+    }
+}
@@ -1,11 +1,28 @@
 use serde::{Deserialize, Serialize};
 use std::cmp::PartialEq;
+use std::path::PathBuf;
 
 #[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
 pub enum OpenType {
     Graphical,
     Terminal,
     Window,
+    /// A plain text choice, e.g. a line read in dmenu mode
+    Text,
+    /// A session action like power off or log out
+    Power,
+    /// A MIME type whose default application can be changed
+    MimeType,
+    /// A program started at login, activating it toggles whether it is
+    Autostart,
+    /// A line of the inline help, activating it types its exec into the search
+    Help,
+    /// An environment variable, activating it copies its value from exec
+    Variable,
+    /// A group of apps from the config, activating it lists them
+    Folder,
+    /// The heading of a category section, activating it shows or hides its apps
+    Category,
 }
 
 impl Default for OpenType {
@@ -16,8 +33,35 @@ impl Default for OpenType {
 
 #[derive(Default, Debug, Clone, Serialize, Deserialize)]
 pub struct Entry {
+    /// Desktop file ID for applications, app ID for windows
+    pub id: String,
     pub open_type: OpenType,
     pub exec: String,
     pub icon: String,
     pub name: String,
+    /// The app announces its startup, so it's worth waiting for its window
+    pub startup_notify: bool,
+    /// MIME types the app declares it can open
+    pub mime_types: Vec<String>,
+    /// Main category from the desktop file, e.g. "Development", empty without one
+    pub category: String,
+    /// Search terms from the desktop file, e.g. "Internet;WWW" for a browser
+    pub keywords: Vec<String>,
+    /// What kind of app it is, e.g. "Web Browser"
+    pub generic_name: String,
+    /// The desktop file's tooltip, e.g. "Browse the World Wide Web"
+    pub comment: String,
+    /// Where the app's desktop file was found when scanning
+    pub desktop_file: Option<PathBuf>,
+}
+
+impl Entry {
+    pub fn text(line: &str) -> Self {
+        Self {
+            name: line.to_string(),
+            exec: line.to_string(),
+            open_type: OpenType::Text,
+            ..Default::default()
+        }
+    }
 }
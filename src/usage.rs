@@ -1,4 +1,6 @@
 use crate::error::Result;
+use crate::profile;
+use crate::types::{Entry, OpenType};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::fs;
@@ -25,16 +27,52 @@ impl UsageStats {
     }
 }
 
+/// Recent launches kept, more than are shown so hidden or removed ones leave no gap
+const RECENT_LIMIT: usize = 20;
+
+/// A launch of anything, an app, a file or a command, enough to show and repeat it
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct RecentLaunch {
+    pub id: String,
+    pub name: String,
+    pub exec: String,
+    pub icon: String,
+    pub open_type: OpenType,
+}
+
+impl RecentLaunch {
+    pub fn entry(&self) -> Entry {
+        Entry {
+            id: self.id.clone(),
+            name: self.name.clone(),
+            exec: self.exec.clone(),
+            icon: self.icon.clone(),
+            open_type: self.open_type.clone(),
+            ..Default::default()
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Default)]
 pub struct UsageTracker {
     // Key is the app name, value is usage stats
     stats: HashMap<String, UsageStats>,
+    /// Newest first
+    #[serde(default)]
+    recent: Vec<RecentLaunch>,
+}
+
+/// usage.dat from before recent launches were kept
+#[derive(Deserialize)]
+struct StatsOnly {
+    stats: HashMap<String, UsageStats>,
 }
 
 impl UsageTracker {
     pub fn new() -> Self {
         Self {
             stats: HashMap::new(),
+            recent: vec![],
         }
     }
 
@@ -46,12 +84,25 @@ impl UsageTracker {
         }
 
         let data = fs::read(&path)?;
-        postcard::from_bytes(&data).map_err(|e| {
-            crate::error::LauncherError::Io(std::io::Error::new(
-                std::io::ErrorKind::InvalidData,
-                format!("Failed to deserialize usage data: {}", e),
-            ))
-        })
+        Self::from_bytes(&data)
+    }
+
+    fn from_bytes(data: &[u8]) -> Result<Self> {
+        postcard::from_bytes(data)
+            .or_else(|e| {
+                postcard::from_bytes::<StatsOnly>(data)
+                    .map(|old| Self {
+                        stats: old.stats,
+                        recent: vec![],
+                    })
+                    .map_err(|_| e)
+            })
+            .map_err(|e| {
+                crate::error::LauncherError::Io(std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    format!("Failed to deserialize usage data: {}", e),
+                ))
+            })
     }
 
     pub fn save(&self) -> Result<()> {
@@ -72,6 +123,13 @@ impl UsageTracker {
         Ok(())
     }
 
+    /// Whether any usage data has been written yet
+    pub fn has_data() -> bool {
+        Self::get_storage_path()
+            .map(|path| path.exists())
+            .unwrap_or(false)
+    }
+
     pub fn record_launch(&mut self, app_name: &str) {
         self.stats
             .entry(app_name.to_string())
@@ -83,6 +141,41 @@ impl UsageTracker {
         self.stats.get(app_name)
     }
 
+    /// Names of the most frequently launched apps, most used first
+    pub fn most_used(&self, limit: usize) -> Vec<&str> {
+        let mut stats: Vec<(&String, &UsageStats)> = self.stats.iter().collect();
+        stats.sort_by(|a, b| {
+            b.1.use_count
+                .cmp(&a.1.use_count)
+                .then(b.1.last_used.cmp(&a.1.last_used))
+        });
+
+        stats
+            .into_iter()
+            .take(limit)
+            .map(|(name, _)| name.as_str())
+            .collect()
+    }
+
+    /// Put a launch first among the recent ones, launching it again moves it up
+    pub fn record_recent(&mut self, entry: &Entry) {
+        let launch = RecentLaunch {
+            id: entry.id.clone(),
+            name: entry.name.clone(),
+            exec: entry.exec.clone(),
+            icon: entry.icon.clone(),
+            open_type: entry.open_type.clone(),
+        };
+        self.recent.retain(|recent| *recent != launch);
+        self.recent.insert(0, launch);
+        self.recent.truncate(RECENT_LIMIT);
+    }
+
+    /// The latest launches, newest first
+    pub fn recent(&self) -> &[RecentLaunch] {
+        &self.recent
+    }
+
     /// Calculate a boost score for an app based on usage
     /// Returns a value between 0.0 and 1.0
     pub fn calculate_boost(&self, app_name: &str) -> f64 {
@@ -128,7 +221,21 @@ impl UsageTracker {
             ))
         })?;
 
-        Ok(cache_dir.join("adwlauncher").join("usage.dat"))
+        Ok(cache_dir.join(profile::directory()).join("usage.dat"))
+    }
+}
+
+#[cfg(test)]
+impl UsageTracker {
+    /// Usage as if the app was launched `use_count` times, the last time `age_secs` ago
+    pub fn insert(&mut self, app_name: &str, use_count: u32, age_secs: u64) {
+        self.stats.insert(
+            app_name.to_string(),
+            UsageStats {
+                last_used: current_timestamp().saturating_sub(age_secs),
+                use_count,
+            },
+        );
     }
 }
 
@@ -138,3 +245,44 @@ fn current_timestamp() -> u64 {
         .unwrap_or_default()
         .as_secs()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn launch(id: &str, exec: &str) -> Entry {
+        Entry {
+            id: id.to_string(),
+            name: id.to_string(),
+            exec: exec.to_string(),
+            open_type: OpenType::Terminal,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn launching_again_moves_a_launch_first() {
+        let mut tracker = UsageTracker::new();
+        tracker.record_recent(&launch("history-ls", "ls"));
+        tracker.record_recent(&launch("foot.desktop", "foot"));
+        tracker.record_recent(&launch("history-ls", "ls"));
+
+        let ids: Vec<&str> = tracker.recent().iter().map(|r| r.id.as_str()).collect();
+        assert_eq!(ids, ["history-ls", "foot.desktop"]);
+    }
+
+    #[test]
+    fn usage_data_from_before_recent_launches_still_loads() {
+        let mut stats = HashMap::new();
+        stats.insert("Firefox".to_string(), UsageStats::new());
+        #[derive(Serialize)]
+        struct Old {
+            stats: HashMap<String, UsageStats>,
+        }
+        let data = postcard::to_allocvec(&Old { stats }).unwrap();
+
+        let tracker = UsageTracker::from_bytes(&data).unwrap();
+        assert_eq!(tracker.get_stats("Firefox").unwrap().use_count, 1);
+        assert!(tracker.recent().is_empty());
+    }
+}
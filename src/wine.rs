@@ -0,0 +1,386 @@
+use crate::app_discovery;
+use crate::error::{LauncherError, Result};
+use crate::exec::quote_argument;
+use crate::icon;
+use crate::types::{Entry, OpenType};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Start menus nest a few folders deep at most, this keeps a symlink loop from running away
+const MAX_DEPTH: usize = 6;
+const BOTTLES_FLATPAK_ID: &str = "com.usebottles.bottles";
+
+/// The home directory, below which Wine and the game launchers keep everything
+pub fn home() -> Result<PathBuf> {
+    dirs::home_dir().ok_or_else(|| {
+        LauncherError::Io(std::io::Error::new(
+            std::io::ErrorKind::NotFound,
+            "Could not find home directory",
+        ))
+    })
+}
+
+/// Windows programs from Wine's own menu entries, Bottles, Lutris and the start
+/// menus of common prefixes, the first source naming a program wins
+pub fn get_wine_entries() -> Result<Vec<Entry>> {
+    let home = home()?;
+    let mut entries = menu_entries(&home);
+    entries.extend(bottles_entries(&home));
+    entries.extend(lutris_entries(&home));
+    entries.extend(start_menu_entries(&home));
+
+    let mut seen = HashSet::new();
+    entries.retain(|entry| seen.insert(entry.name.to_lowercase()));
+    Ok(entries)
+}
+
+/// Directories whose changes mean the Windows programs need to be scanned again
+pub fn watched_directories() -> Result<Vec<PathBuf>> {
+    let home = home()?;
+    let mut dirs = vec![menu_directory(&home)];
+    dirs.extend(bottles_directories(&home));
+    dirs.extend(lutris_directories(&home));
+    dirs.extend(
+        wine_prefixes(&home)
+            .into_iter()
+            .flat_map(|prefix| start_menus(&prefix)),
+    );
+    Ok(dirs)
+}
+
+/// winemenubuilder writes desktop files into a tree below the user's applications
+fn menu_directory(home: &Path) -> PathBuf {
+    home.join(".local/share/applications/wine")
+}
+
+fn menu_entries(home: &Path) -> Vec<Entry> {
+    let root = menu_directory(home);
+
+    find_files(&root, "desktop")
+        .into_iter()
+        .filter_map(|path| {
+            let mut entry = app_discovery::parse_desktop_entry(&path).ok()?;
+            // Desktop file IDs of nested files include their directories
+            entry.id = format!(
+                "wine-{}",
+                path.strip_prefix(&root)
+                    .ok()?
+                    .to_string_lossy()
+                    .replace('/', "-")
+            );
+            Some(entry)
+        })
+        .collect()
+}
+
+fn bottles_directories(home: &Path) -> Vec<PathBuf> {
+    vec![
+        home.join(".local/share/bottles/bottles"),
+        home.join(format!(
+            ".var/app/{}/data/bottles/bottles",
+            BOTTLES_FLATPAK_ID
+        )),
+    ]
+}
+
+/// Programs added to a bottle, run through bottles-cli of the same installation
+fn bottles_entries(home: &Path) -> Vec<Entry> {
+    let mut entries = vec![];
+
+    for (index, root) in bottles_directories(home).iter().enumerate() {
+        let cli = if index == 0 {
+            "bottles-cli".to_string()
+        } else {
+            format!("flatpak run --command=bottles-cli {}", BOTTLES_FLATPAK_ID)
+        };
+
+        let Ok(bottles) = fs::read_dir(root) else {
+            continue;
+        };
+        for bottle_dir in bottles
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+        {
+            let Ok(config) = fs::read_to_string(bottle_dir.join("bottle.yml")) else {
+                continue;
+            };
+            let bottle = top_level_value(&config, "Name").unwrap_or_else(|| {
+                bottle_dir
+                    .file_name()
+                    .map(|name| name.to_string_lossy().to_string())
+                    .unwrap_or_default()
+            });
+
+            for program in external_programs(&config) {
+                let icon = find_bottle_icon(&bottle_dir, &program)
+                    .unwrap_or_else(|| BOTTLES_FLATPAK_ID.to_string());
+
+                entries.push(Entry {
+                    id: format!("bottles-{}-{}", bottle, program),
+                    name: program.clone(),
+                    exec: format!(
+                        "{} run -b {} -p {}",
+                        cli,
+                        quote_argument(&bottle),
+                        quote_argument(&program)
+                    ),
+                    icon,
+                    open_type: OpenType::Graphical,
+                    ..Default::default()
+                });
+            }
+        }
+    }
+
+    entries
+}
+
+/// Bottles keeps the icons it extracted from program executables next to the bottle
+fn find_bottle_icon(bottle_dir: &Path, program: &str) -> Option<String> {
+    ["png", "svg", "ico"]
+        .iter()
+        .map(|ext| {
+            bottle_dir
+                .join("icons")
+                .join(format!("{}.{}", program, ext))
+        })
+        .find(|path| path.exists())
+        .and_then(|path| path.to_str().map(String::from))
+}
+
+/// Names from the `External_Programs` mapping of a bottle.yml
+fn external_programs(config: &str) -> Vec<String> {
+    let mut programs = vec![];
+    let mut in_programs = false;
+
+    for line in config.lines() {
+        let indent = line.len() - line.trim_start().len();
+        if indent == 0 && !line.trim().is_empty() {
+            in_programs = line.trim_end() == "External_Programs:";
+            continue;
+        }
+
+        if in_programs && let Some(name) = line.trim().strip_prefix("name:") {
+            let name = unquote(name);
+            if !name.is_empty() {
+                programs.push(name);
+            }
+        }
+    }
+
+    programs
+}
+
+fn lutris_directories(home: &Path) -> Vec<PathBuf> {
+    vec![
+        home.join(".local/share/lutris/games"),
+        home.join(".config/lutris/games"),
+    ]
+}
+
+/// Lutris games that run through Wine, each launched through Lutris with its own settings
+fn lutris_entries(home: &Path) -> Vec<Entry> {
+    let mut entries = vec![];
+
+    for dir in lutris_directories(home) {
+        let Ok(read_dir) = fs::read_dir(&dir) else {
+            continue;
+        };
+        for path in read_dir
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+        {
+            if path.extension().is_none_or(|ext| ext != "yml") {
+                continue;
+            }
+            let Ok(config) = fs::read_to_string(&path) else {
+                continue;
+            };
+            let is_wine = config
+                .lines()
+                .any(|line| line == "wine:" || line.trim_start().starts_with("prefix:"));
+            let Some(slug) = path
+                .file_stem()
+                .map(|stem| lutris_slug(&stem.to_string_lossy()))
+            else {
+                continue;
+            };
+            if !is_wine || slug.is_empty() {
+                continue;
+            }
+
+            // Lutris saves the icons it downloads into the user's hicolor theme
+            let icon = icon::resolve_icon_path(&format!("lutris_{}", slug))
+                .filter(|icon| icon.starts_with('/'))
+                .unwrap_or_else(|| "lutris".to_string());
+
+            entries.push(Entry {
+                id: format!("lutris-{}", slug),
+                name: title_from_slug(&slug),
+                exec: format!(
+                    "lutris {}",
+                    quote_argument(&format!("lutris:rungame/{}", slug))
+                ),
+                icon,
+                open_type: OpenType::Graphical,
+                ..Default::default()
+            });
+        }
+    }
+
+    entries
+}
+
+/// Game configs are named after the slug and a timestamp, e.g. `the-witcher-3-1712345678`
+fn lutris_slug(stem: &str) -> String {
+    match stem.rsplit_once('-') {
+        Some((slug, timestamp)) if timestamp.chars().all(|c| c.is_ascii_digit()) => {
+            slug.to_string()
+        }
+        _ => stem.to_string(),
+    }
+}
+
+fn title_from_slug(slug: &str) -> String {
+    slug.split('-')
+        .filter(|word| !word.is_empty())
+        .map(|word| {
+            let mut chars = word.chars();
+            chars
+                .next()
+                .map(|first| first.to_uppercase().chain(chars).collect())
+                .unwrap_or_default()
+        })
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+/// `~/.wine`, `$WINEPREFIX` and prefixes managed with winetricks
+fn wine_prefixes(home: &Path) -> Vec<PathBuf> {
+    let mut prefixes = vec![home.join(".wine")];
+
+    if let Some(prefix) = std::env::var_os("WINEPREFIX") {
+        prefixes.push(PathBuf::from(prefix));
+    }
+
+    if let Ok(read_dir) = fs::read_dir(home.join(".local/share/wineprefixes")) {
+        prefixes.extend(
+            read_dir
+                .filter_map(|entry| entry.ok())
+                .map(|entry| entry.path()),
+        );
+    }
+
+    prefixes.dedup();
+    prefixes
+        .into_iter()
+        .filter(|prefix| prefix.join("drive_c").is_dir())
+        .collect()
+}
+
+fn start_menus(prefix: &Path) -> Vec<PathBuf> {
+    let drive = prefix.join("drive_c");
+    let mut menus = vec![drive.join("ProgramData/Microsoft/Windows/Start Menu/Programs")];
+
+    if let Ok(users) = fs::read_dir(drive.join("users")) {
+        menus.extend(users.filter_map(|entry| entry.ok()).map(|entry| {
+            entry
+                .path()
+                .join("AppData/Roaming/Microsoft/Windows/Start Menu/Programs")
+        }));
+    }
+
+    menus
+}
+
+/// Start menu shortcuts of prefixes that winemenubuilder never exported
+fn start_menu_entries(home: &Path) -> Vec<Entry> {
+    let mut entries = vec![];
+
+    for prefix in wine_prefixes(home) {
+        for path in start_menus(&prefix)
+            .into_iter()
+            .flat_map(|menu| find_files(&menu, "lnk"))
+        {
+            let Some(name) = path
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().to_string())
+            else {
+                continue;
+            };
+            // Every installer leaves one of these behind
+            if name.to_lowercase().starts_with("uninstall") {
+                continue;
+            }
+
+            entries.push(Entry {
+                id: format!("wine-{}", path.display()),
+                name,
+                exec: format!(
+                    "env {} wine start /unix {}",
+                    quote_argument(&format!("WINEPREFIX={}", prefix.to_string_lossy())),
+                    quote_argument(&path.to_string_lossy())
+                ),
+                icon: "wine".to_string(),
+                open_type: OpenType::Graphical,
+                ..Default::default()
+            });
+        }
+    }
+
+    entries
+}
+
+/// Files with the given extension anywhere below `dir`
+fn find_files(dir: &Path, extension: &str) -> Vec<PathBuf> {
+    let mut files = vec![];
+    collect_files(dir, extension, 0, &mut files);
+    files
+}
+
+fn collect_files(dir: &Path, extension: &str, depth: usize, files: &mut Vec<PathBuf>) {
+    if depth > MAX_DEPTH {
+        return;
+    }
+    let Ok(read_dir) = fs::read_dir(dir) else {
+        return;
+    };
+
+    for path in read_dir
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+    {
+        if path.is_dir() {
+            collect_files(&path, extension, depth + 1, files);
+        } else if path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case(extension))
+        {
+            files.push(path);
+        }
+    }
+}
+
+/// A scalar at the top level of a YAML file, e.g. `Name: Gaming`
+fn top_level_value(config: &str, key: &str) -> Option<String> {
+    config
+        .lines()
+        .find_map(|line| line.strip_prefix(key)?.strip_prefix(':'))
+        .map(unquote)
+        .filter(|value| !value.is_empty())
+}
+
+/// A YAML scalar without its quotes
+fn unquote(value: &str) -> String {
+    let value = value.trim();
+    for quote in ['\'', '"'] {
+        if let Some(inner) = value
+            .strip_prefix(quote)
+            .and_then(|value| value.strip_suffix(quote))
+        {
+            return inner.to_string();
+        }
+    }
+    value.to_string()
+}